@@ -1,4 +1,5 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use pint::blocks::BlockMap;
 use pint::navigation::*;
 use pint::{decoder::*, types::*};
 use std::fs::File;
@@ -25,9 +26,45 @@ fn get_block_white_color_bench(b: &mut Criterion) {
     });
 }
 
+fn label_blocks_bench(b: &mut Criterion) {
+    let mut file = File::open("tests/fixtures/pi_big.png").unwrap();
+    check_valid_png(&mut file);
+    let rgb_img = black_box(decode_png(file));
+
+    b.bench_function("label_blocks", |b| b.iter(|| BlockMap::new(&rgb_img, 1)));
+}
+
+fn next_color_bench(b: &mut Criterion) {
+    let mut file = File::open("tests/fixtures/piet_hello_world.png").unwrap();
+    check_valid_png(&mut file);
+    let rgb_img = black_box(decode_png(file));
+    let map = BlockMap::new(&rgb_img, 5);
+
+    b.bench_function("next_color_flood_fill", |b| {
+        b.iter(|| {
+            let (mut dp, mut cc) = (Direction::RIGHT, CodelChooser::LEFT);
+            next_color(
+                &rgb_img,
+                &mut Coordinates { x: 0, y: 0 },
+                5,
+                &mut dp,
+                &mut cc,
+            )
+        })
+    });
+    b.bench_function("next_color_lookup", |b| {
+        b.iter(|| {
+            let (mut dp, mut cc) = (Direction::RIGHT, CodelChooser::LEFT);
+            map.next_color(&mut Coordinates { x: 0, y: 0 }, &mut dp, &mut cc)
+        })
+    });
+}
+
 criterion_group!(
     benches,
     get_block_normal_color_bench,
-    get_block_white_color_bench
+    get_block_white_color_bench,
+    label_blocks_bench,
+    next_color_bench
 );
 criterion_main!(benches);
@@ -0,0 +1,273 @@
+use crate::gif::Palette;
+use crate::machine::*;
+use crate::types::*;
+
+mod tests;
+
+// glyphs are 3x5 pixels, drawn twice as big with a gap of 2 pixels
+const ZOOM: usize = 2;
+const CHAR_WIDTH: usize = 4 * ZOOM;
+const LINE_HEIGHT: usize = 6 * ZOOM;
+const MARGIN: usize = 2;
+// frames are at least this many characters wide, so there is room for the stack
+const MIN_COLUMNS: usize = 32;
+
+// rows from the top, the lowest 3 bits from left to right
+const GLYPHS: [(char, [u8; 5]); 46] = [
+    ('A', [0b010, 0b101, 0b111, 0b101, 0b101]),
+    ('B', [0b110, 0b101, 0b110, 0b101, 0b110]),
+    ('C', [0b011, 0b100, 0b100, 0b100, 0b011]),
+    ('D', [0b110, 0b101, 0b101, 0b101, 0b110]),
+    ('E', [0b111, 0b100, 0b110, 0b100, 0b111]),
+    ('F', [0b111, 0b100, 0b110, 0b100, 0b100]),
+    ('G', [0b011, 0b100, 0b101, 0b101, 0b011]),
+    ('H', [0b101, 0b101, 0b111, 0b101, 0b101]),
+    ('I', [0b111, 0b010, 0b010, 0b010, 0b111]),
+    ('J', [0b001, 0b001, 0b001, 0b101, 0b010]),
+    ('K', [0b101, 0b101, 0b110, 0b101, 0b101]),
+    ('L', [0b100, 0b100, 0b100, 0b100, 0b111]),
+    ('M', [0b101, 0b111, 0b111, 0b101, 0b101]),
+    ('N', [0b110, 0b101, 0b101, 0b101, 0b101]),
+    ('O', [0b010, 0b101, 0b101, 0b101, 0b010]),
+    ('P', [0b110, 0b101, 0b110, 0b100, 0b100]),
+    ('Q', [0b010, 0b101, 0b101, 0b110, 0b011]),
+    ('R', [0b110, 0b101, 0b110, 0b101, 0b101]),
+    ('S', [0b011, 0b100, 0b010, 0b001, 0b110]),
+    ('T', [0b111, 0b010, 0b010, 0b010, 0b010]),
+    ('U', [0b101, 0b101, 0b101, 0b101, 0b111]),
+    ('V', [0b101, 0b101, 0b101, 0b101, 0b010]),
+    ('W', [0b101, 0b101, 0b111, 0b111, 0b101]),
+    ('X', [0b101, 0b101, 0b010, 0b101, 0b101]),
+    ('Y', [0b101, 0b101, 0b010, 0b010, 0b010]),
+    ('Z', [0b111, 0b001, 0b010, 0b100, 0b111]),
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b110, 0b001, 0b010, 0b100, 0b111]),
+    ('3', [0b110, 0b001, 0b010, 0b001, 0b110]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b110, 0b001, 0b110]),
+    ('6', [0b011, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b010, 0b010, 0b010]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b110]),
+    ('-', [0b000, 0b000, 0b111, 0b000, 0b000]),
+    ('=', [0b000, 0b111, 0b000, 0b111, 0b000]),
+    ('_', [0b000, 0b000, 0b000, 0b000, 0b111]),
+    ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+    (',', [0b000, 0b000, 0b000, 0b010, 0b100]),
+    (':', [0b000, 0b010, 0b000, 0b010, 0b000]),
+    ('[', [0b110, 0b100, 0b100, 0b100, 0b110]),
+    (']', [0b011, 0b001, 0b001, 0b001, 0b011]),
+    (' ', [0b000, 0b000, 0b000, 0b000, 0b000]),
+    ('?', [0b110, 0b001, 0b010, 0b000, 0b010]),
+];
+
+fn glyph(c: char) -> [u8; 5] {
+    let c = c.to_ascii_uppercase();
+    GLYPHS
+        .iter()
+        .find(|(g, _)| *g == c)
+        .or_else(|| GLYPHS.last())
+        .unwrap()
+        .1
+}
+
+// black on light colors, white on dark ones
+fn contrast(RGB(r, g, b): RGB) -> RGB {
+    if 299 * r as u32 + 587 * g as u32 + 114 * b as u32 > 128_000 {
+        BLACK
+    } else {
+        WHITE
+    }
+}
+
+/// Draws the frames of an animated execution: the program with `scale` pixels per codel, the
+/// current block outlined and an arrow for DP in the codel the block is left from (which depends
+/// on CC), above two lines of text with the step, command, DP, CC and the stack.
+pub struct Renderer {
+    pub scale: usize,
+    /// Size of the frames in pixels.
+    pub width: usize,
+    pub height: usize,
+    /// The program without anything drawn on top.
+    background: Vec<Vec<RGB>>,
+}
+
+impl Renderer {
+    pub fn new(machine: &Machine, scale: usize) -> Renderer {
+        let blocks = &machine.blocks;
+        let (program_width, program_height) = (
+            blocks.width as usize * scale,
+            blocks.height as usize * scale,
+        );
+        let (width, height) = Renderer::frame_size(machine, scale);
+
+        let mut background = vec![vec![BLACK; width]; height];
+        for (y, row) in background.iter_mut().take(program_height).enumerate() {
+            for (x, pixel) in row.iter_mut().take(program_width).enumerate() {
+                let codel = Coordinates {
+                    x: (x / scale) as i32,
+                    y: (y / scale) as i32,
+                };
+                *pixel = blocks.color(codel).unwrap();
+            }
+        }
+        Renderer {
+            scale,
+            width,
+            height,
+            background,
+        }
+    }
+
+    /// Size of the frames in pixels, without drawing anything.
+    pub fn frame_size(machine: &Machine, scale: usize) -> (usize, usize) {
+        let (program_width, program_height) = (
+            machine.blocks.width as usize * scale,
+            machine.blocks.height as usize * scale,
+        );
+        (
+            program_width.max(MIN_COLUMNS * CHAR_WIDTH + 2 * MARGIN),
+            program_height + 2 * LINE_HEIGHT + 2 * MARGIN,
+        )
+    }
+
+    /// The colors the frames are drawn with: black and white for what is drawn on top and the
+    /// colors of the program.
+    pub fn palette(&self) -> Palette {
+        Palette::new(
+            [BLACK, WHITE]
+                .iter()
+                .copied()
+                .chain(self.background.iter().flatten().copied()),
+        )
+    }
+
+    /// The frame after `step`, `None` for the frame before the first step.
+    pub fn frame(&self, machine: &Machine, step: Option<&Step>) -> Vec<Vec<RGB>> {
+        let mut frame = self.background.clone();
+        let blocks = &machine.blocks;
+        let codel = machine.codel();
+        let block = blocks.block_id(codel);
+        let inside = |c: Coordinates| match block {
+            Some(id) => blocks.block_id(c) == Some(id),
+            None => c == codel,
+        };
+        let codels = match blocks.block(codel) {
+            Some(block) => block.codels.clone(),
+            None => vec![codel],
+        };
+
+        // an outline of black outside and white inside on the edges of the block
+        let thickness = (self.scale / 8).max(1);
+        let directions = [
+            Direction::RIGHT,
+            Direction::DOWN,
+            Direction::LEFT,
+            Direction::UP,
+        ];
+        for c in &codels {
+            for dp in directions {
+                let next = Coordinates {
+                    x: c.x + dp.cords().x,
+                    y: c.y + dp.cords().y,
+                };
+                if inside(next) {
+                    continue;
+                }
+                for (band, color) in [BLACK, WHITE].iter().enumerate() {
+                    for along in 0..self.scale {
+                        for depth in band * thickness..((band + 1) * thickness).min(self.scale) {
+                            let (x, y) = edge_pixel(dp, along, depth, self.scale);
+                            frame[c.y as usize * self.scale + y][c.x as usize * self.scale + x] =
+                                *color;
+                        }
+                    }
+                }
+            }
+        }
+
+        // a triangle pointing in direction of DP
+        let (dp, cc) = (machine.interp.dp, machine.interp.cc);
+        let exit = blocks
+            .block(codel)
+            .map_or(codel, |block| block.exit(dp, cc));
+        if self.scale >= 3 {
+            let color = contrast(blocks.color(exit).unwrap());
+            let s = self.scale as f64;
+            for y in 0..self.scale {
+                for x in 0..self.scale {
+                    // how far ahead in direction of DP and how far off the middle, 0 to 1
+                    let (px, py) = ((x as f64 + 0.5) / s, (y as f64 + 0.5) / s);
+                    let (ahead, across) = match dp {
+                        Direction::RIGHT => (px, py),
+                        Direction::LEFT => (1.0 - px, py),
+                        Direction::DOWN => (py, px),
+                        Direction::UP => (1.0 - py, px),
+                    };
+                    if (0.25..=0.75).contains(&ahead) && (across - 0.5).abs() <= (0.75 - ahead) {
+                        frame[exit.y as usize * self.scale + y][exit.x as usize * self.scale + x] =
+                            color;
+                    }
+                }
+            }
+        }
+
+        let command = step.map_or("start", |step| step.command.name());
+        let status = format!("step {} {} dp={:?} cc={:?}", machine.steps, command, dp, cc);
+        let columns = (self.width - 2 * MARGIN) / CHAR_WIDTH;
+        let top = self.height - 2 * LINE_HEIGHT - MARGIN;
+        self.text(&mut frame, &status, top);
+        self.text(&mut frame, &stack_line(machine, columns), top + LINE_HEIGHT);
+        frame
+    }
+
+    fn text(&self, frame: &mut [Vec<RGB>], text: &str, top: usize) {
+        for (i, c) in text.chars().enumerate() {
+            let left = MARGIN + i * CHAR_WIDTH;
+            if left + CHAR_WIDTH > self.width {
+                break;
+            }
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for column in 0..3 {
+                    if bits & (0b100 >> column) == 0 {
+                        continue;
+                    }
+                    for dy in 0..ZOOM {
+                        for dx in 0..ZOOM {
+                            frame[top + row * ZOOM + dy][left + column * ZOOM + dx] = WHITE;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// the pixel of a codel `depth` pixels inside its edge in direction of `dp`
+fn edge_pixel(dp: Direction, along: usize, depth: usize, scale: usize) -> (usize, usize) {
+    match dp {
+        Direction::RIGHT => (scale - 1 - depth, along),
+        Direction::LEFT => (depth, along),
+        Direction::DOWN => (along, scale - 1 - depth),
+        Direction::UP => (along, depth),
+    }
+}
+
+// the stack like `trace_line` shows it, the bottom left out if it doesn't fit
+fn stack_line(machine: &Machine, columns: usize) -> String {
+    let values: Vec<String> = machine.interp.stack.iter().map(|n| n.to_string()).collect();
+    let line = format!("stack=[{}]", values.join(", "));
+    if line.chars().count() <= columns {
+        return line;
+    }
+    let mut shown = values.len();
+    while shown > 0 {
+        let line = format!("stack=[..., {}]", values[values.len() - shown..].join(", "));
+        if line.chars().count() <= columns {
+            return line;
+        }
+        shown -= 1;
+    }
+    String::from("stack=[...]")
+}
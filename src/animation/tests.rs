@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod tests {
+    use crate::animation::*;
+    use crate::test_support::hello_world;
+
+    #[test]
+    fn frames_leave_room_for_text() {
+        let machine = hello_world();
+        let renderer = Renderer::new(&machine, 10);
+        let frame = renderer.frame(&machine, None);
+
+        assert_eq!(renderer.width, machine.blocks.width as usize * 10);
+        assert_eq!(frame.len(), renderer.height);
+        assert_eq!(frame[0].len(), renderer.width);
+        assert!(renderer.height > machine.blocks.height as usize * 10 + 2 * LINE_HEIGHT);
+
+        let small = Renderer::new(&machine, 1);
+        assert_eq!(small.width, MIN_COLUMNS * CHAR_WIDTH + 2 * MARGIN);
+    }
+
+    #[test]
+    fn outlines_the_current_block() {
+        let mut machine = hello_world();
+        let renderer = Renderer::new(&machine, 8);
+        let red = RGB(255, 0, 0);
+        let frame = renderer.frame(&machine, None);
+        // the top left corner of the red block, its middle isn't drawn over
+        assert_eq!(frame[0][0], BLACK);
+        assert_eq!(frame[1][1], WHITE);
+        assert_eq!(frame[2][2], red);
+        assert_eq!(frame[8 + 4][8 + 4], red);
+
+        let step = machine.step().unwrap().unwrap();
+        let frame = renderer.frame(&machine, Some(&step));
+        assert_eq!(frame[0][0], red);
+        // the next block is the single dark red codel at (11, 0)
+        assert_eq!(frame[0][11 * 8], BLACK);
+    }
+
+    #[test]
+    fn points_in_direction_of_dp() {
+        let machine = hello_world();
+        let renderer = Renderer::new(&machine, 8);
+        let frame = renderer.frame(&machine, None);
+        // dp right, cc left: the red block is left from the top right codel
+        let exit = machine
+            .blocks
+            .block(machine.codel())
+            .unwrap()
+            .exit(Direction::RIGHT, CodelChooser::LEFT);
+        let (x, y) = (exit.x as usize * 8, exit.y as usize * 8);
+        // red is dark enough for a white arrow, the codel sticks out so it is outlined but on the left
+        assert_eq!(frame[y + 4][x + 3], WHITE);
+        assert_eq!(frame[y + 5][x + 2], WHITE);
+        assert_eq!(frame[y + 4][x], RGB(255, 0, 0));
+        assert_eq!(frame[y][x + 1], BLACK);
+        assert_eq!(frame[y + 1][x + 1], WHITE);
+    }
+
+    #[test]
+    fn shows_the_top_of_the_stack() {
+        let mut machine = hello_world();
+        machine.interp.stack = (1..=5).map(Into::into).collect();
+        assert_eq!(stack_line(&machine, 32), "stack=[1, 2, 3, 4, 5]");
+        assert_eq!(stack_line(&machine, 19), "stack=[..., 4, 5]");
+        assert_eq!(stack_line(&machine, 5), "stack=[...]");
+    }
+}
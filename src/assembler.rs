@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+use std::num::{IntErrorKind, ParseIntError};
+
+use crate::interpreter::*;
+use crate::types::*;
+
+mod tests;
+
+// color of the first block of code and of every turn, any of the 18 colors would do
+const FIRST: RGB = RGB(255, 192, 192);
+// the space between the columns jumps are routed through
+const COLUMN_SPACING: i32 = 6;
+// pushing more than this is done with arithmetic, since pushing n needs a block of n codels
+const MAX_PUSH: i32 = 12;
+
+/// How a basic block ends, targets are indices of basic blocks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum End {
+    Halt,
+    Jump(usize),
+    /// `taken` if the value popped by `pointer` is 1, `otherwise` if it is 0.
+    Branch {
+        taken: usize,
+        otherwise: usize,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct BasicBlock {
+    /// Commands and the size of the block they are executed from, which is pushed by `push`.
+    code: Vec<(Command, i32)>,
+    end: End,
+}
+
+// an `End` before the labels are resolved
+enum Pending {
+    Open,
+    Halt,
+    Jump(String, usize),
+    Branch(String, usize),
+}
+
+// blocks can only push positive numbers, large ones are pushed as n = a * a + rest
+fn push_number(n: i32, code: &mut Vec<(Command, i32)>) {
+    match n {
+        1..=MAX_PUSH => code.push((Command::Push, n)),
+        0 => code.extend([(Command::Push, 1), (Command::Not, 1)]),
+        n if n > 0 => {
+            let a = (1..)
+                .take_while(|a: &i64| a * a <= n as i64)
+                .last()
+                .unwrap() as i32;
+            push_number(a, code);
+            code.extend([(Command::Dup, 1), (Command::Mult, 1)]);
+            if n > a * a {
+                push_number(n - a * a, code);
+                code.push((Command::Add, 1));
+            }
+        }
+        // as 1 - (1 - n), or as (n + 1) - 1 where 1 - n doesn't fit
+        n => match 1i32.checked_sub(n) {
+            Some(m) => {
+                code.push((Command::Push, 1));
+                push_number(m, code);
+                code.push((Command::Sub, 1));
+            }
+            None => {
+                push_number(n + 1, code);
+                code.extend([(Command::Push, 1), (Command::Sub, 1)]);
+            }
+        },
+    }
+}
+
+fn parse(source: &str) -> Result<Vec<BasicBlock>, String> {
+    let mut labels = HashMap::new();
+    let mut blocks: Vec<(Vec<(Command, i32)>, Pending)> = vec![(Vec::new(), Pending::Open)];
+
+    for (i, line) in source.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line.split([';', '#']).next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |msg: String| format!("line {}: {}", line_no, msg);
+
+        if let Some(label) = line.strip_suffix(':') {
+            let label = label.trim();
+            if label.is_empty() || label.contains(char::is_whitespace) {
+                return Err(error(format!("invalid label \"{}\"", label)));
+            }
+            // a label starts a new block unless the current one is still empty
+            let (code, end) = blocks.last_mut().unwrap();
+            if !code.is_empty() || !matches!(end, Pending::Open) {
+                if let Pending::Open = end {
+                    *end = Pending::Jump(label.to_string(), line_no);
+                }
+                blocks.push((Vec::new(), Pending::Open));
+            }
+            if labels.insert(label.to_string(), blocks.len() - 1).is_some() {
+                return Err(error(format!("label \"{}\" is defined twice", label)));
+            }
+            continue;
+        }
+
+        let mut words = line.split_whitespace();
+        let op = words.next().unwrap();
+        let arg = words.next();
+        let takes_arg = ["push", "jmp", "jz", "jnz"].contains(&op);
+        match (takes_arg, arg, words.next()) {
+            (_, _, Some(_)) => return Err(error(format!("too many arguments for {}", op))),
+            (true, None, _) => return Err(error(format!("{} needs an argument", op))),
+            (false, Some(_), _) => return Err(error(format!("{} doesn't take an argument", op))),
+            _ => (),
+        }
+        if !matches!(blocks.last().unwrap().1, Pending::Open) {
+            // code after a jump, only reachable through a label
+            blocks.push((Vec::new(), Pending::Open));
+        }
+        let (code, end) = blocks.last_mut().unwrap();
+
+        match op {
+            "push" => {
+                let n = arg.unwrap();
+                let n: i32 = n.parse().map_err(|e: ParseIntError| match e.kind() {
+                    IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => error(format!(
+                        "{} is out of range, push takes numbers from {} to {}",
+                        n,
+                        i32::MIN,
+                        i32::MAX
+                    )),
+                    _ => error(format!("\"{}\" is not a number", n)),
+                })?;
+                push_number(n, code);
+            }
+            "jmp" => *end = Pending::Jump(arg.unwrap().to_string(), line_no),
+            "jz" | "jnz" => {
+                code.push((Command::Not, 1));
+                if op == "jnz" {
+                    code.push((Command::Not, 1));
+                }
+                code.push((Command::Pointer, 1));
+                *end = Pending::Branch(arg.unwrap().to_string(), line_no);
+                blocks.push((Vec::new(), Pending::Open));
+            }
+            "halt" => *end = Pending::Halt,
+            "pointer" | "switch" => {
+                return Err(error(format!(
+                    "{} can't be used directly, use jmp, jz or jnz instead",
+                    op
+                )))
+            }
+            _ => match Command::from_name(op) {
+                Some(command) if command != Command::None => code.push((command, 1)),
+                _ => return Err(error(format!("unknown instruction \"{}\"", op))),
+            },
+        }
+    }
+
+    let resolve = |label: &str, line_no: usize| {
+        labels
+            .get(label)
+            .copied()
+            .ok_or_else(|| format!("line {}: unknown label \"{}\"", line_no, label))
+    };
+    blocks
+        .into_iter()
+        .enumerate()
+        .map(|(i, (code, end))| {
+            let end = match end {
+                Pending::Open | Pending::Halt => End::Halt,
+                Pending::Jump(label, line_no) => End::Jump(resolve(&label, line_no)?),
+                Pending::Branch(label, line_no) => End::Branch {
+                    taken: resolve(&label, line_no)?,
+                    otherwise: i + 1,
+                },
+            };
+            Ok(BasicBlock { code, end })
+        })
+        .collect()
+}
+
+struct Canvas {
+    grid: Vec<Vec<RGB>>,
+    /// Rows of the line every basic block is entered on.
+    entries: Vec<i32>,
+    /// Columns jumps to every basic block are routed through.
+    columns: Vec<i32>,
+}
+
+impl Canvas {
+    fn set(&mut self, x: i32, y: i32, color: RGB) {
+        self.grid[y as usize][x as usize] = color;
+    }
+
+    fn white(&mut self, y: i32, from: i32, to: i32) {
+        for x in from..=to {
+            self.set(x, y, WHITE);
+        }
+    }
+
+    // `push` the size of `a` and turn with `pointer` into `c`
+    fn turn(&mut self, a: &[(i32, i32)], b: (i32, i32), c: (i32, i32)) {
+        let push = encode_command(FIRST, Command::Push);
+        for (x, y) in a {
+            self.set(*x, *y, FIRST);
+        }
+        self.set(b.0, b.1, push);
+        self.set(c.0, c.1, encode_command(push, Command::Pointer));
+    }
+
+    // goes right on row `y` from `x` and turns into the column of `target`, towards its entry
+    fn jump(&mut self, y: i32, x: i32, target: usize) {
+        let column = self.columns[target];
+        if self.entries[target] > y {
+            // one clockwise turn goes down
+            self.white(y, x, column - 3);
+            self.turn(&[(column - 2, y)], (column - 1, y), (column, y));
+        } else {
+            // three go up
+            self.white(y, x, column - 5);
+            let a = [(column - 4, y), (column - 3, y), (column - 2, y)];
+            self.turn(&a, (column - 1, y), (column, y));
+        }
+    }
+
+    // turns what comes down or up the column onto the entry line, left along it and down to the code
+    fn entry(&mut self, target: usize) {
+        let (x, r) = (self.columns[target], self.entries[target]);
+        self.turn(&[(x, r - 2)], (x, r - 1), (x, r));
+        self.turn(&[(x, r + 4), (x, r + 3), (x, r + 2)], (x, r + 1), (x, r));
+        self.white(r, 5, x - 1);
+        self.turn(&[(4, r), (3, r), (2, r)], (1, r), (0, r));
+        self.set(0, r + 1, WHITE);
+        self.turn(
+            &[(0, r + 2), (0, r + 3), (0, r + 4)],
+            (0, r + 5),
+            (0, r + 6),
+        );
+        self.set(1, r + 6, WHITE);
+    }
+}
+
+/// Assembles a program in a simple stack assembly into a piet program of codels. Every line holds one of
+/// - a command of the interpreter like `add`, `dup` or `out_char`, with `push N` taking the number to push
+/// - a label like `loop:`
+/// - `jmp label`, or `jz label` and `jnz label` which pop a value and jump if it is (not) zero
+/// - `halt`, which is also where the program ends after the last line.
+///
+/// `;` and `#` start comments. Every basic block is laid out on its own rows running right,
+/// jumps are routed through a column per target using white codels and `pointer`.
+pub fn assemble(source: &str) -> Result<Vec<Vec<RGB>>, String> {
+    let blocks = parse(source)?;
+
+    // pushing n needs a block of n codels, which are stacked this high
+    let push_height = blocks
+        .iter()
+        .flat_map(|block| &block.code)
+        .filter(|(command, _)| *command == Command::Push)
+        .map(|(_, n)| (1..).find(|h| h * h >= *n).unwrap())
+        .max()
+        .unwrap_or(1);
+    let code_width = |block: &BasicBlock| -> i32 {
+        block
+            .code
+            .iter()
+            .map(|(_, size)| (*size as u32).div_ceil(push_height as u32) as i32)
+            .sum()
+    };
+
+    // code starts at column 2 and is followed by the last block and what ends it
+    let code_end = blocks
+        .iter()
+        .map(|b| 2 + code_width(b) + 2)
+        .max()
+        .unwrap_or(4);
+    let columns: Vec<i32> = (0..blocks.len() as i32)
+        .map(|i| code_end + 5 + COLUMN_SPACING * i)
+        .collect();
+    // row 0 is where the program starts, every block gets an entry line and 6 rows down to its code
+    let mut entries = Vec::new();
+    let mut top = 2;
+    for _ in &blocks {
+        entries.push(top + 2);
+        top += 2 + 6 + 6.max(push_height - 1) + 2;
+    }
+
+    let (width, height) = (columns.last().unwrap() + 2, top - 1);
+    let mut canvas = Canvas {
+        grid: vec![vec![BLACK; width as usize]; height as usize],
+        entries,
+        columns,
+    };
+    for x in canvas.columns.clone() {
+        for y in 0..height {
+            canvas.set(x, y, WHITE);
+        }
+    }
+    canvas.set(0, 0, FIRST);
+    canvas.jump(0, 1, 0);
+
+    for (i, block) in blocks.iter().enumerate() {
+        canvas.entry(i);
+        let y = canvas.entries[i] + 6;
+        let mut x = 2;
+        let mut color = FIRST;
+        for (command, size) in &block.code {
+            for n in 0..*size {
+                canvas.set(x + n / push_height, y + n % push_height, color);
+            }
+            x += (*size as u32).div_ceil(push_height as u32) as i32;
+            color = encode_command(color, *command);
+        }
+        canvas.set(x, y, color);
+
+        match block.end {
+            End::Halt => {
+                // entered from the left, but every way out is blocked
+                let stop = encode_command(color, Command::Push);
+                for (dx, dy) in [(0, -1), (1, -1), (1, 0), (1, 1)] {
+                    canvas.set(x + dx, y + dy, stop);
+                }
+            }
+            End::Jump(target) => canvas.jump(y, x + 1, target),
+            End::Branch { taken, otherwise } => {
+                // `pointer` turned down, so turn right again below the code
+                canvas.set(x, y + 1, WHITE);
+                canvas.turn(
+                    &[(x, y + 2), (x, y + 3), (x, y + 4)],
+                    (x, y + 5),
+                    (x, y + 6),
+                );
+                canvas.jump(y + 6, x + 1, taken);
+                canvas.jump(y, x + 1, otherwise);
+            }
+        }
+    }
+    Ok(canvas.grid)
+}
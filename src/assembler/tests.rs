@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod tests {
+    use crate::assembler::*;
+    use crate::machine::*;
+    use crate::piet_io::*;
+    use std::io::{empty, Cursor};
+
+    fn run(source: &str, input: &str) -> String {
+        let grid = assemble(source).unwrap();
+        let out = SharedBuffer::default();
+        let io = Io::new(
+            Box::new(Cursor::new(input.as_bytes().to_vec())),
+            Box::new(out.clone()),
+        );
+        let mut machine = Machine::new(grid, 1, Interpreter::new(io));
+        let limits = Limits {
+            max_steps: Some(100_000),
+            ..Limits::default()
+        };
+        assert_eq!(machine.run(&limits, |_, _| ()), Ok(Halt::Terminated));
+        machine.interp.io.flush().unwrap();
+        String::from_utf8(out.contents()).unwrap()
+    }
+
+    #[test]
+    fn runs_straight_code() {
+        assert_eq!(run("push 6\npush 7\nmult\nout_num", ""), "42");
+        assert_eq!(run("push 0\nout_num\npush -5\nout_num", ""), "0-5");
+        assert_eq!(run("", ""), "");
+    }
+    #[test]
+    fn loops_and_branches() {
+        let countdown = "
+            push 5
+        loop:           ; jumps back up
+            dup
+            out_num
+            push 1
+            sub
+            dup
+            jnz loop
+            halt
+        ";
+        assert_eq!(run(countdown, ""), "54321");
+
+        // prints whether each of 4 numbers is zero, jumping down and up
+        let zeros = "
+            push 4
+        next:
+            in_num
+            jz zero
+            push 110    # n
+            out_char
+            jmp count
+        zero:
+            push 122    # z
+            out_char
+        count:
+            push 1
+            sub
+            dup
+            jnz next
+        ";
+        // every in_num prompts
+        assert_eq!(run(zeros, "3\n0\n0\n-2\n"), "> n> z> z> n");
+    }
+    #[test]
+    fn big_pushes_and_unreachable_code() {
+        let source = "
+            jmp end
+            push 1000
+            out_num
+        end:
+            push 1000
+            push 3
+            sub
+            out_num
+        ";
+        assert_eq!(run(source, ""), "997");
+    }
+    #[test]
+    fn builds_large_numbers_from_small_pushes() {
+        for n in [i32::MAX, i32::MIN, i32::MIN + 1, 1_234_567, -1_000_001] {
+            assert_eq!(run(&format!("push {}\nout_num", n), ""), n.to_string());
+        }
+        // no block of a million codels
+        let grid = assemble("push 1234567\nout_num").unwrap();
+        assert!(grid.len() * grid[0].len() < 10_000);
+    }
+    #[test]
+    fn reports_errors_with_the_line() {
+        let error = |source| assemble(source).unwrap_err();
+        assert_eq!(
+            error("push 1\njmp nowhere"),
+            "line 2: unknown label \"nowhere\""
+        );
+        assert_eq!(error("a:\na:"), "line 2: label \"a\" is defined twice");
+        assert_eq!(error("push x"), "line 1: \"x\" is not a number");
+        assert_eq!(
+            error("push 1\npush 2147483648"),
+            "line 2: 2147483648 is out of range, push takes numbers from -2147483648 to 2147483647"
+        );
+        assert_eq!(error("push"), "line 1: push needs an argument");
+        assert_eq!(error("add 2"), "line 1: add doesn't take an argument");
+        assert_eq!(error("jump"), "line 1: unknown instruction \"jump\"");
+        assert!(error("pointer").contains("use jmp, jz or jnz"));
+    }
+}
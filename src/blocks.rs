@@ -0,0 +1,239 @@
+use crate::interpreter::get_color_index;
+use crate::types::*;
+
+mod tests;
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::RIGHT,
+    Direction::DOWN,
+    Direction::LEFT,
+    Direction::UP,
+];
+
+fn dp_index(dp: Direction) -> usize {
+    match dp {
+        Direction::RIGHT => 0,
+        Direction::DOWN => 1,
+        Direction::LEFT => 2,
+        Direction::UP => 3,
+    }
+}
+
+fn cc_index(cc: CodelChooser) -> usize {
+    match cc {
+        CodelChooser::LEFT => 0,
+        CodelChooser::RIGHT => 1,
+    }
+}
+
+/// A color block, all positions are in codels.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    pub color: RGB,
+    pub size: i32,
+    pub codels: Vec<Coordinates>,
+    /// The codel the block is left from, indexed by DP and CC.
+    exits: [[Coordinates; 2]; 4],
+}
+
+impl Block {
+    /// The codel the block is left from, the same as `navigation::exit_codel`.
+    pub fn exit(&self, dp: Direction, cc: CodelChooser) -> Coordinates {
+        self.exits[dp_index(dp)][cc_index(cc)]
+    }
+
+    /// The topmost codel of the block, the leftmost one of them. Labeling scans the codels row
+    /// by row, so it is the codel the block was found at.
+    pub fn top_left(&self) -> Coordinates {
+        self.codels[0]
+    }
+}
+
+// the furthest codels in direction of DP, the first one to the left of DP, the second to its right
+fn exits(codels: &[Coordinates]) -> [[Coordinates; 2]; 4] {
+    let mut exits = [[Coordinates { x: 0, y: 0 }; 2]; 4];
+    for dp in DIRECTIONS {
+        // how far a codel is in direction of DP, and how far to the right of DP
+        let ahead = |c: &Coordinates| c.x * dp.cords().x + c.y * dp.cords().y;
+        let right = |c: &Coordinates| c.y * dp.cords().x - c.x * dp.cords().y;
+
+        let front = codels.iter().map(ahead).max().unwrap();
+        let edge = codels.iter().filter(|c| ahead(c) == front);
+        exits[dp_index(dp)] = [
+            *edge.clone().min_by_key(|c| right(c)).unwrap(),
+            *edge.max_by_key(|c| right(c)).unwrap(),
+        ];
+    }
+    exits
+}
+
+/// The program divided into color blocks once, so every step is a table lookup
+/// instead of a flood fill. Colors outside of the 18 piet colors (white, black and
+/// any other) don't belong to a block.
+pub struct BlockMap {
+    /// Size of the program in codels.
+    pub width: i32,
+    pub height: i32,
+    /// Color of every codel, row by row.
+    colors: Vec<RGB>,
+    /// Index into `blocks` of every codel, `None` for codels without a block.
+    labels: Vec<Option<usize>>,
+    pub blocks: Vec<Block>,
+}
+
+impl BlockMap {
+    /// Labels the codels of `rgb_img`, taking the top left pixel of every codel.
+    pub fn new(rgb_img: &[Vec<RGB>], codel_size: i32) -> BlockMap {
+        let width = rgb_img[0].len() as i32 / codel_size;
+        let height = rgb_img.len() as i32 / codel_size;
+        let mut colors = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                colors.push(rgb_img[(y * codel_size) as usize][(x * codel_size) as usize]);
+            }
+        }
+
+        let mut map = BlockMap {
+            width,
+            height,
+            labels: vec![None; colors.len()],
+            colors,
+            blocks: Vec::new(),
+        };
+        for start in 0..map.labels.len() {
+            let color = map.colors[start];
+            if map.labels[start].is_some() || get_color_index(color).is_none() {
+                continue;
+            }
+            let id = map.blocks.len();
+            map.labels[start] = Some(id);
+            let mut codels = Vec::new();
+            let mut todo = vec![start];
+            while let Some(i) = todo.pop() {
+                let codel = Coordinates {
+                    x: i as i32 % width,
+                    y: i as i32 / width,
+                };
+                codels.push(codel);
+                for dp in DIRECTIONS {
+                    let next = Coordinates {
+                        x: codel.x + dp.cords().x,
+                        y: codel.y + dp.cords().y,
+                    };
+                    if let Some(j) = map.index(next) {
+                        if map.labels[j].is_none() && map.colors[j] == color {
+                            map.labels[j] = Some(id);
+                            todo.push(j);
+                        }
+                    }
+                }
+            }
+            map.blocks.push(Block {
+                color,
+                size: codels.len() as i32,
+                exits: exits(&codels),
+                codels,
+            });
+        }
+        map
+    }
+
+    fn index(&self, codel: Coordinates) -> Option<usize> {
+        if codel.x >= 0 && codel.x < self.width && codel.y >= 0 && codel.y < self.height {
+            Some((codel.y * self.width + codel.x) as usize)
+        } else {
+            None
+        }
+    }
+
+    pub fn color(&self, codel: Coordinates) -> Option<RGB> {
+        self.index(codel).map(|i| self.colors[i])
+    }
+
+    pub fn block_id(&self, codel: Coordinates) -> Option<usize> {
+        self.index(codel).and_then(|i| self.labels[i])
+    }
+
+    pub fn block(&self, codel: Coordinates) -> Option<&Block> {
+        self.block_id(codel).map(|id| &self.blocks[id])
+    }
+
+    // the last codel of the same color in direction of DP
+    fn slide(&self, mut codel: Coordinates, dp: Direction) -> Coordinates {
+        let color = self.color(codel);
+        loop {
+            let next = Coordinates {
+                x: codel.x + dp.cords().x,
+                y: codel.y + dp.cords().y,
+            };
+            if self.color(next) != color {
+                return codel;
+            }
+            codel = next;
+        }
+    }
+
+    /// The codels `navigation::get_block` returns for the block at `codel`:
+    /// the whole block or, outside of blocks, where sliding in direction of DP ends.
+    pub fn block_codels(&self, codel: Coordinates, dp: Direction) -> Vec<Coordinates> {
+        match self.block(codel) {
+            Some(block) => block.codels.clone(),
+            None => vec![self.slide(codel, dp)],
+        }
+    }
+
+    /// Color and size of the block at `codel`, sizes outside of blocks are 1.
+    pub fn color_info(&self, codel: Coordinates) -> ColorInfo {
+        ColorInfo {
+            color: self.color(codel).unwrap(),
+            size: self.block(codel).map_or(1, |block| block.size),
+        }
+    }
+
+    /// Moves `codel` to the next block following the same rules as `navigation::next_color`,
+    /// `None` if the program terminates.
+    pub fn next_color(
+        &self,
+        codel: &mut Coordinates,
+        dp: &mut Direction,
+        cc: &mut CodelChooser,
+    ) -> Option<ColorInfo> {
+        let mut cc_toggled = false;
+        let mut rotations = 0;
+
+        let block = self.block(*codel);
+        // outside of blocks the codel to leave from is found once, before DP changes
+        let slide_end = match block {
+            Some(_) => *codel,
+            None => self.slide(*codel, *dp),
+        };
+        loop {
+            let exit = match block {
+                Some(block) => block.exit(*dp, *cc),
+                None => slide_end,
+            };
+            let next = Coordinates {
+                x: exit.x + dp.cords().x,
+                y: exit.y + dp.cords().y,
+            };
+            match self.color(next) {
+                Some(color) if color != BLACK => {
+                    *codel = next;
+                    return Some(self.color_info(next));
+                }
+                _ => {
+                    if rotations >= 4 {
+                        return None;
+                    } else if cc_toggled {
+                        *dp = dp.next();
+                        cc_toggled = false;
+                        rotations += 1;
+                    } else {
+                        *cc = cc.toggle();
+                        cc_toggled = true;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+#[cfg(test)]
+mod tests {
+    use crate::blocks::*;
+    use crate::interpreter::get_color_index;
+    use crate::navigation;
+    use crate::test_support::{load_image, HELLO_WORLD};
+
+    const DPS: [Direction; 4] = [
+        Direction::RIGHT,
+        Direction::DOWN,
+        Direction::LEFT,
+        Direction::UP,
+    ];
+    const CCS: [CodelChooser; 2] = [CodelChooser::LEFT, CodelChooser::RIGHT];
+
+    // the lookup has to agree with the flood fill from every codel in every direction
+    fn agrees_with_navigation(path: &str) {
+        let (rgb_img, size) = load_image(path);
+        let map = BlockMap::new(&rgb_img, size);
+
+        for y in 0..map.height {
+            for x in 0..map.width {
+                let codel = Coordinates { x, y };
+                if map.color(codel) == Some(RGB(0, 0, 0)) {
+                    continue;
+                }
+                for dp in DPS {
+                    for cc in CCS {
+                        let (mut pos, mut dp1, mut cc1) = (
+                            Coordinates {
+                                x: x * size,
+                                y: y * size,
+                            },
+                            dp,
+                            cc,
+                        );
+                        let expected =
+                            navigation::next_color(&rgb_img, &mut pos, size, &mut dp1, &mut cc1);
+                        let (mut next, mut dp2, mut cc2) = (codel, dp, cc);
+                        let actual = map.next_color(&mut next, &mut dp2, &mut cc2);
+
+                        assert_eq!(actual, expected, "{:?} {:?} {:?}", codel, dp, cc);
+                        assert_eq!((dp2, cc2), (dp1, cc1));
+                        if expected.is_some() {
+                            assert_eq!(
+                                next,
+                                Coordinates {
+                                    x: pos.x / size,
+                                    y: pos.y / size
+                                }
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn agrees_with_navigation_hello_world() {
+        agrees_with_navigation("tests/fixtures/piet_hello_world.png");
+    }
+    #[test]
+    fn agrees_with_navigation_with_white() {
+        agrees_with_navigation("tests/fixtures/fizzbuzz.png");
+    }
+    #[test]
+    fn labels_blocks_once() {
+        let (rgb_img, size) = load_image(HELLO_WORLD);
+        let map = BlockMap::new(&rgb_img, size);
+
+        let red = map.block(Coordinates { x: 3, y: 4 }).unwrap();
+        assert_eq!(red.color, RGB(255, 0, 0));
+        assert_eq!(red.size, 72);
+        assert!(red.codels.contains(&Coordinates { x: 0, y: 0 }));
+        assert_eq!(
+            map.block_id(Coordinates { x: 0, y: 0 }),
+            map.block_id(Coordinates { x: 3, y: 4 })
+        );
+        assert_ne!(
+            map.block_id(Coordinates { x: 0, y: 0 }),
+            map.block_id(Coordinates { x: 11, y: 0 })
+        );
+        // every codel of the 18 colors belongs to exactly one block
+        let colored = rgb_img
+            .iter()
+            .step_by(size as usize)
+            .flat_map(|row| row.iter().step_by(size as usize))
+            .filter(|c| get_color_index(**c).is_some())
+            .count();
+        assert_eq!(
+            map.blocks.iter().map(|b| b.size).sum::<i32>(),
+            colored as i32
+        );
+        assert_eq!(
+            red.exit(Direction::RIGHT, CodelChooser::LEFT),
+            Coordinates { x: 10, y: 0 }
+        );
+    }
+    #[test]
+    fn top_left_is_the_first_codel_in_reading_order() {
+        let (rgb_img, size) = load_image("tests/fixtures/fizzbuzz.png");
+        let map = BlockMap::new(&rgb_img, size);
+
+        for block in &map.blocks {
+            let first = block.codels.iter().min_by_key(|c| (c.y, c.x)).unwrap();
+            assert_eq!(block.top_left(), *first);
+        }
+    }
+    #[test]
+    fn white_and_black_have_no_block() {
+        let rgb_img = vec![vec![
+            RGB(255, 0, 0),
+            RGB(255, 255, 255),
+            RGB(255, 255, 255),
+            RGB(0, 0, 0),
+        ]];
+        let map = BlockMap::new(&rgb_img, 1);
+
+        assert_eq!(map.block_id(Coordinates { x: 1, y: 0 }), None);
+        assert_eq!(map.block_id(Coordinates { x: 3, y: 0 }), None);
+        assert_eq!(map.color_info(Coordinates { x: 1, y: 0 }).size, 1);
+        assert_eq!(
+            map.block_codels(Coordinates { x: 1, y: 0 }, Direction::RIGHT),
+            [Coordinates { x: 2, y: 0 }]
+        );
+    }
+}
@@ -1,18 +1,29 @@
 use clap::{Arg, ArgMatches, Command};
 
+fn program_file(s: &str) -> Result<(), String> {
+    if !s.ends_with(".png") && !s.ends_with(".txt") {
+        Err(String::from("File must end with .png or .txt"))
+    } else {
+        Ok(())
+    }
+}
+
+fn file_arg() -> Arg<'static> {
+    Arg::new("file")
+        .help("The program to execute, a png or a codel grid in a .txt file.")
+        .index(1)
+        .required(true)
+        .validator(program_file)
+}
+
 pub fn cli_options() -> ArgMatches {
     Command::new("piet interpreter")
         .author("Philipp Rados")
         .about("An interpreter for the piet programming language")
-        .arg(
-            Arg::new("file")
-                .help("The image to execute. Currently only supports png.")
-                .index(1)
-                .required(true)
-                .validator(|s| {
-                    if !s.ends_with(".png") {Err(String::from("File must end with .png"))} else {Ok(())}
-                }),
-        )
+        // `pint prog.png` runs the program, everything else is a subcommand
+        .args_conflicts_with_subcommands(true)
+        .subcommand_negates_reqs(true)
+        .arg(file_arg())
         .arg(
             Arg::new("codel_size")
                 .help("The size of a codel in pixels")
@@ -25,6 +36,7 @@ pub fn cli_options() -> ArgMatches {
                     In such enlarged programs, the term 'codel' is used to mean a block of colour equivalent to a single pixel of code,\n
                     to avoid confusion with the actual pixels of the enlarged graphic, of which many may make up one codel."
                 )
+                .global(true)
                 .takes_value(true)
                 .required(false)
                 .validator(|size|{
@@ -39,5 +51,470 @@ pub fn cli_options() -> ArgMatches {
                 }
             ),
         )
+        .arg(
+            Arg::new("char_encoding")
+                .help("How in_char and out_char read and write characters")
+                .long("char-encoding")
+                .long_help(
+                    "utf8: characters are whole unicode scalar values encoded as UTF-8.\n
+                    bytes: characters are single raw bytes (0-255)."
+                )
+                .global(true)
+                .takes_value(true)
+                .possible_values(["utf8", "bytes"])
+                .default_value("utf8"),
+        )
+        .arg(
+            Arg::new("invalid_char")
+                .help("What out_char does with values that are not a valid character")
+                .long("invalid-char")
+                .long_help(
+                    "skip: the value is popped and nothing is printed.\n
+                    replace: the replacement character U+FFFD is printed.\n
+                    error: the program stops with an error."
+                )
+                .global(true)
+                .takes_value(true)
+                .possible_values(["skip", "replace", "error"])
+                .default_value("replace"),
+        )
+        .arg(
+            Arg::new("int")
+                .help("The kind of integers on the stack")
+                .long("int")
+                .long_help(
+                    "i32: 32-bit integers that wrap around on overflow.\n
+                    i64: 64-bit integers, overflowing stops the program with an error.\n
+                    big: integers of unlimited size."
+                )
+                .global(true)
+                .takes_value(true)
+                .possible_values(["i32", "i64", "big"])
+                .default_value("i32"),
+        )
+        .arg(
+            Arg::new("overflow")
+                .help("What happens when a result doesn't fit into the integers on the stack [default: wrap for i32, error for i64]")
+                .long("overflow")
+                .long_help(
+                    "wrap: wraps around like two's complement arithmetic.\n
+                    saturate: clamps to the smallest or biggest value.\n
+                    error: stops the program with an error.\n
+                    Defaults to wrap for --int i32 and to error for --int i64, can't be used with --int big."
+                )
+                .global(true)
+                .takes_value(true)
+                .possible_values(["wrap", "saturate", "error"]),
+        )
+        .arg(
+            Arg::new("division")
+                .help("How div rounds and which sign the result of mod has")
+                .long("division")
+                .long_help(
+                    "floor: div rounds towards negative infinity, mod has the sign of the divisor.\n
+                    truncate: div rounds towards zero, mod has the sign of the dividend."
+                )
+                .global(true)
+                .takes_value(true)
+                .possible_values(["floor", "truncate"])
+                .default_value("floor"),
+        )
+        .arg(
+            Arg::new("strict")
+                .help("Reports commands that are ignored, or stops with --strict=error")
+                .long("strict")
+                .long_help(
+                    "Reports commands that are ignored because there are too few values on the stack,\n
+                    the divisor is 0 or the roll depth isn't positive or deeper than the stack,\n
+                    with the step number and the codel of the block that was left.\n
+                    --strict=warn prints a warning to stderr and goes on, --strict=error (the default) stops."
+                )
+                .global(true)
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .possible_values(["warn", "error"])
+                .default_missing_value("error"),
+        )
+        .arg(
+            Arg::new("compile")
+                .help("Compiles the program into a graph of states before running it")
+                .long("compile")
+                .long_help(
+                    "Compiles the program into a graph of (block, DP, CC) states before running it. \
+                    Every step is a single lookup then, which pays off for long running programs."
+                )
+                .global(true),
+        )
+        .arg(
+            Arg::new("max_steps")
+                .help("Stops the program after executing this many steps")
+                .long("max-steps")
+                .value_name("N")
+                .takes_value(true)
+                .validator(|n| {
+                    n.parse::<u64>()
+                        .map(|_| ())
+                        .map_err(|_| String::from("Max steps must be a positive number"))
+                }),
+        )
+        .arg(
+            Arg::new("timeout")
+                .help("Stops the program after running this many seconds")
+                .long("timeout")
+                .long_help(
+                    "Stops the program after running this many seconds. The time is checked between steps, a program waiting for input in in_char or in_num \
+                    isn't stopped until the input arrives."
+                )
+                .value_name("SECS")
+                .takes_value(true)
+                .validator(|secs| match secs.parse::<f64>() {
+                    Ok(s) if s >= 0.0 && s.is_finite() => Ok(()),
+                    _ => Err(String::from("Timeout must be a positive number of seconds")),
+                }),
+        )
+        .arg(
+            Arg::new("trace")
+                .help("Prints every executed step to stderr")
+                .long("trace")
+                .long_help(
+                    "Prints a line for every executed step to stderr, containing the step number,\n
+                    the position, color and size of the block that was left and the one that was entered,\n
+                    DP, CC, the executed command and the stack afterwards.\n
+                    --trace=json prints every step as JSON object instead."
+                )
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .possible_values(["text", "json"])
+                .default_missing_value("text"),
+        )
+        .arg(
+            Arg::new("profile")
+                .help("Prints how often commands, blocks and transitions were executed to stderr")
+                .long("profile")
+                .long_help(
+                    "Counts every executed command, how often every color block was left and with\n
+                    which DP and CC, and how deep the stack got. Prints a report sorted by count to stderr\n
+                    when the program stops. --profile=json prints it as JSON object instead."
+                )
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .possible_values(["text", "json"])
+                .default_missing_value("text"),
+        )
+        .arg(
+            Arg::new("coverage")
+                .help("Writes the image with the blocks shaded by how often they were entered to FILE")
+                .long("coverage")
+                .long_help(
+                    "Writes a png of the program to FILE when it stops. Blocks that were never entered \
+                    are faded to gray, the others are tinted from yellow to red the more often they were \
+                    entered. With --coverage-data the counts of earlier runs are included."
+                )
+                .value_name("FILE")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("coverage_data")
+                .help("Adds how often every block was entered to the counts in FILE")
+                .long("coverage-data")
+                .long_help(
+                    "Counts how often every block was entered and adds the counts to those in FILE, \
+                    which is created if it doesn't exist. Running a test suite with the same FILE \
+                    collects the coverage of all tests. Refuses files of other images or codel sizes."
+                )
+                .value_name("FILE")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("save_state")
+                .help("Saves the state of the program to FILE when it stops")
+                .long("save-state-on-exit")
+                .long_help(
+                    "Saves position, DP, CC, stack, step count and the input read ahead to FILE \
+                    when the program stops, for whatever reason. Continue it with --resume FILE."
+                )
+                .value_name("FILE")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("resume")
+                .help("Continues a program from a state saved with --save-state-on-exit")
+                .long("resume")
+                .long_help(
+                    "Continues a program from a state saved with --save-state-on-exit. \
+                    Refuses to resume if the state was saved for another image or codel size. \
+                    --max-steps counts the steps from before the state was saved as well."
+                )
+                .value_name("FILE")
+                .takes_value(true),
+        )
+        .subcommand(
+            Command::new("debug")
+                .about("Runs the program in an interactive debugger")
+                .arg(file_arg())
+                .arg(
+                    Arg::new("tui")
+                        .long("tui")
+                        .help("Shows the program, the stack and the output on a full-screen view")
+                        .long_help(
+                            "Shows the program, the stack and the output on a full-screen view.\n
+                    The program is drawn with one character per codel, the current block \
+                    is highlighted and an arrow marks where it is left in direction of DP. \
+                    Needs a terminal supporting 24-bit colors.",
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("dap")
+                .about("Runs a Debug Adapter Protocol server on stdin and stdout")
+                .long_about(
+                    "Runs a Debug Adapter Protocol server on stdin and stdout, for editors like VS Code.\n
+                    The launch request takes the `program` to debug, its `input` as string \
+                    and `stopOnEntry`. Lines and columns are codel coordinates.",
+                ),
+        )
+        .subcommand(
+            Command::new("graph")
+                .about("Prints the program as graph in the DOT language")
+                .long_about(
+                    "Prints the program as graph in the DOT language, render it with e.g. `dot -Tsvg`.\n
+                    Nodes are the color blocks with their color, size and top left codel, \
+                    edges are the steps between them with the command and the DP/CC states they are taken in.",
+                )
+                .arg(file_arg())
+                .arg(
+                    Arg::new("reachable")
+                        .long("reachable")
+                        .help("Only includes the states reachable from the start of the program"),
+                ),
+        )
+        .subcommand(
+            Command::new("disasm")
+                .about("Prints the reachable part of the program as assembly listing")
+                .long_about(
+                    "Prints the reachable part of the program as assembly listing of labeled basic blocks.\n
+                    `pointer` and `switch` list the block they continue at for every value \
+                    (mod 4 and mod 2), steps through white are left out.",
+                )
+                .arg(file_arg()),
+        )
+        .subcommand(
+            Command::new("asm")
+                .about("Assembles a program in stack assembly into a png")
+                .long_about(
+                    "Assembles a program in stack assembly into a png. Every line holds a command \
+                    like `push 5`, `add` or `out_char`, a label like `loop:`, `jmp label`, \
+                    `jz label`, `jnz label` or `halt`. `;` and `#` start comments.\n
+                    The codels of the image are --codel-size pixels big, 1 by default.",
+                )
+                .arg(
+                    Arg::new("source")
+                        .help("The program to assemble")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("output")
+                        .help("Where to write the png")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("gen-print")
+                .about("Generates a program printing the given text")
+                .long_about(
+                    "Generates a program printing the given text with out_char. Characters are \
+                    built with push, dup, mult and add to keep blocks small.\n
+                    The codels of the image are --codel-size pixels big, 1 by default.",
+                )
+                .arg(
+                    Arg::new("text")
+                        .help("The text the program prints")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("output")
+                        .help("Where to write the png")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("transpile")
+                .about("Transpiles the program into C or Rust source")
+                .long_about(
+                    "Transpiles the part of the program reachable from the start into C or Rust source, \
+                    printed to stdout. The commands run on an explicit stack and a switch dispatches \
+                    on the (block, DP, CC) state. Integers have 32 bits and wrap around, \
+                    --division is taken into account.",
+                )
+                .arg(file_arg())
+                .arg(
+                    Arg::new("to")
+                        .help("The language to transpile to")
+                        .long("to")
+                        .takes_value(true)
+                        .possible_values(["c", "rust"])
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("lint")
+                .about("Looks for problems in the program")
+                .long_about(
+                    "Looks for problems in the program without running it: pixels outside of the \
+                    20 colors, codels that aren't one color, blocks that are never reached, commands \
+                    that (can) underflow the stack and states the program can't terminate from.\n
+                    Exits with 1 if there are findings.",
+                )
+                .arg(file_arg())
+                .arg(
+                    Arg::new("json")
+                        .help("Prints the findings as JSON array")
+                        .long("json"),
+                ),
+        )
+        .subcommand(
+            Command::new("render-trace")
+                .about("Renders the execution as animated GIF or sequence of png frames")
+                .long_about(
+                    "Runs the program and renders a frame before the first step and after every step: \
+                    the program with the current block outlined and an arrow for DP in the codel it is \
+                    left from, above the step, the command, DP, CC and the stack.\n
+                    --out ending in .gif writes an animated GIF, anything else is a directory the frames \
+                    are written to as frame-00000.png and so on.",
+                )
+                .arg(file_arg())
+                .arg(
+                    Arg::new("out")
+                        .help("The GIF or directory to write the frames to")
+                        .short('o')
+                        .long("out")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("scale")
+                        .help("Size of a codel in the frames, in pixels")
+                        .long("scale")
+                        .value_name("PIXELS")
+                        .takes_value(true)
+                        .default_value("8")
+                        .validator(|n| {
+                            n.parse::<usize>()
+                                .ok()
+                                .filter(|n| *n > 0)
+                                .map(|_| ())
+                                .ok_or_else(|| String::from("Scale must be a positive number"))
+                        }),
+                )
+                .arg(
+                    Arg::new("max_frames")
+                        .help("Stops after rendering this many frames")
+                        .long("max-frames")
+                        .value_name("N")
+                        .takes_value(true)
+                        .default_value("500")
+                        .validator(|n| {
+                            n.parse::<usize>()
+                                .ok()
+                                .filter(|n| *n > 0)
+                                .map(|_| ())
+                                .ok_or_else(|| String::from("Max frames must be a positive number"))
+                        }),
+                )
+                .arg(
+                    Arg::new("delay")
+                        .help("How long every frame of the GIF is shown, in milliseconds")
+                        .long_help(
+                            "How long every frame of the GIF is shown, in milliseconds. \
+                            GIFs count in hundredths of a second, so it is rounded down to them \
+                            and longer delays than 655350 are cut to it.",
+                        )
+                        .long("delay")
+                        .value_name("MS")
+                        .takes_value(true)
+                        .default_value("200")
+                        .validator(|n| {
+                            n.parse::<u64>()
+                                .map(|_| ())
+                                .map_err(|_| String::from("Delay must be a positive number"))
+                        }),
+                ),
+        )
+        .subcommand(
+            Command::new("show")
+                .about("Prints the codel grid of the program")
+                .long_about(
+                    "Prints the codel grid of the program, two columns per codel: in 24-bit colors \
+                    or as letters for lightness and hue like `lR` (light red) and `dB` (dark blue), \
+                    `WW` is white and `KK` black.\n
+                    With --ids the top left codel of every block shows the id of the block, \
+                    with --path the program is run and the codels it enters blocks at are marked with `*`.",
+                )
+                .arg(file_arg())
+                .arg(
+                    Arg::new("color")
+                        .help("Whether to use colors, by default only on a terminal without NO_COLOR set")
+                        .long("color")
+                        .takes_value(true)
+                        .possible_values(["auto", "always", "never"])
+                        .default_value("auto"),
+                )
+                .arg(
+                    Arg::new("ids")
+                        .help("Shows the id of every block at its top left codel")
+                        .long("ids"),
+                )
+                .arg(
+                    Arg::new("path")
+                        .help("Runs the program and marks the codels it enters blocks at")
+                        .long("path"),
+                )
+                .arg(
+                    Arg::new("max_steps")
+                        .help("Stops the program after this many steps for --path")
+                        .long("max-steps")
+                        .value_name("N")
+                        .takes_value(true)
+                        .default_value("10000")
+                        .validator(|n| {
+                            n.parse::<u64>()
+                                .map(|_| ())
+                                .map_err(|_| String::from("Max steps must be a positive number"))
+                        }),
+                ),
+        )
+        .subcommand(
+            Command::new("convert")
+                .about("Converts a program between png and codel grid")
+                .long_about(
+                    "Converts a png into a codel grid in a .txt file or the other way round. A codel grid \
+                    has a line per row of codels with a token per codel: l, n or d for light, normal and dark \
+                    followed by the first letter of the hue (R, Y, G, C, B, M), WW for white and KK for black. \
+                    Everything after # is a comment.\n
+                    --codel-size is the codel size of the png, inferred when reading and 1 when writing by default.",
+                )
+                .arg(file_arg().help("The program to convert"))
+                .arg(
+                    Arg::new("output")
+                        .help("Where to write the program, a .png or .txt file")
+                        .index(2)
+                        .required(true)
+                        .validator(program_file),
+                ),
+        )
     .get_matches()
 }
@@ -0,0 +1,199 @@
+use crate::json::Json;
+use crate::machine::*;
+use crate::snapshot::image_hash;
+use crate::types::*;
+
+mod tests;
+
+const FORMAT: &str = "pint-coverage";
+/// Version of the coverage file format, files of newer versions are refused.
+pub const FORMAT_VERSION: i64 = 1;
+
+/// How often every color block of a program was entered, over one or more runs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Coverage {
+    /// Identifies the image the counts belong to, see `image_hash`.
+    pub image_hash: String,
+    /// Number of runs the counts were collected in.
+    pub runs: u64,
+    /// Indexed by the block id in the `BlockMap`.
+    counts: Vec<u64>,
+}
+
+impl Coverage {
+    /// Starts a run, the block `machine` is in counts as visited.
+    pub fn new(machine: &Machine) -> Coverage {
+        let mut coverage = Coverage {
+            image_hash: image_hash(&machine.rgb_img),
+            runs: 1,
+            counts: vec![0; machine.blocks.blocks.len()],
+        };
+        coverage.visit(machine);
+        coverage
+    }
+
+    fn visit(&mut self, machine: &Machine) {
+        if let Some(id) = machine.blocks.block_id(machine.codel()) {
+            self.counts[id] += 1;
+        }
+    }
+
+    /// Counts the block that was entered by a step.
+    pub fn record(&mut self, machine: &Machine, _: &Step) {
+        self.visit(machine);
+    }
+
+    /// How often the block with `id` was entered.
+    pub fn count(&self, id: usize) -> u64 {
+        self.counts[id]
+    }
+
+    /// Adds the counts of another run of the same image.
+    pub fn merge(&mut self, other: &Coverage) -> Result<(), String> {
+        if other.image_hash != self.image_hash || other.counts.len() != self.counts.len() {
+            return Err(String::from("the coverage is for a different image"));
+        }
+        self.runs += other.runs;
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other;
+        }
+        Ok(())
+    }
+
+    /// The image of `machine` with blocks that were never entered faded to gray and the others
+    /// tinted from yellow to red, the more often they were entered the redder.
+    /// White, black and other colors are left as they are.
+    pub fn render(&self, machine: &Machine) -> Vec<Vec<RGB>> {
+        let max = self.counts.iter().copied().max().unwrap_or(0);
+        let codel_size = machine.codel_size as usize;
+        machine
+            .rgb_img
+            .iter()
+            .enumerate()
+            .map(|(y, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(x, pixel)| {
+                        let codel = Coordinates {
+                            x: (x / codel_size) as i32,
+                            y: (y / codel_size) as i32,
+                        };
+                        match machine.blocks.block_id(codel) {
+                            Some(id) => shade(*pixel, self.counts[id], max),
+                            None => *pixel,
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Blocks are stored by their top left codel, so files of runs with different codel sizes
+    /// can't be merged.
+    pub fn to_json(&self, machine: &Machine) -> Json {
+        let blocks = machine
+            .blocks
+            .blocks
+            .iter()
+            .zip(&self.counts)
+            .map(|(block, count)| {
+                let codel = block.top_left();
+                Json::object(vec![
+                    ("x", Json::number(codel.x)),
+                    ("y", Json::number(codel.y)),
+                    ("count", Json::number(count)),
+                ])
+            })
+            .collect();
+        Json::object(vec![
+            ("format", Json::string(FORMAT)),
+            ("version", Json::number(FORMAT_VERSION)),
+            (
+                "image",
+                Json::object(vec![
+                    ("hash", Json::string(&self.image_hash)),
+                    ("codel_size", Json::number(machine.codel_size)),
+                ]),
+            ),
+            ("runs", Json::number(self.runs)),
+            ("blocks", Json::Array(blocks)),
+        ])
+    }
+
+    /// Reads the counts of a coverage file for the program of `machine`.
+    pub fn from_json(json: &Json, machine: &Machine) -> Result<Coverage, String> {
+        let field = |key: &str| json.get(key).ok_or(format!("missing `{}`", key));
+        let number = |value: &Json, key: &str| {
+            value
+                .as_i64()
+                .filter(|n| *n >= 0)
+                .ok_or(format!("`{}` is not a positive integer", key))
+        };
+
+        if json.get("format").and_then(Json::as_str) != Some(FORMAT) {
+            return Err(String::from("not a pint coverage file"));
+        }
+        let version = number(field("version")?, "version")?;
+        if version > FORMAT_VERSION {
+            return Err(format!(
+                "coverage file version {} is newer than the supported version {}",
+                version, FORMAT_VERSION
+            ));
+        }
+
+        let image = field("image")?;
+        let hash = image
+            .get("hash")
+            .and_then(Json::as_str)
+            .ok_or("`image.hash` is not a string")?;
+        if hash != image_hash(&machine.rgb_img) {
+            return Err(String::from("the coverage is for a different image"));
+        }
+        let codel_size = image.get("codel_size").and_then(Json::as_i64);
+        if codel_size != Some(machine.codel_size as i64) {
+            return Err(String::from(
+                "the coverage was collected with a different codel size",
+            ));
+        }
+
+        let mut counts = vec![0; machine.blocks.blocks.len()];
+        let blocks = field("blocks")?
+            .as_array()
+            .ok_or("`blocks` is not an array")?;
+        for block in blocks {
+            let get = |key: &str| {
+                block
+                    .get(key)
+                    .ok_or(format!("missing `blocks.{}`", key))
+                    .and_then(|value| number(value, &format!("blocks.{}", key)))
+            };
+            let codel = Coordinates {
+                x: get("x")? as i32,
+                y: get("y")? as i32,
+            };
+            let id = machine
+                .blocks
+                .block_id(codel)
+                .ok_or(format!("there is no block at ({}, {})", codel.x, codel.y))?;
+            counts[id] += get("count")? as u64;
+        }
+
+        Ok(Coverage {
+            image_hash: String::from(hash),
+            runs: number(field("runs")?, "runs")? as u64,
+            counts,
+        })
+    }
+}
+
+// faded towards gray if the block was never entered, mixed with yellow to red by how often it was
+fn shade(RGB(r, g, b): RGB, count: u64, max: u64) -> RGB {
+    if count == 0 {
+        let fade = |c: u8| c / 4 + 96;
+        return RGB(fade(r), fade(g), fade(b));
+    }
+    // on a log scale, a loop easily runs a thousand times more often than the rest
+    let heat = ((count as f64).ln_1p() / (max as f64).ln_1p()).min(1.0);
+    let mix = |c: u8, tint: f64| ((c as f64 + tint) / 2.0).round() as u8;
+    RGB(mix(r, 255.0), mix(g, 255.0 * (1.0 - heat)), mix(b, 0.0))
+}
@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod tests {
+    use crate::coverage::*;
+    use crate::test_support::hello_world;
+
+    fn run(machine: &mut Machine) -> Coverage {
+        let mut coverage = Coverage::new(machine);
+        let limits = Limits {
+            max_steps: None,
+            timeout: None,
+        };
+        machine
+            .run(&limits, |machine, step| coverage.record(machine, step))
+            .unwrap();
+        coverage
+    }
+
+    fn id(machine: &Machine, x: i32, y: i32) -> usize {
+        machine.blocks.block_id(Coordinates { x, y }).unwrap()
+    }
+
+    #[test]
+    fn counts_entered_blocks() {
+        let mut machine = hello_world();
+        let coverage = run(&mut machine);
+
+        // the block the program starts in and the first one it enters
+        assert_eq!(coverage.count(id(&machine, 0, 0)), 1);
+        assert_eq!(coverage.count(id(&machine, 11, 0)), 1);
+        let total: u64 = (0..machine.blocks.blocks.len())
+            .map(|id| coverage.count(id))
+            .sum();
+        assert!(total <= machine.steps + 1);
+    }
+
+    #[test]
+    fn shades_blocks_by_count() {
+        let mut machine = hello_world();
+        let coverage = run(&mut machine);
+        let image = coverage.render(&machine);
+
+        assert_eq!(image.len(), machine.rgb_img.len());
+        let shaded = |x: i32, y: i32| image[y as usize * 5][x as usize * 5];
+        let original = |x: i32, y: i32| machine.rgb_img[y as usize * 5][x as usize * 5];
+        // red, entered once like every other block, stays red
+        assert_eq!(original(0, 0), RGB(255, 0, 0));
+        assert_eq!(shaded(0, 0), RGB(255, 0, 0));
+        let unvisited = (0..machine.blocks.blocks.len())
+            .find(|id| coverage.count(*id) == 0)
+            .unwrap();
+        let codel = machine.blocks.blocks[unvisited].top_left();
+        let RGB(r, g, b) = original(codel.x, codel.y);
+        assert_eq!(
+            shaded(codel.x, codel.y),
+            RGB(r / 4 + 96, g / 4 + 96, b / 4 + 96)
+        );
+    }
+
+    #[test]
+    fn merges_runs_through_json() {
+        let mut machine = hello_world();
+        let mut coverage = run(&mut machine);
+        let saved: Json = coverage.to_json(&machine).to_string().parse().unwrap();
+        let earlier = Coverage::from_json(&saved, &machine).unwrap();
+        assert_eq!(earlier, coverage);
+
+        coverage.merge(&earlier).unwrap();
+        assert_eq!(coverage.runs, 2);
+        assert_eq!(coverage.count(id(&machine, 0, 0)), 2);
+
+        let other = Coverage {
+            image_hash: String::from("fnv1a64:0000000000000000"),
+            ..earlier
+        };
+        assert!(coverage.merge(&other).is_err());
+    }
+}
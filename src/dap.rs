@@ -0,0 +1,566 @@
+use std::io::{self, BufRead, Cursor, Write};
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::thread;
+
+use crate::debugger::*;
+use crate::interpreter::*;
+use crate::json::Json;
+use crate::machine::Machine;
+use crate::piet_io::*;
+use crate::types::*;
+
+mod tests;
+
+/// Loads the program given in the `launch` request, with the input it reads from and writes to.
+pub type Loader = Box<dyn Fn(&str, Io) -> Result<Machine, String>>;
+
+// the program only has one thread of execution
+const THREAD_ID: i64 = 1;
+// steps run between looking for new requests, so `pause` is noticed while running
+const BATCH_STEPS: usize = 10_000;
+const STACK_SCOPE: i64 = 1;
+const REGISTERS_SCOPE: i64 = 2;
+
+/// Reads a message framed by a `Content-Length` header, `None` at the end of the input.
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Json>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim();
+        if header.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let mut body = vec![0; length.unwrap()];
+    reader.read_exact(&mut body)?;
+    String::from_utf8_lossy(&body)
+        .parse()
+        .map(Some)
+        .map_err(|why| io::Error::new(io::ErrorKind::InvalidData, why))
+}
+
+pub fn write_message<W: Write>(writer: &mut W, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+/// Reads messages on another thread, so requests like `pause` arrive while the program runs.
+pub fn spawn_reader<R: BufRead + Send + 'static>(mut reader: R) -> Receiver<Json> {
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        while let Ok(Some(message)) = read_message(&mut reader) {
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+/// A Debug Adapter Protocol server for a single program.
+/// Lines and columns of breakpoints and stack frames are codel coordinates.
+pub struct Adapter<W: Write> {
+    out: W,
+    seq: i64,
+    load: Loader,
+    debugger: Option<Debugger>,
+    program: String,
+    /// Where the program writes to, sent as `output` events.
+    output: SharedBuffer,
+    /// How much of the output was sent already.
+    sent: usize,
+    /// Lines and columns start at 1 unless the client says otherwise.
+    line_base: i32,
+    column_base: i32,
+    stop_on_entry: bool,
+    running: bool,
+}
+
+impl<W: Write> Adapter<W> {
+    pub fn new(out: W, load: Loader) -> Adapter<W> {
+        Adapter {
+            out,
+            seq: 0,
+            load,
+            debugger: None,
+            program: String::new(),
+            output: SharedBuffer::default(),
+            sent: 0,
+            line_base: 1,
+            column_base: 1,
+            stop_on_entry: false,
+            running: false,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Handles requests until the client disconnects.
+    pub fn serve(&mut self, requests: Receiver<Json>) {
+        loop {
+            let request = if self.running {
+                self.run_batch();
+                match requests.try_recv() {
+                    Ok(request) => request,
+                    Err(TryRecvError::Empty) => continue,
+                    Err(TryRecvError::Disconnected) => break,
+                }
+            } else {
+                match requests.recv() {
+                    Ok(request) => request,
+                    Err(_) => break,
+                }
+            };
+            if !self.handle(&request) {
+                break;
+            }
+        }
+    }
+
+    fn send(&mut self, mut fields: Vec<(&str, Json)>) {
+        self.seq += 1;
+        fields.insert(0, ("seq", Json::number(self.seq)));
+        // a client that went away can't be told about it
+        let _ = write_message(&mut self.out, &Json::object(fields));
+    }
+
+    fn event(&mut self, event: &str, body: Json) {
+        self.send(vec![
+            ("type", Json::string("event")),
+            ("event", Json::string(event)),
+            ("body", body),
+        ]);
+    }
+
+    fn respond(&mut self, request: &Json, result: Result<Json, String>) {
+        let mut fields = vec![
+            ("type", Json::string("response")),
+            (
+                "request_seq",
+                request.get("seq").cloned().unwrap_or(Json::Null),
+            ),
+            ("success", Json::Bool(result.is_ok())),
+            (
+                "command",
+                request.get("command").cloned().unwrap_or(Json::Null),
+            ),
+        ];
+        match result {
+            Ok(body) => fields.push(("body", body)),
+            Err(message) => fields.push(("message", Json::string(message))),
+        }
+        self.send(fields);
+    }
+
+    fn stopped(&mut self, reason: &str) {
+        self.running = false;
+        self.event(
+            "stopped",
+            Json::object(vec![
+                ("reason", Json::string(reason)),
+                ("threadId", Json::number(THREAD_ID)),
+                ("allThreadsStopped", Json::Bool(true)),
+            ]),
+        );
+    }
+
+    fn send_output(&mut self) {
+        let contents = self.output.contents();
+        if contents.len() > self.sent {
+            let text = String::from_utf8_lossy(&contents[self.sent..]).into_owned();
+            self.sent = contents.len();
+            self.event(
+                "output",
+                Json::object(vec![
+                    ("category", Json::string("stdout")),
+                    ("output", Json::string(text)),
+                ]),
+            );
+        }
+    }
+
+    fn terminated(&mut self, error: Option<String>) {
+        self.running = false;
+        self.send_output();
+        if let Some(why) = &error {
+            self.event(
+                "output",
+                Json::object(vec![
+                    ("category", Json::string("stderr")),
+                    ("output", Json::string(format!("pint: {}\n", why))),
+                ]),
+            );
+        }
+        let code = if error.is_some() { 1 } else { 0 };
+        self.event(
+            "exited",
+            Json::object(vec![("exitCode", Json::number(code))]),
+        );
+        self.event("terminated", Json::object(vec![]));
+    }
+
+    // one step and what the client is told about it
+    fn step(&mut self, back: bool) {
+        let debugger = match &mut self.debugger {
+            Some(debugger) => debugger,
+            None => return,
+        };
+        let result = if back {
+            Ok(debugger.step_back_once())
+        } else {
+            debugger.step_once()
+        };
+        match result {
+            Ok(_) if back => self.stopped("step"),
+            Ok(true) => {
+                self.send_output();
+                self.stopped("step");
+            }
+            Ok(false) => self.terminated(None),
+            Err(why) => self.terminated(Some(why)),
+        }
+    }
+
+    fn run_batch(&mut self) {
+        let debugger = match &mut self.debugger {
+            Some(debugger) => debugger,
+            None => return,
+        };
+        for _ in 0..BATCH_STEPS {
+            match debugger.step_once() {
+                Ok(true) => (),
+                Ok(false) => return self.terminated(None),
+                Err(why) => return self.terminated(Some(why)),
+            }
+            if debugger.hit_breakpoint().is_some() {
+                self.send_output();
+                return self.stopped("breakpoint");
+            }
+        }
+        self.send_output();
+    }
+
+    fn reverse_continue(&mut self) {
+        if let Some(debugger) = &mut self.debugger {
+            let mut reason = "entry";
+            while debugger.step_back_once() {
+                if debugger.hit_breakpoint().is_some() {
+                    reason = "breakpoint";
+                    break;
+                }
+            }
+            self.stopped(reason);
+        }
+    }
+
+    /// Handles a single request, `false` once the session is over.
+    pub fn handle(&mut self, request: &Json) -> bool {
+        let command = request.get("command").and_then(Json::as_str).unwrap_or("");
+        let empty = Json::object(vec![]);
+        let args = request.get("arguments").unwrap_or(&empty);
+
+        match command {
+            "initialize" => {
+                if args.get("linesStartAt1").and_then(Json::as_bool) == Some(false) {
+                    self.line_base = 0;
+                }
+                if args.get("columnsStartAt1").and_then(Json::as_bool) == Some(false) {
+                    self.column_base = 0;
+                }
+                let capabilities = Json::object(vec![
+                    ("supportsConfigurationDoneRequest", Json::Bool(true)),
+                    ("supportsFunctionBreakpoints", Json::Bool(true)),
+                    ("supportsStepBack", Json::Bool(true)),
+                ]);
+                self.respond(request, Ok(capabilities));
+            }
+            "launch" => {
+                let result = self.launch(args);
+                let launched = result.is_ok();
+                self.respond(request, result);
+                if launched {
+                    self.event("initialized", Json::object(vec![]));
+                }
+            }
+            "configurationDone" => {
+                self.respond(request, Ok(Json::object(vec![])));
+                if self.stop_on_entry {
+                    self.stopped("entry");
+                } else {
+                    self.running = true;
+                }
+            }
+            "setBreakpoints" => {
+                let result = self.set_breakpoints(args);
+                self.respond(request, result);
+            }
+            "setFunctionBreakpoints" => {
+                let result = self.set_function_breakpoints(args);
+                self.respond(request, result);
+            }
+            "setExceptionBreakpoints" => {
+                self.respond(request, Ok(Json::object(vec![])));
+            }
+            "threads" => {
+                let thread = Json::object(vec![
+                    ("id", Json::number(THREAD_ID)),
+                    ("name", Json::string("main")),
+                ]);
+                let body = Json::object(vec![("threads", Json::Array(vec![thread]))]);
+                self.respond(request, Ok(body));
+            }
+            "stackTrace" => {
+                let result = self.stack_trace();
+                self.respond(request, result);
+            }
+            "scopes" => {
+                let scope = |name: &str, reference: i64| {
+                    Json::object(vec![
+                        ("name", Json::string(name)),
+                        ("variablesReference", Json::number(reference)),
+                        ("expensive", Json::Bool(false)),
+                    ])
+                };
+                let mut registers = scope("Registers", REGISTERS_SCOPE);
+                if let Json::Object(fields) = &mut registers {
+                    fields.push((String::from("presentationHint"), Json::string("registers")));
+                }
+                let body = Json::object(vec![(
+                    "scopes",
+                    Json::Array(vec![scope("Stack", STACK_SCOPE), registers]),
+                )]);
+                self.respond(request, Ok(body));
+            }
+            "variables" => {
+                let result = self.variables(args);
+                self.respond(request, result);
+            }
+            "continue" => {
+                let body = Json::object(vec![("allThreadsContinued", Json::Bool(true))]);
+                self.respond(request, Ok(body));
+                self.running = true;
+            }
+            "next" | "stepIn" | "stepOut" | "stepBack" => {
+                self.respond(request, Ok(Json::object(vec![])));
+                self.step(command == "stepBack");
+            }
+            "reverseContinue" => {
+                self.respond(request, Ok(Json::object(vec![])));
+                self.reverse_continue();
+            }
+            "pause" => {
+                self.respond(request, Ok(Json::object(vec![])));
+                if self.running {
+                    self.stopped("pause");
+                }
+            }
+            "evaluate" => {
+                // expressions are debugger commands, like `stack` or `push 3`
+                let line = args.get("expression").and_then(Json::as_str).unwrap_or("");
+                let result = match &mut self.debugger {
+                    Some(debugger) => debugger.execute_line(line).ok_or(()),
+                    None => Ok(String::from("no program is running")),
+                };
+                let result = result.unwrap_or_else(|_| String::from("use disconnect to quit"));
+                let body = Json::object(vec![
+                    ("result", Json::string(result)),
+                    ("variablesReference", Json::number(0)),
+                ]);
+                self.respond(request, Ok(body));
+            }
+            "disconnect" | "terminate" => {
+                self.respond(request, Ok(Json::object(vec![])));
+                self.send_output();
+                return false;
+            }
+            _ => {
+                let why = format!("unsupported request `{}`", command);
+                self.respond(request, Err(why));
+            }
+        }
+        true
+    }
+
+    fn launch(&mut self, args: &Json) -> Result<Json, String> {
+        let program = args
+            .get("program")
+            .and_then(Json::as_str)
+            .ok_or("launch needs the path of the program")?;
+        // the input of the adapter is taken by the protocol
+        let input = args.get("input").and_then(Json::as_str).unwrap_or("");
+        let io = Io::new(
+            Box::new(Cursor::new(input.as_bytes().to_vec())),
+            Box::new(self.output.clone()),
+        );
+        let machine = (self.load)(program, io)?;
+        self.debugger = Some(Debugger::new(machine));
+        self.program = program.to_string();
+        self.stop_on_entry = args.get("stopOnEntry").and_then(Json::as_bool) == Some(true);
+        Ok(Json::object(vec![]))
+    }
+
+    fn set_breakpoints(&mut self, args: &Json) -> Result<Json, String> {
+        let (line_base, column_base) = (self.line_base, self.column_base);
+        let debugger = self.debugger.as_mut().ok_or("no program is running")?;
+        let (width, height) = (
+            debugger.machine.rgb_img[0].len() as i32 / debugger.machine.codel_size,
+            debugger.machine.rgb_img.len() as i32 / debugger.machine.codel_size,
+        );
+
+        debugger
+            .breakpoints
+            .retain(|bp| matches!(bp, Breakpoint::Command(_)));
+        let mut verified = Vec::new();
+        for bp in args
+            .get("breakpoints")
+            .and_then(Json::as_array)
+            .unwrap_or(&[])
+        {
+            let line = bp.get("line").and_then(Json::as_i64).unwrap_or(0) as i32;
+            // without a column the first codel of the line is meant
+            let column = bp
+                .get("column")
+                .and_then(Json::as_i64)
+                .map_or(column_base, |c| c as i32);
+            let codel = Coordinates {
+                x: column - column_base,
+                y: line - line_base,
+            };
+            let inside = (0..width).contains(&codel.x) && (0..height).contains(&codel.y);
+            if inside {
+                debugger.breakpoints.push(Breakpoint::Codel(codel));
+            }
+            verified.push(Json::object(vec![
+                ("verified", Json::Bool(inside)),
+                ("line", Json::number(line)),
+                ("column", Json::number(column)),
+            ]));
+        }
+        Ok(Json::object(vec![("breakpoints", Json::Array(verified))]))
+    }
+
+    fn set_function_breakpoints(&mut self, args: &Json) -> Result<Json, String> {
+        let debugger = self.debugger.as_mut().ok_or("no program is running")?;
+        debugger
+            .breakpoints
+            .retain(|bp| matches!(bp, Breakpoint::Codel(_)));
+        let mut verified = Vec::new();
+        for bp in args
+            .get("breakpoints")
+            .and_then(Json::as_array)
+            .unwrap_or(&[])
+        {
+            let name = bp.get("name").and_then(Json::as_str).unwrap_or("");
+            let command = Command::from_name(name);
+            if let Some(command) = command {
+                debugger.breakpoints.push(Breakpoint::Command(command));
+            }
+            let mut result = vec![("verified", Json::Bool(command.is_some()))];
+            if command.is_none() {
+                result.push((
+                    "message",
+                    Json::string(format!("{} is not a command", name)),
+                ));
+            }
+            verified.push(Json::object(result));
+        }
+        Ok(Json::object(vec![("breakpoints", Json::Array(verified))]))
+    }
+
+    fn stack_trace(&self) -> Result<Json, String> {
+        let debugger = self.debugger.as_ref().ok_or("no program is running")?;
+        let machine = &debugger.machine;
+        let codel = machine.codel();
+        let next = match machine.peek() {
+            Some((_, command)) => command.name(),
+            None => "terminate",
+        };
+        let name = self
+            .program
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or(&self.program);
+        let frame = Json::object(vec![
+            ("id", Json::number(1)),
+            (
+                "name",
+                Json::string(format!(
+                    "{} block, next: {}",
+                    color_name(machine.current_color.color),
+                    next
+                )),
+            ),
+            (
+                "source",
+                Json::object(vec![
+                    ("name", Json::string(name)),
+                    ("path", Json::string(&self.program)),
+                ]),
+            ),
+            ("line", Json::number(codel.y + self.line_base)),
+            ("column", Json::number(codel.x + self.column_base)),
+        ]);
+        Ok(Json::object(vec![
+            ("stackFrames", Json::Array(vec![frame])),
+            ("totalFrames", Json::number(1)),
+        ]))
+    }
+
+    fn variables(&self, args: &Json) -> Result<Json, String> {
+        let debugger = self.debugger.as_ref().ok_or("no program is running")?;
+        let machine = &debugger.machine;
+        let variable = |name: String, value: String| {
+            Json::object(vec![
+                ("name", Json::string(name)),
+                ("value", Json::string(value)),
+                ("variablesReference", Json::number(0)),
+            ])
+        };
+
+        let variables = match args.get("variablesReference").and_then(Json::as_i64) {
+            // the top of the stack comes first
+            Some(STACK_SCOPE) => machine
+                .interp
+                .stack
+                .iter()
+                .rev()
+                .enumerate()
+                .map(|(i, n)| variable(i.to_string(), n.to_string()))
+                .collect(),
+            Some(REGISTERS_SCOPE) => {
+                let codel = machine.codel();
+                vec![
+                    variable(String::from("dp"), format!("{:?}", machine.interp.dp)),
+                    variable(String::from("cc"), format!("{:?}", machine.interp.cc)),
+                    variable(
+                        String::from("position"),
+                        format!("({}, {})", codel.x, codel.y),
+                    ),
+                    variable(
+                        String::from("block"),
+                        format!(
+                            "{}/{}",
+                            color_name(machine.current_color.color),
+                            machine.current_color.size
+                        ),
+                    ),
+                    variable(String::from("steps"), machine.steps.to_string()),
+                ]
+            }
+            _ => Vec::new(),
+        };
+        Ok(Json::object(vec![("variables", Json::Array(variables))]))
+    }
+}
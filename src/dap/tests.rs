@@ -0,0 +1,255 @@
+#[cfg(test)]
+mod tests {
+    use crate::dap::*;
+    use crate::interpreter::Interpreter;
+    use crate::json::Json;
+    use crate::machine::Machine;
+    use crate::test_support::load_image;
+    use crate::types::RGB;
+    use std::fs::metadata;
+    use std::io::Cursor;
+    use std::sync::mpsc::channel;
+
+    fn load(path: &str, io: Io) -> Result<Machine, String> {
+        if path == "loop" {
+            // light red and light magenta, in_char and add forever
+            let rgb_img = vec![vec![RGB(255, 192, 192), RGB(255, 192, 255)]];
+            return Ok(Machine::new(rgb_img, 1, Interpreter::new(io)));
+        }
+        // a missing file fails the launch, not the test
+        metadata(path).map_err(|why| why.to_string())?;
+        let (rgb_img, codel_size) = load_image(path);
+        Ok(Machine::new(rgb_img, codel_size, Interpreter::new(io)))
+    }
+
+    fn request(command: &str, arguments: Json) -> Json {
+        Json::object(vec![
+            ("type", Json::string("request")),
+            ("command", Json::string(command)),
+            ("arguments", arguments),
+        ])
+    }
+
+    fn args(json: &str) -> Json {
+        json.parse().unwrap()
+    }
+
+    // starts a session for `program`, runs the requests and returns every message sent back
+    fn session(program: &str, requests: Vec<Json>) -> Vec<Json> {
+        let (sender, receiver) = channel();
+        let launch = format!(r#"{{"program":"{}","stopOnEntry":true}}"#, program);
+        let mut script = vec![
+            request("initialize", args(r#"{"adapterID":"pint"}"#)),
+            request("launch", args(&launch)),
+        ];
+        script.extend(requests);
+        for (i, mut message) in script.into_iter().enumerate() {
+            if let Json::Object(fields) = &mut message {
+                fields.insert(0, (String::from("seq"), Json::number(i + 1)));
+            }
+            sender.send(message).unwrap();
+        }
+        drop(sender);
+
+        let mut adapter = Adapter::new(Vec::new(), Box::new(load));
+        adapter.serve(receiver);
+        let mut out = Cursor::new(adapter.into_inner());
+        let mut messages = Vec::new();
+        while let Some(message) = read_message(&mut out).unwrap() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    fn events<'a>(messages: &'a [Json], name: &str) -> Vec<&'a Json> {
+        messages
+            .iter()
+            .filter(|m| m.get("event").and_then(Json::as_str) == Some(name))
+            .collect()
+    }
+
+    fn response<'a>(messages: &'a [Json], command: &str) -> &'a Json {
+        messages
+            .iter()
+            .rev()
+            .find(|m| m.get("command").and_then(Json::as_str) == Some(command))
+            .unwrap()
+    }
+
+    fn body<'a>(message: &'a Json, key: &str) -> &'a Json {
+        message.get("body").and_then(|b| b.get(key)).unwrap()
+    }
+
+    #[test]
+    fn frames_messages_with_content_length() {
+        let mut buffer = Vec::new();
+        let message = Json::object(vec![("seq", Json::number(1)), ("ä", Json::Null)]);
+        write_message(&mut buffer, &message).unwrap();
+        write_message(&mut buffer, &message).unwrap();
+        // the length is in bytes, not characters
+        assert!(buffer.starts_with(b"Content-Length: 19\r\n\r\n{"));
+
+        let mut reader = Cursor::new(buffer);
+        assert_eq!(read_message(&mut reader).unwrap(), Some(message.clone()));
+        assert_eq!(read_message(&mut reader).unwrap(), Some(message));
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+    #[test]
+    fn stops_at_codel_breakpoint() {
+        let messages = session(
+            "tests/fixtures/piet_hello_world.png",
+            vec![
+                request(
+                    "setBreakpoints",
+                    args(r#"{"breakpoints":[{"line":3,"column":15},{"line":300}]}"#),
+                ),
+                request("configurationDone", Json::Null),
+                request("continue", args(r#"{"threadId":1}"#)),
+                request("stackTrace", args(r#"{"threadId":1}"#)),
+                request("disconnect", Json::Null),
+            ],
+        );
+
+        assert!(messages[0]
+            .get("body")
+            .unwrap()
+            .get("supportsStepBack")
+            .is_some());
+        assert_eq!(events(&messages, "initialized").len(), 1);
+        let verified: Vec<_> = body(response(&messages, "setBreakpoints"), "breakpoints")
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|bp| bp.get("verified").and_then(Json::as_bool).unwrap())
+            .collect();
+        assert_eq!(verified, [true, false]);
+
+        let stops = events(&messages, "stopped");
+        let reasons: Vec<_> = stops
+            .iter()
+            .map(|e| body(e, "reason").as_str().unwrap())
+            .collect();
+        assert_eq!(reasons, ["entry", "breakpoint"]);
+
+        // the magenta block containing (14, 2) was entered at (12, 0)
+        let frame = &body(response(&messages, "stackTrace"), "stackFrames")
+            .as_array()
+            .unwrap()[0];
+        assert_eq!(frame.get("line").and_then(Json::as_i64), Some(1));
+        assert_eq!(frame.get("column").and_then(Json::as_i64), Some(13));
+    }
+    #[test]
+    fn shows_stack_and_registers_while_stepping() {
+        let messages = session(
+            "tests/fixtures/piet_hello_world.png",
+            vec![
+                request("configurationDone", Json::Null),
+                request("next", args(r#"{"threadId":1}"#)),
+                request("scopes", args(r#"{"frameId":1}"#)),
+                request("variables", args(r#"{"variablesReference":1}"#)),
+                request("variables", args(r#"{"variablesReference":2}"#)),
+                request("stepBack", args(r#"{"threadId":1}"#)),
+                request("evaluate", args(r#"{"expression":"where"}"#)),
+            ],
+        );
+
+        let scopes: Vec<_> = body(response(&messages, "scopes"), "scopes")
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s.get("name").and_then(Json::as_str).unwrap())
+            .collect();
+        assert_eq!(scopes, ["Stack", "Registers"]);
+
+        let variables: Vec<_> = messages
+            .iter()
+            .filter(|m| m.get("command").and_then(Json::as_str) == Some("variables"))
+            .map(|m| {
+                body(m, "variables")
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|v| {
+                        let name = v.get("name").and_then(Json::as_str).unwrap();
+                        let value = v.get("value").and_then(Json::as_str).unwrap();
+                        format!("{}={}", name, value)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(variables[0], ["0=72"]);
+        assert_eq!(
+            variables[1],
+            [
+                "dp=RIGHT",
+                "cc=LEFT",
+                "position=(11, 0)",
+                "block=dark red/1",
+                "steps=1"
+            ]
+        );
+
+        assert_eq!(events(&messages, "stopped").len(), 3);
+        let location = body(response(&messages, "evaluate"), "result");
+        assert!(location.as_str().unwrap().starts_with("step 0 at (0, 0)"));
+    }
+    #[test]
+    fn runs_to_the_end_and_sends_output() {
+        let messages = session(
+            "tests/fixtures/piet_hello_world.png",
+            vec![
+                request(
+                    "setFunctionBreakpoints",
+                    args(r#"{"breakpoints":[{"name":"roll"},{"name":"jump"}]}"#),
+                ),
+                request("configurationDone", Json::Null),
+                request("continue", args(r#"{"threadId":1}"#)),
+            ],
+        );
+
+        let output: String = events(&messages, "output")
+            .iter()
+            .map(|e| body(e, "output").as_str().unwrap())
+            .collect();
+        assert_eq!(output, "Hello world!");
+        assert_eq!(events(&messages, "terminated").len(), 1);
+        assert_eq!(
+            body(events(&messages, "exited")[0], "exitCode").as_i64(),
+            Some(0)
+        );
+    }
+    #[test]
+    fn pauses_running_program() {
+        let messages = session(
+            "loop",
+            vec![
+                request("configurationDone", Json::Null),
+                request("continue", args(r#"{"threadId":1}"#)),
+                request("pause", args(r#"{"threadId":1}"#)),
+                request("variables", args(r#"{"variablesReference":2}"#)),
+            ],
+        );
+
+        let stops = events(&messages, "stopped");
+        assert_eq!(body(stops[1], "reason").as_str(), Some("pause"));
+        let steps = body(response(&messages, "variables"), "variables")
+            .as_array()
+            .unwrap()[4]
+            .get("value")
+            .cloned();
+        assert_eq!(steps, Some(Json::string("10000")));
+    }
+    #[test]
+    fn reports_failed_launch() {
+        let messages = session("missing.png", vec![request("frobnicate", Json::Null)]);
+
+        let launch = response(&messages, "launch");
+        assert_eq!(launch.get("success"), Some(&Json::Bool(false)));
+        assert!(events(&messages, "initialized").is_empty());
+        let unknown = response(&messages, "frobnicate");
+        assert_eq!(
+            unknown.get("message").and_then(Json::as_str),
+            Some("unsupported request `frobnicate`")
+        );
+    }
+}
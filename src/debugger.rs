@@ -0,0 +1,390 @@
+use std::io::{self, Write};
+
+use crate::interpreter::*;
+use crate::machine::*;
+use crate::number::Int;
+use crate::types::*;
+
+mod tests;
+
+const HELP: &str = "\
+step, s [n]          execute the next n steps (default 1)
+continue, c          run until a breakpoint is hit or the program terminates
+step-back, sb [n]    take back the last n steps (default 1), input is read again
+reverse-continue, rc go back until a breakpoint is hit or the start is reached
+goto <n>             go forwards or backwards to step n
+break, b <x> <y>     break when entering the block containing codel (x, y)
+break, b <command>   break before executing a command, e.g. `break roll`
+delete, d [n]        delete breakpoint n or all breakpoints
+breakpoints          list all breakpoints
+stack [values...]    show the stack or replace it with the given values
+push <n>             push a value
+pop                  pop the top value
+                     (going back stops at changes made by hand)
+dp [direction]       show or set the direction pointer (right, down, left, up)
+cc [left|right]      show or set the codel chooser
+block                show the current block
+where, w             show the current position and the next command
+quit, q              quit the debugger
+An empty line repeats the last command.";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// Stops when entering the block containing this codel.
+    Codel(Coordinates),
+    /// Stops before this command is executed.
+    Command(Command),
+}
+
+impl Breakpoint {
+    fn describe(&self) -> String {
+        match self {
+            Breakpoint::Codel(c) => format!("codel ({}, {})", c.x, c.y),
+            Breakpoint::Command(cmd) => format!("command {}", cmd.name()),
+        }
+    }
+}
+
+pub struct Debugger {
+    pub machine: Machine,
+    pub breakpoints: Vec<Breakpoint>,
+    /// Set once the program terminated or stopped with an error.
+    pub finished: bool,
+    last_line: String,
+}
+
+impl Debugger {
+    pub fn new(mut machine: Machine) -> Debugger {
+        machine.record_history();
+        Debugger {
+            machine,
+            breakpoints: Vec::new(),
+            finished: false,
+            last_line: String::new(),
+        }
+    }
+
+    /// Reads commands from the input of the program until it ends or `quit` is entered.
+    pub fn repl(&mut self) {
+        println!("pint debugger, type `help` for a list of commands");
+        println!("{}", self.location());
+        loop {
+            print!("(pint) ");
+            let _ = io::stdout().flush();
+
+            let line = match self.machine.interp.io.read_line() {
+                Ok(Some(line)) => line,
+                _ => break,
+            };
+            match self.execute_line(&line) {
+                Some(output) => println!("{}", output),
+                None => break,
+            }
+        }
+    }
+
+    /// Executes a single debugger command and returns what to print, `None` to quit.
+    pub fn execute_line(&mut self, line: &str) -> Option<String> {
+        let line = if line.trim().is_empty() {
+            self.last_line.clone()
+        } else {
+            line.trim().to_string()
+        };
+        self.last_line = line.clone();
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (cmd, args) = match words.split_first() {
+            Some((cmd, args)) => (*cmd, args),
+            None => return Some(String::new()),
+        };
+        let output = match cmd {
+            "help" | "h" => Ok(String::from(HELP)),
+            "step" | "s" => self.step_command(args),
+            "continue" | "c" => Ok(self.continue_command()),
+            "step-back" | "sb" => self.step_back_command(args),
+            "reverse-continue" | "rc" => Ok(self.reverse_continue_command()),
+            "goto" => self.goto_command(args),
+            "break" | "b" => self.break_command(args),
+            "delete" | "d" => self.delete_command(args),
+            "breakpoints" => Ok(self.list_breakpoints()),
+            "stack" => self.stack_command(args),
+            "push" => self.push_command(args),
+            "pop" => Ok(match self.machine.interp.stack.pop() {
+                Some(n) => {
+                    self.machine.clear_history();
+                    format!("popped {}", n)
+                }
+                None => String::from("stack is empty"),
+            }),
+            "dp" => self.dp_command(args),
+            "cc" => self.cc_command(args),
+            "block" => Ok(self.describe_block()),
+            "where" | "w" => Ok(self.location()),
+            "quit" | "q" => return None,
+            _ => Err(format!("unknown command `{}`, type `help`", cmd)),
+        };
+        Some(output.unwrap_or_else(|why| format!("error: {}", why)))
+    }
+
+    /// The step count, position, DP, CC and the command the next step will execute.
+    pub fn location(&self) -> String {
+        if self.finished {
+            return format!("program finished after {} steps", self.machine.steps);
+        }
+        let codel = self.machine.codel();
+        let next = match self.machine.peek() {
+            Some((_, command)) => command.name(),
+            None => "terminate",
+        };
+        format!(
+            "step {} at ({}, {}) {}/{}, dp {:?}, cc {:?}, next: {}",
+            self.machine.steps,
+            codel.x,
+            codel.y,
+            color_name(self.machine.current_color.color),
+            self.machine.current_color.size,
+            self.machine.interp.dp,
+            self.machine.interp.cc,
+            next
+        )
+    }
+
+    /// Executes a step, `false` once the program can't continue.
+    pub fn step_once(&mut self) -> Result<bool, String> {
+        if self.finished {
+            return Ok(false);
+        }
+        match self.machine.step() {
+            Ok(Some(_)) => Ok(true),
+            Ok(None) => {
+                self.finished = true;
+                Ok(false)
+            }
+            Err(why) => {
+                self.finished = true;
+                Err(why.to_string())
+            }
+        }
+    }
+
+    /// Index of the first breakpoint that matches the current state.
+    pub fn hit_breakpoint(&self) -> Option<usize> {
+        let mut block = None;
+        let next = self.machine.peek();
+        self.breakpoints.iter().position(|bp| match bp {
+            Breakpoint::Codel(c) => {
+                let pixel = Coordinates {
+                    x: c.x * self.machine.codel_size,
+                    y: c.y * self.machine.codel_size,
+                };
+                block
+                    .get_or_insert_with(|| self.machine.current_block())
+                    .contains(&pixel)
+            }
+            Breakpoint::Command(cmd) => next.is_some_and(|(_, n)| n == *cmd),
+        })
+    }
+
+    fn step_command(&mut self, args: &[&str]) -> Result<String, String> {
+        let n = match args.first() {
+            Some(n) => n
+                .parse::<u64>()
+                .map_err(|_| format!("{} is not a number of steps", n))?,
+            None => 1,
+        };
+        for _ in 0..n {
+            if !self.step_once()? {
+                break;
+            }
+        }
+        Ok(self.location())
+    }
+
+    fn continue_command(&mut self) -> String {
+        loop {
+            match self.step_once() {
+                Ok(true) => (),
+                Ok(false) => return self.location(),
+                Err(why) => return format!("error: {}\n{}", why, self.location()),
+            }
+            if let Some(i) = self.hit_breakpoint() {
+                return format!(
+                    "breakpoint {} ({})\n{}",
+                    i + 1,
+                    self.breakpoints[i].describe(),
+                    self.location()
+                );
+            }
+        }
+    }
+
+    /// Takes back a step, `false` at the start of the program.
+    pub fn step_back_once(&mut self) -> bool {
+        let stepped = self.machine.step_back();
+        if stepped {
+            self.finished = false;
+        }
+        stepped
+    }
+
+    fn step_back_command(&mut self, args: &[&str]) -> Result<String, String> {
+        let n = match args.first() {
+            Some(n) => n
+                .parse::<u64>()
+                .map_err(|_| format!("{} is not a number of steps", n))?,
+            None => 1,
+        };
+        if self.machine.steps == 0 {
+            return Err(String::from("already at the start of the program"));
+        }
+        if !self.step_back_once() {
+            return Err(String::from("can't go back past a change made by hand"));
+        }
+        for _ in 1..n {
+            if !self.step_back_once() {
+                break;
+            }
+        }
+        Ok(self.location())
+    }
+
+    fn reverse_continue_command(&mut self) -> String {
+        while self.step_back_once() {
+            if let Some(i) = self.hit_breakpoint() {
+                return format!(
+                    "breakpoint {} ({})\n{}",
+                    i + 1,
+                    self.breakpoints[i].describe(),
+                    self.location()
+                );
+            }
+        }
+        self.location()
+    }
+
+    fn goto_command(&mut self, args: &[&str]) -> Result<String, String> {
+        let target = match args {
+            [n] => n
+                .parse::<u64>()
+                .map_err(|_| format!("{} is not a step number", n))?,
+            _ => return Err(String::from("usage: goto <n>")),
+        };
+        while self.machine.steps > target && self.step_back_once() {}
+        while self.machine.steps < target && self.step_once()? {}
+        Ok(self.location())
+    }
+
+    fn break_command(&mut self, args: &[&str]) -> Result<String, String> {
+        let bp = match args {
+            [x, y] => Breakpoint::Codel(Coordinates {
+                x: x.parse()
+                    .map_err(|_| format!("{} is not a coordinate", x))?,
+                y: y.parse()
+                    .map_err(|_| format!("{} is not a coordinate", y))?,
+            }),
+            [name] => Breakpoint::Command(
+                Command::from_name(name).ok_or(format!("{} is not a command", name))?,
+            ),
+            _ => return Err(String::from("usage: break <x> <y> | break <command>")),
+        };
+        self.breakpoints.push(bp);
+        Ok(format!(
+            "breakpoint {} at {}",
+            self.breakpoints.len(),
+            bp.describe()
+        ))
+    }
+
+    fn delete_command(&mut self, args: &[&str]) -> Result<String, String> {
+        match args.first() {
+            None => {
+                self.breakpoints.clear();
+                Ok(String::from("deleted all breakpoints"))
+            }
+            Some(n) => match n.parse::<usize>() {
+                Ok(i) if i >= 1 && i <= self.breakpoints.len() => {
+                    self.breakpoints.remove(i - 1);
+                    Ok(format!("deleted breakpoint {}", i))
+                }
+                _ => Err(format!("there is no breakpoint {}", n)),
+            },
+        }
+    }
+
+    fn list_breakpoints(&self) -> String {
+        if self.breakpoints.is_empty() {
+            return String::from("no breakpoints");
+        }
+        let lines: Vec<String> = self
+            .breakpoints
+            .iter()
+            .enumerate()
+            .map(|(i, bp)| format!("{}: {}", i + 1, bp.describe()))
+            .collect();
+        lines.join("\n")
+    }
+
+    fn stack_command(&mut self, args: &[&str]) -> Result<String, String> {
+        if !args.is_empty() {
+            self.machine.interp.stack = args
+                .iter()
+                .map(|n| n.parse::<Int>())
+                .collect::<Result<_, _>>()?;
+            self.machine.clear_history();
+        }
+        let stack: Vec<String> = self
+            .machine
+            .interp
+            .stack
+            .iter()
+            .map(|n| n.to_string())
+            .collect();
+        Ok(format!("[{}]", stack.join(", ")))
+    }
+
+    fn push_command(&mut self, args: &[&str]) -> Result<String, String> {
+        let n = match args {
+            [n] => n.parse::<Int>()?,
+            _ => return Err(String::from("usage: push <n>")),
+        };
+        self.machine.interp.stack.push(n);
+        self.machine.clear_history();
+        self.stack_command(&[])
+    }
+
+    fn dp_command(&mut self, args: &[&str]) -> Result<String, String> {
+        if let Some(dir) = args.first() {
+            self.machine.interp.dp = dir.parse()?;
+            self.machine.clear_history();
+        }
+        Ok(format!("dp {:?}", self.machine.interp.dp))
+    }
+
+    fn cc_command(&mut self, args: &[&str]) -> Result<String, String> {
+        if let Some(cc) = args.first() {
+            self.machine.interp.cc = cc.parse()?;
+            self.machine.clear_history();
+        }
+        Ok(format!("cc {:?}", self.machine.interp.cc))
+    }
+
+    fn describe_block(&self) -> String {
+        let block = self.machine.current_block();
+        let codels: Vec<Coordinates> = block.iter().map(|&p| self.machine.to_codel(p)).collect();
+        let min_x = codels.iter().map(|c| c.x).min().unwrap_or(0);
+        let max_x = codels.iter().map(|c| c.x).max().unwrap_or(0);
+        let min_y = codels.iter().map(|c| c.y).min().unwrap_or(0);
+        let max_y = codels.iter().map(|c| c.y).max().unwrap_or(0);
+        format!(
+            "{} block of {} codels, entered at ({}, {}), spanning ({}, {}) to ({}, {})",
+            color_name(self.machine.current_color.color),
+            self.machine.current_color.size,
+            self.machine.codel().x,
+            self.machine.codel().y,
+            min_x,
+            min_y,
+            max_x,
+            max_y
+        )
+    }
+}
@@ -0,0 +1,138 @@
+#[cfg(test)]
+mod tests {
+    use crate::debugger::*;
+    use crate::piet_io::*;
+    use crate::test_support::{load, HELLO_WORLD};
+
+    fn hello_world() -> (Debugger, SharedBuffer) {
+        let (machine, out) = load(HELLO_WORLD);
+        (Debugger::new(machine), out)
+    }
+
+    #[test]
+    fn steps_and_repeats_last_command() {
+        let (mut dbg, _) = hello_world();
+
+        assert_eq!(
+            dbg.execute_line("step").unwrap(),
+            "step 1 at (11, 0) dark red/1, dp RIGHT, cc LEFT, next: out_char"
+        );
+        dbg.execute_line("").unwrap();
+        assert_eq!(dbg.machine.steps, 2);
+        dbg.execute_line("s 3").unwrap();
+        assert_eq!(dbg.machine.steps, 5);
+    }
+    #[test]
+    fn breaks_before_command() {
+        let (mut dbg, out) = hello_world();
+
+        dbg.execute_line("break out_char").unwrap();
+        let stop = dbg.execute_line("continue").unwrap();
+        assert!(stop.starts_with("breakpoint 1 (command out_char)\nstep 1 "));
+        assert_eq!(out.contents(), b"");
+
+        dbg.execute_line("c").unwrap();
+        assert_eq!(out.contents(), b"H");
+        assert_eq!(dbg.machine.steps, 3);
+    }
+    #[test]
+    fn breaks_on_codel_inside_block() {
+        let (mut dbg, _) = hello_world();
+
+        dbg.execute_line("b 14 2").unwrap();
+        let stop = dbg.execute_line("c").unwrap();
+        assert!(stop.starts_with("breakpoint 1 (codel (14, 2))\nstep 2 at (12, 0) magenta"));
+    }
+    #[test]
+    fn runs_to_the_end_without_breakpoints() {
+        let (mut dbg, out) = hello_world();
+
+        dbg.execute_line("b roll").unwrap();
+        dbg.execute_line("delete 1").unwrap();
+        assert!(dbg
+            .execute_line("c")
+            .unwrap()
+            .starts_with("program finished after"));
+        assert_eq!(out.contents(), b"Hello world!");
+        assert!(dbg
+            .execute_line("step")
+            .unwrap()
+            .starts_with("program finished"));
+    }
+    #[test]
+    fn modifies_stack_dp_and_cc() {
+        let (mut dbg, _) = hello_world();
+
+        assert_eq!(dbg.execute_line("stack 1 2 3").unwrap(), "[1, 2, 3]");
+        assert_eq!(dbg.execute_line("push -4").unwrap(), "[1, 2, 3, -4]");
+        assert_eq!(dbg.execute_line("pop").unwrap(), "popped -4");
+        assert_eq!(dbg.execute_line("dp up").unwrap(), "dp UP");
+        assert_eq!(dbg.execute_line("cc right").unwrap(), "cc RIGHT");
+        assert_eq!(dbg.machine.interp.dp, Direction::UP);
+        assert!(dbg.execute_line("push x").unwrap().starts_with("error:"));
+        assert!(dbg.execute_line("quit").is_none());
+    }
+    #[test]
+    fn describes_current_block() {
+        let (mut dbg, _) = hello_world();
+
+        assert_eq!(
+            dbg.execute_line("block").unwrap(),
+            "red block of 72 codels, entered at (0, 0), spanning (0, 0) to (10, 10)"
+        );
+    }
+    #[test]
+    fn steps_back_and_jumps_to_steps() {
+        let (mut dbg, _) = hello_world();
+
+        let at_step_4 = dbg.execute_line("s 4").unwrap();
+        let stack = dbg.machine.interp.stack.clone();
+        dbg.execute_line("c").unwrap();
+        assert!(dbg.finished);
+
+        assert_eq!(dbg.execute_line("goto 4").unwrap(), at_step_4);
+        assert_eq!(dbg.machine.interp.stack, stack);
+        dbg.execute_line("sb 2").unwrap();
+        assert_eq!(dbg.machine.steps, 2);
+        dbg.execute_line("goto 4").unwrap();
+        assert_eq!(dbg.machine.interp.stack, stack);
+        dbg.execute_line("goto 0").unwrap();
+        assert!(dbg.execute_line("step-back").unwrap().starts_with("error:"));
+    }
+    #[test]
+    fn going_back_stops_at_changes_made_by_hand() {
+        let (mut dbg, _) = hello_world();
+
+        dbg.execute_line("s 2").unwrap();
+        dbg.execute_line("stack 7 8 9 10").unwrap();
+        assert_eq!(
+            dbg.execute_line("sb").unwrap(),
+            "error: can't go back past a change made by hand"
+        );
+        assert_eq!(dbg.machine.steps, 2);
+        assert_eq!(dbg.execute_line("stack").unwrap(), "[7, 8, 9, 10]");
+
+        // steps after the change can still be taken back
+        dbg.execute_line("s 2").unwrap();
+        dbg.execute_line("sb 5").unwrap();
+        assert_eq!(dbg.machine.steps, 2);
+        assert_eq!(dbg.execute_line("stack").unwrap(), "[7, 8, 9, 10]");
+    }
+    #[test]
+    fn reverse_continues_to_previous_breakpoint() {
+        let (mut dbg, _) = hello_world();
+
+        dbg.execute_line("b out_char").unwrap();
+        dbg.execute_line("c").unwrap();
+        dbg.execute_line("c").unwrap();
+        let second = dbg.machine.steps;
+        dbg.execute_line("c").unwrap();
+
+        let stop = dbg.execute_line("rc").unwrap();
+        assert!(stop.starts_with("breakpoint 1 (command out_char)"));
+        assert_eq!(dbg.machine.steps, second);
+        dbg.execute_line("delete").unwrap();
+        dbg.execute_line("rc").unwrap();
+        assert_eq!(dbg.machine.steps, 0);
+    }
+}
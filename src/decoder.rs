@@ -293,7 +293,10 @@ fn parse_data(
 }
 
 fn parse_plte(data: Vec<u8>) -> Vec<RGB> {
-    assert!(data.len() % 3 == 0, "data should be splittable in triplets");
+    assert!(
+        data.len().is_multiple_of(3),
+        "data should be splittable in triplets"
+    );
 
     let mut result: Vec<RGB> = Vec::new();
     for i in (0..data.len()).step_by(3) {
@@ -326,7 +329,7 @@ pub fn decode_png(mut file: File) -> Vec<Vec<RGB>> {
             "IHDR" => meta_data = parse_ihdr(chunk.data),
             "PLTE" => plte = Some(parse_plte(chunk.data)),
             "IDAT" => {
-                if meta_data.color_type == ColorType::Indexed && plte == None {
+                if meta_data.color_type == ColorType::Indexed && plte.is_none() {
                     // if color is indexed then it needs a palette
                     panic!("pint: couldn't find PLTE chunk in image.");
                 }
@@ -350,16 +353,29 @@ pub fn decode_png(mut file: File) -> Vec<Vec<RGB>> {
     }
     rgb_img
 }
-pub fn infer_codel_size(rgb_img: &Vec<Vec<RGB>>) -> i32 {
+
+/// The codel size of the image, falls back to 1 with a warning if it can't be inferred.
+pub fn infer_codel_size(rgb_img: &[Vec<RGB>]) -> i32 {
+    match try_infer_codel_size(rgb_img) {
+        Ok(size) => size,
+        Err(why) => {
+            eprintln!("warning: {} => defaults to 1", why);
+            1
+        }
+    }
+}
+
+/// The length of the shortest run of one color, if it divides the size of the image.
+pub fn try_infer_codel_size(rgb_img: &[Vec<RGB>]) -> Result<i32, String> {
     let mut min_size = i32::MAX;
     let mut current_size = 1i32;
     let mut current_color: RGB;
 
     // go from left to right through img
-    for y in 0..rgb_img.len() {
-        let mut prev_color: RGB = rgb_img[y][0];
-        for x in 1..rgb_img[0].len() {
-            current_color = rgb_img[y][x];
+    for row in rgb_img {
+        let mut prev_color: RGB = row[0];
+        for &pixel in &row[1..] {
+            current_color = pixel;
             if prev_color == current_color {
                 current_size += 1;
             } else if current_size < min_size {
@@ -372,8 +388,8 @@ pub fn infer_codel_size(rgb_img: &Vec<Vec<RGB>>) -> i32 {
     // go from top to bottom through img
     for x in 0..rgb_img[0].len() {
         let mut prev_color: RGB = rgb_img[0][x];
-        for y in 1..rgb_img.len() {
-            current_color = rgb_img[y][x];
+        for row in &rgb_img[1..] {
+            current_color = row[x];
             if prev_color == current_color {
                 current_size += 1;
             } else if current_size < min_size {
@@ -384,12 +400,12 @@ pub fn infer_codel_size(rgb_img: &Vec<Vec<RGB>>) -> i32 {
         }
     }
     if min_size < 1 {
-        eprintln!("warning: inferred codel-size less than 1 => defaults to 1");
-        1
+        Err(String::from("inferred codel-size less than 1"))
     } else if rgb_img[0].len() as i32 % min_size != 0 || rgb_img.len() as i32 % min_size != 0 {
-        eprintln!("warning: inferred codel-size doesnt fit image-dimensions => defaults to 1");
-        1
+        Err(String::from(
+            "inferred codel-size doesnt fit image-dimensions",
+        ))
     } else {
-        min_size
+        Ok(min_size)
     }
 }
@@ -87,13 +87,13 @@ mod tests {
     }
     #[test]
     fn can_convert_multibyte_arr_to_int() {
-        let buf = vec![0b0, 0b0, 0b0000_0010, 0b1001_1011];
+        let buf = [0b0, 0b0, 0b0000_0010, 0b1001_1011];
         let result = bytes_to_int(&buf[..]);
         assert_eq!(result, 667);
     }
     #[test]
     fn can_convert_single_byte_arr_to_int() {
-        let buf = vec![0b0, 0b0, 0b0, 0b1001_1011];
+        let buf = [0b0, 0b0, 0b0, 0b1001_1011];
         let result = bytes_to_int(&buf[..]);
         assert_eq!(result, 155);
     }
@@ -137,9 +137,11 @@ mod tests {
             153, 16, 207, 132, 120, 38, 196, 51, 33, 158, 9, 241, 76, 136, 103, 66, 60, 19, 226,
             153, 16, 207, 132, 120, 38, 196, 251, 1, 201, 164, 87, 175,
         ]];
-        let mut meta = IHDRData::default();
-        meta.width = 150;
-        meta.color_type = ColorType::TrueColorRGB;
+        let meta = IHDRData {
+            width: 150,
+            color_type: ColorType::TrueColorRGB,
+            ..IHDRData::default()
+        };
         let result = parse_data(idat, meta, None, RGBorU8::Rgb(RGB(0, 0, 0)));
 
         // println!("{:?}",result);
@@ -201,9 +203,11 @@ mod tests {
             RGB(255, 192, 255),
             RGB(192, 0, 192),
         ]);
-        let mut meta = IHDRData::default();
-        meta.width = 110;
-        meta.color_type = ColorType::Indexed;
+        let meta = IHDRData {
+            width: 110,
+            color_type: ColorType::Indexed,
+            ..IHDRData::default()
+        };
         let result = parse_data(idat, meta, plte, RGBorU8::U8(0));
 
         // result is too big so its stored in temp-file
@@ -273,9 +277,11 @@ mod tests {
             RGB(255, 255, 192),
             RGB(255, 255, 255),
         ]);
-        let mut meta = IHDRData::default();
-        meta.width = 115;
-        meta.color_type = ColorType::Indexed;
+        let meta = IHDRData {
+            width: 115,
+            color_type: ColorType::Indexed,
+            ..IHDRData::default()
+        };
         let result = parse_data(idat, meta, plte, RGBorU8::U8(0));
 
         // result is too big so its stored in temp-file
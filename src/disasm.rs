@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::graph::*;
+use crate::interpreter::*;
+use crate::types::*;
+
+mod tests;
+
+/// A command executed when the program leaves the node of `state`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub command: Command,
+    /// The value pushed by `push`, the size of the block that is left.
+    pub size: i32,
+    pub state: State,
+}
+
+/// How a basic block ends, targets are indices into `Listing::blocks`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Exit {
+    Halt,
+    Jump(usize),
+    /// After `pointer` (four targets, value mod 4) or `switch` (two targets, value mod 2).
+    Branch(Vec<usize>),
+}
+
+/// A straight run of states that is only entered at its first state.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub entry: State,
+    /// Everything that is executed, without the steps through white.
+    pub instructions: Vec<Instruction>,
+    pub exit: Exit,
+}
+
+/// The reachable part of a program as basic blocks, the first one is where the program starts.
+pub struct Listing<'a> {
+    graph: &'a Graph,
+    pub blocks: Vec<BasicBlock>,
+}
+
+impl<'a> Listing<'a> {
+    pub fn new(graph: &'a Graph) -> Listing<'a> {
+        let reachable = graph.reachable();
+
+        // a state starts a basic block if it's the start, the target of a branch
+        // or can be entered from more than one state, branch targets are counted twice for that
+        let mut entered: HashMap<State, usize> = HashMap::new();
+        for state in &reachable {
+            let targets = graph.successors(*state);
+            for next in &targets {
+                let count = entered.entry(*next).or_insert(0);
+                *count += if targets.len() > 1 { 2 } else { 1 };
+            }
+        }
+        let leaders: Vec<State> = reachable
+            .iter()
+            .copied()
+            .filter(|state| *state == graph.start() || entered[state] > 1)
+            .collect();
+        let labels: HashMap<State, usize> = leaders
+            .iter()
+            .enumerate()
+            .map(|(label, state)| (*state, label))
+            .collect();
+
+        let blocks = leaders
+            .iter()
+            .map(|entry| {
+                let mut state = *entry;
+                let mut instructions = Vec::new();
+                let exit = loop {
+                    let edge = match graph.edge(state) {
+                        Some(edge) => edge,
+                        None => break Exit::Halt,
+                    };
+                    if edge.command != Command::None {
+                        instructions.push(Instruction {
+                            command: edge.command,
+                            size: edge.size,
+                            state,
+                        });
+                    }
+                    let targets = edge.targets();
+                    if targets.len() > 1 {
+                        break Exit::Branch(targets.iter().map(|t| labels[t]).collect());
+                    }
+                    state = targets[0];
+                    if let Some(label) = labels.get(&state) {
+                        break Exit::Jump(*label);
+                    }
+                };
+                BasicBlock {
+                    entry: *entry,
+                    instructions,
+                    exit,
+                }
+            })
+            .collect();
+
+        Listing { graph, blocks }
+    }
+
+    fn location(&self, state: State) -> String {
+        let node = &self.graph.nodes[state.node];
+        format!(
+            "({}, {}) {}",
+            node.codels[0].x,
+            node.codels[0].y,
+            color_name(node.color)
+        )
+    }
+}
+
+impl fmt::Display for Listing<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (label, block) in self.blocks.iter().enumerate() {
+            if label > 0 {
+                writeln!(f)?;
+            }
+            writeln!(
+                f,
+                "L{}:  ; {} dp={:?} cc={:?}",
+                label,
+                self.location(block.entry),
+                block.entry.dp,
+                block.entry.cc
+            )?;
+
+            for (i, instruction) in block.instructions.iter().enumerate() {
+                let mut text = String::from(instruction.command.name());
+                if instruction.command == Command::Push {
+                    text += &format!(" {}", instruction.size);
+                }
+                // the branch is the last instruction of its block
+                if let (Exit::Branch(targets), true) =
+                    (&block.exit, i + 1 == block.instructions.len())
+                {
+                    let targets: Vec<String> = targets
+                        .iter()
+                        .enumerate()
+                        .map(|(value, target)| format!("{} -> L{}", value, target))
+                        .collect();
+                    text += &format!(" {}", targets.join(", "));
+                }
+                writeln!(f, "    {:<40} ; {}", text, self.location(instruction.state))?;
+            }
+
+            match block.exit {
+                Exit::Halt => writeln!(f, "    halt")?,
+                Exit::Jump(target) => writeln!(f, "    jmp L{}", target)?,
+                Exit::Branch(_) => (),
+            }
+        }
+        Ok(())
+    }
+}
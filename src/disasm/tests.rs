@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod tests {
+    use crate::blocks::BlockMap;
+    use crate::disasm::*;
+    use crate::test_support::load_image;
+
+    fn compile(path: &str) -> Graph {
+        let (rgb_img, codel_size) = load_image(path);
+        Graph::compile(&BlockMap::new(&rgb_img, codel_size))
+    }
+
+    #[test]
+    fn straight_programs_are_one_block() {
+        let graph = compile("tests/fixtures/piet_hello_world.png");
+        let listing = Listing::new(&graph);
+
+        assert_eq!(listing.blocks.len(), 1);
+        let block = &listing.blocks[0];
+        assert_eq!(block.entry, graph.start());
+        assert_eq!(block.exit, Exit::Halt);
+        // every character is pushed and printed
+        let pushed: Vec<u8> = block
+            .instructions
+            .iter()
+            .filter(|i| i.command == Command::Push)
+            .map(|i| i.size as u8)
+            .collect();
+        assert_eq!(pushed, b"Helo wrd!");
+
+        let text = listing.to_string();
+        assert!(text.starts_with("L0:  ; (0, 0) red dp=RIGHT cc=LEFT\n    push 72 "));
+        assert!(text.ends_with("    halt\n"));
+    }
+    #[test]
+    fn branches_name_their_targets() {
+        let graph = compile("tests/fixtures/fizzbuzz.png");
+        let listing = Listing::new(&graph);
+
+        let pointer = listing
+            .blocks
+            .iter()
+            .find(|b| {
+                b.instructions
+                    .last()
+                    .is_some_and(|i| i.command == Command::Pointer)
+            })
+            .unwrap();
+        match &pointer.exit {
+            Exit::Branch(targets) => assert_eq!(targets.len(), 4),
+            exit => panic!("pointer ends with {:?}", exit),
+        }
+        for block in &listing.blocks {
+            match &block.exit {
+                Exit::Jump(target) => assert!(*target < listing.blocks.len()),
+                Exit::Branch(targets) => {
+                    // the branch goes on from the same node with the DP or CC of the value
+                    let last = block.instructions.last().unwrap();
+                    let next = graph.edge(last.state).unwrap().to;
+                    for target in targets {
+                        assert_eq!(listing.blocks[*target].entry.node, next);
+                    }
+                }
+                Exit::Halt => (),
+            }
+        }
+        assert!(listing
+            .to_string()
+            .contains("    pointer 0 -> L3, 1 -> L4, 2 -> L5, 3 -> L6 ; (12, 0) green\n"));
+    }
+}
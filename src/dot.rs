@@ -0,0 +1,117 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::graph::*;
+use crate::interpreter::*;
+use crate::types::*;
+
+mod tests;
+
+fn state_name(state: State) -> String {
+    format!("{:?}/{:?}", state.dp, state.cc).to_lowercase()
+}
+
+fn node_attributes(node: &Node) -> String {
+    let RGB(r, g, b) = node.color;
+    // light text on dark colors
+    let font = if (r as u32 * 3 + g as u32 * 6 + b as u32) / 10 > 128 {
+        "black"
+    } else {
+        "white"
+    };
+    let corner = node.codels[0];
+    let size = match node.block {
+        Some(_) if node.size == 1 => String::from("1 codel"),
+        Some(_) => format!("{} codels", node.size),
+        None => String::from("no block"),
+    };
+    format!(
+        "label=\"{}\\n{}\\n({}, {})\" style=filled fillcolor=\"#{:02x}{:02x}{:02x}\" fontcolor={}",
+        color_name(node.color),
+        size,
+        corner.x,
+        corner.y,
+        r,
+        g,
+        b,
+        font
+    )
+}
+
+/// The program as graph in the DOT language: a node per color block, labeled with its color,
+/// size and top left codel, and an edge per step between them, labeled with the command and
+/// the DP/CC states it is taken in. Codels outside of blocks only show up where they are entered.
+/// With `reachable_only` only what can be reached from the start is included.
+pub fn to_dot(graph: &Graph, reachable_only: bool) -> String {
+    let states: Vec<State> = if reachable_only {
+        graph.reachable()
+    } else {
+        // all blocks, and whatever is entered from them
+        let mut seen = vec![false; graph.nodes.len()];
+        let mut todo: VecDeque<usize> = (0..graph.nodes.len())
+            .filter(|n| graph.nodes[*n].block.is_some())
+            .collect();
+        for n in &todo {
+            seen[*n] = true;
+        }
+        // the start is queued already unless it is outside of the blocks
+        let start = graph.start().node;
+        if !seen[start] {
+            seen[start] = true;
+            todo.push_back(start);
+        }
+        let mut states = Vec::new();
+        while let Some(node) = todo.pop_front() {
+            for index in node * 8..node * 8 + 8 {
+                let state = State::from_index(index);
+                states.push(state);
+                if let Some(edge) = graph.edge(state) {
+                    if !seen[edge.to] {
+                        seen[edge.to] = true;
+                        todo.push_back(edge.to);
+                    }
+                }
+            }
+        }
+        states
+    };
+
+    // states leading to the same node with the same command share an edge
+    let mut nodes = BTreeMap::new();
+    let mut edges: BTreeMap<(usize, usize, &str), Vec<State>> = BTreeMap::new();
+    for state in states {
+        nodes.insert(state.node, ());
+        if let Some(edge) = graph.edge(state) {
+            nodes.insert(edge.to, ());
+            edges
+                .entry((state.node, edge.to, edge.command.name()))
+                .or_default()
+                .push(state);
+        }
+    }
+
+    let mut dot = String::from("digraph piet {\n    node [shape=box];\n");
+    for node in nodes.keys() {
+        dot.push_str(&format!(
+            "    n{} [{}{}];\n",
+            node,
+            node_attributes(&graph.nodes[*node]),
+            if *node == graph.start().node {
+                " penwidth=3"
+            } else {
+                ""
+            }
+        ));
+    }
+    for ((from, to, command), states) in edges {
+        let states: Vec<String> = states.into_iter().map(state_name).collect();
+        dot.push_str(&format!(
+            "    n{} -> n{} [label=\"{}\\n{}\"];\n",
+            from,
+            to,
+            command,
+            states.join(", ")
+        ));
+    }
+    dot.push_str("}\n");
+    dot
+}
@@ -0,0 +1,45 @@
+#[cfg(test)]
+mod tests {
+    use crate::blocks::BlockMap;
+    use crate::dot::*;
+    use crate::test_support::load_image;
+
+    fn compile(path: &str) -> Graph {
+        let (rgb_img, codel_size) = load_image(path);
+        Graph::compile(&BlockMap::new(&rgb_img, codel_size))
+    }
+
+    #[test]
+    fn blocks_are_nodes_and_steps_are_edges() {
+        let graph = compile("tests/fixtures/piet_hello_world.png");
+        let dot = to_dot(&graph, false);
+
+        assert!(dot.starts_with("digraph piet {\n"));
+        assert!(dot.ends_with("}\n"));
+        let start = graph.start().node;
+        assert!(dot.contains(&format!(
+            "    n{} [label=\"red\\n72 codels\\n(0, 0)\" style=filled fillcolor=\"#ff0000\" fontcolor=white penwidth=3];",
+            start
+        )));
+        let to = graph.edge(graph.start()).unwrap().to;
+        let edge = format!("    n{} -> n{} [", start, to);
+        let line = dot.lines().find(|l| l.starts_with(&edge)).unwrap();
+        assert_eq!(
+            &line[edge.len()..],
+            "label=\"push\\nright/left, right/right, left/left, left/right, up/left, up/right\"];"
+        );
+    }
+    #[test]
+    fn reachable_only_leaves_out_the_rest() {
+        let graph = compile("tests/fixtures/fizzbuzz.png");
+        let all = to_dot(&graph, false);
+        let reachable = to_dot(&graph, true);
+
+        assert!(reachable.lines().count() < all.lines().count());
+        // edges may be taken in fewer states, but lead to the same nodes
+        for line in reachable.lines() {
+            let start = line.split('[').next().unwrap();
+            assert!(all.lines().any(|l| l.starts_with(start)), "{}", line);
+        }
+    }
+}
@@ -0,0 +1,41 @@
+#[cfg(test)]
+mod tests {
+    use crate::decoder::*;
+    use crate::encoder::*;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn decode(png: &[u8]) -> Vec<Vec<RGB>> {
+        let mut tmp = NamedTempFile::new().unwrap();
+        tmp.write_all(png).unwrap();
+        let mut file = File::open(tmp.path()).unwrap();
+        check_valid_png(&mut file);
+        decode_png(file)
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+    #[test]
+    fn decodes_what_it_encodes() {
+        let img = vec![
+            vec![RGB(255, 0, 0), RGB(0, 0, 0), RGB(192, 192, 255)],
+            vec![RGB(255, 255, 255), RGB(0, 192, 0), RGB(255, 0, 0)],
+        ];
+        assert_eq!(decode(&encode_png(&img, 1)), img);
+
+        let scaled = decode(&encode_png(&img, 3));
+        assert_eq!(scaled.len(), 6);
+        assert_eq!(scaled[5][8], RGB(255, 0, 0));
+        assert_eq!(infer_codel_size(&scaled), 3);
+    }
+    #[test]
+    fn splits_big_images_into_blocks() {
+        // more than 65535 bytes of scanlines
+        let img = vec![vec![RGB(0, 0, 255); 200]; 120];
+        assert_eq!(decode(&encode_png(&img, 1)), img);
+    }
+}
@@ -0,0 +1,23 @@
+use crate::assembler::assemble;
+use crate::types::RGB;
+
+mod tests;
+
+/// Stack assembly for a program printing `text`, runs of the same character are pushed once.
+pub fn print_source(text: &str) -> String {
+    let mut source = String::new();
+    let chars: Vec<char> = text.chars().collect();
+    for run in chars.chunk_by(|a, b| a == b) {
+        source.push_str(&format!("push {}\n", run[0] as u32));
+        for _ in 1..run.len() {
+            source.push_str("dup\nout_char\n");
+        }
+        source.push_str("out_char\n");
+    }
+    source
+}
+
+/// A program printing `text` with `out_char`.
+pub fn print_program(text: &str) -> Vec<Vec<RGB>> {
+    assemble(&print_source(text)).expect("generated source assembles")
+}
@@ -0,0 +1,43 @@
+#[cfg(test)]
+mod tests {
+    use crate::blocks::*;
+    use crate::generator::*;
+    use crate::interpreter::*;
+    use crate::machine::*;
+    use crate::piet_io::*;
+    use std::io::empty;
+
+    fn output(img: Vec<Vec<RGB>>) -> Vec<u8> {
+        let out = SharedBuffer::default();
+        let io = Io::new(Box::new(empty()), Box::new(out.clone()));
+        let mut machine = Machine::new(img, 1, Interpreter::new(io));
+        let limits = Limits {
+            max_steps: Some(1_000_000),
+            ..Limits::default()
+        };
+        assert_eq!(machine.run(&limits, |_, _| ()), Ok(Halt::Terminated));
+        machine.interp.io.flush().unwrap();
+        out.contents()
+    }
+
+    #[test]
+    fn prints_the_text() {
+        for text in [
+            "Hello, World!\n",
+            "",
+            "aaa  bb",
+            "grüße, ✓ 🦀",
+            "\0\t\u{10FFFF}",
+        ] {
+            assert_eq!(output(print_program(text)), text.as_bytes(), "{:?}", text);
+        }
+    }
+    #[test]
+    fn keeps_blocks_small() {
+        let img = print_program("~\u{10FFFF}");
+        let map = BlockMap::new(&img, 1);
+        for block in &map.blocks {
+            assert!(block.size <= 12, "{:?}", block.codels);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use crate::types::RGB;
+
+mod tests;
+
+// 8 bits per pixel, every frame uses the global color table
+const MIN_CODE_SIZE: u8 = 8;
+const MAX_CODE_WIDTH: u32 = 12;
+// the most a data sub-block can hold
+const MAX_SUB_BLOCK: usize = 0xFF;
+
+/// Up to 256 colors, every frame is drawn with.
+pub struct Palette {
+    colors: Vec<RGB>,
+    index: HashMap<RGB, u8>,
+}
+
+impl Palette {
+    /// The first 256 different colors, colors after that are drawn with the closest of those.
+    pub fn new<I: IntoIterator<Item = RGB>>(colors: I) -> Palette {
+        let mut palette = Palette {
+            colors: Vec::new(),
+            index: HashMap::new(),
+        };
+        for color in colors {
+            if palette.colors.len() < 256 && !palette.index.contains_key(&color) {
+                palette.index.insert(color, palette.colors.len() as u8);
+                palette.colors.push(color);
+            }
+        }
+        palette
+    }
+
+    fn lookup(&self, color: RGB) -> u8 {
+        if let Some(index) = self.index.get(&color) {
+            return *index;
+        }
+        let distance = |RGB(r, g, b): RGB| {
+            let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+            d(r, color.0) + d(g, color.1) + d(b, color.2)
+        };
+        (0..self.colors.len())
+            .min_by_key(|i| distance(self.colors[*i]))
+            .unwrap_or(0) as u8
+    }
+}
+
+// packs codes of varying width, least significant bit first
+struct BitWriter {
+    bytes: Vec<u8>,
+    bits: u32,
+    pending: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, width: u32) {
+        self.bits |= (code as u32) << self.pending;
+        self.pending += width;
+        while self.pending >= 8 {
+            self.bytes.push(self.bits as u8);
+            self.bits >>= 8;
+            self.pending -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.pending > 0 {
+            self.bytes.push(self.bits as u8);
+        }
+        self.bytes
+    }
+}
+
+/// Compresses color indices with the variable width LZW of GIF, starting over once all 4096 codes
+/// are taken.
+fn lzw(indices: &[u8]) -> Vec<u8> {
+    let clear: u16 = 1 << MIN_CODE_SIZE;
+    let end = clear + 1;
+    let mut out = BitWriter {
+        bytes: Vec::new(),
+        bits: 0,
+        pending: 0,
+    };
+    let mut codes: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut width = MIN_CODE_SIZE as u32 + 1;
+    let mut next = end + 1;
+
+    out.write(clear, width);
+    let mut indices = indices.iter();
+    let mut prefix = match indices.next() {
+        Some(index) => *index as u16,
+        None => {
+            out.write(end, width);
+            return out.finish();
+        }
+    };
+    for index in indices {
+        if let Some(code) = codes.get(&(prefix, *index)) {
+            prefix = *code;
+            continue;
+        }
+        out.write(prefix, width);
+        // the decoder is a code behind, it widens once it added the code this one adds
+        if next >= 1 << width && width < MAX_CODE_WIDTH {
+            width += 1;
+        }
+        if next < 1 << MAX_CODE_WIDTH {
+            codes.insert((prefix, *index), next);
+            next += 1;
+        } else {
+            out.write(clear, width);
+            codes.clear();
+            width = MIN_CODE_SIZE as u32 + 1;
+            next = end + 1;
+        }
+        prefix = *index as u16;
+    }
+    out.write(prefix, width);
+    out.write(end, width);
+    out.finish()
+}
+
+/// Fails when frames of this size are larger than a GIF can describe.
+pub fn check_size(width: usize, height: usize) -> Result<(), String> {
+    let max = u16::MAX as usize;
+    if width > max || height > max {
+        return Err(format!(
+            "the frames are {}x{} pixels, a GIF is at most {}x{}",
+            width, height, max, max
+        ));
+    }
+    Ok(())
+}
+
+/// Writes an animated GIF that loops forever, one frame at a time so they don't have to be kept.
+pub struct GifEncoder {
+    gif: Vec<u8>,
+    palette: Palette,
+    width: u16,
+    height: u16,
+    /// How long every frame is shown, in hundredths of a second.
+    delay: u16,
+}
+
+impl GifEncoder {
+    /// Fails when the frames are larger than a GIF can describe.
+    pub fn new(
+        width: usize,
+        height: usize,
+        palette: Palette,
+        delay: u16,
+    ) -> Result<GifEncoder, String> {
+        check_size(width, height)?;
+        let (width, height) = (width as u16, height as u16);
+        let mut gif = b"GIF89a".to_vec();
+        gif.extend_from_slice(&width.to_le_bytes());
+        gif.extend_from_slice(&height.to_le_bytes());
+        // a global color table of 256 colors with 8 bits per primary, background color 0
+        gif.extend_from_slice(&[0xF7, 0, 0]);
+        for i in 0..256 {
+            let RGB(r, g, b) = palette.colors.get(i).copied().unwrap_or(RGB(0, 0, 0));
+            gif.extend_from_slice(&[r, g, b]);
+        }
+        // the NETSCAPE2.0 extension, looping forever
+        gif.extend_from_slice(&[0x21, 0xFF, 0x0B]);
+        gif.extend_from_slice(b"NETSCAPE2.0");
+        gif.extend_from_slice(&[3, 1, 0, 0, 0]);
+
+        Ok(GifEncoder {
+            gif,
+            palette,
+            width,
+            height,
+            delay,
+        })
+    }
+
+    /// Adds a frame of the size the encoder was created with.
+    pub fn add_frame(&mut self, frame: &[Vec<RGB>]) {
+        // graphic control extension: leave the frame in place, no transparency
+        self.gif.extend_from_slice(&[0x21, 0xF9, 4, 0x04]);
+        self.gif.extend_from_slice(&self.delay.to_le_bytes());
+        self.gif.extend_from_slice(&[0, 0]);
+
+        // image descriptor covering the whole image, without local color table
+        self.gif.extend_from_slice(&[0x2C, 0, 0, 0, 0]);
+        self.gif.extend_from_slice(&self.width.to_le_bytes());
+        self.gif.extend_from_slice(&self.height.to_le_bytes());
+        self.gif.push(0);
+
+        let indices: Vec<u8> = frame
+            .iter()
+            .flat_map(|row| row.iter().map(|color| self.palette.lookup(*color)))
+            .collect();
+        self.gif.push(MIN_CODE_SIZE);
+        for block in lzw(&indices).chunks(MAX_SUB_BLOCK) {
+            self.gif.push(block.len() as u8);
+            self.gif.extend_from_slice(block);
+        }
+        self.gif.push(0);
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        self.gif.push(0x3B);
+        self.gif
+    }
+}
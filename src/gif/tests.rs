@@ -0,0 +1,110 @@
+#[cfg(test)]
+mod tests {
+    use crate::gif::*;
+
+    // reads the codes back like a GIF decoder does
+    fn unlzw(bytes: &[u8]) -> Vec<u8> {
+        let clear = 1u16 << MIN_CODE_SIZE;
+        let end = clear + 1;
+        let (mut bits, mut pending, mut bytes) = (0u32, 0u32, bytes.iter());
+        let mut width = MIN_CODE_SIZE as u32 + 1;
+        let mut table: Vec<Vec<u8>> = Vec::new();
+        let mut prev: Option<Vec<u8>> = None;
+        let mut out = Vec::new();
+        loop {
+            while pending < width {
+                bits |= (*bytes.next().unwrap() as u32) << pending;
+                pending += 8;
+            }
+            let code = (bits & ((1 << width) - 1)) as u16;
+            bits >>= width;
+            pending -= width;
+
+            if code == clear {
+                table = (0..=end).map(|i| vec![i as u8]).collect();
+                width = MIN_CODE_SIZE as u32 + 1;
+                prev = None;
+                continue;
+            }
+            if code == end {
+                return out;
+            }
+            let entry = match (table.get(code as usize), &prev) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(prev)) => [prev.clone(), vec![prev[0]]].concat(),
+                (None, None) => panic!("code {} isn't known yet", code),
+            };
+            out.extend_from_slice(&entry);
+            if let Some(prev) = prev {
+                if table.len() < 1 << MAX_CODE_WIDTH {
+                    table.push([prev, vec![entry[0]]].concat());
+                }
+                if table.len() == 1 << width && width < MAX_CODE_WIDTH {
+                    width += 1;
+                }
+            }
+            prev = Some(entry);
+        }
+    }
+
+    #[test]
+    fn decodes_what_it_compresses() {
+        assert_eq!(unlzw(&lzw(&[])), Vec::<u8>::new());
+        assert_eq!(unlzw(&lzw(&[7])), vec![7]);
+        assert_eq!(unlzw(&lzw(&[1; 1000])), vec![1; 1000]);
+
+        // enough different runs to fill the table and start over a few times
+        let mut seed = 1u32;
+        let noise: Vec<u8> = (0..100_000)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (seed >> 16) as u8 % 24
+            })
+            .collect();
+        assert_eq!(unlzw(&lzw(&noise)), noise);
+    }
+
+    #[test]
+    fn picks_the_closest_color() {
+        let palette = Palette::new(vec![RGB(0, 0, 0), RGB(255, 0, 0), RGB(0, 0, 0)]);
+        assert_eq!(palette.colors.len(), 2);
+        assert_eq!(palette.lookup(RGB(255, 0, 0)), 1);
+        assert_eq!(palette.lookup(RGB(200, 30, 10)), 1);
+        assert_eq!(palette.lookup(RGB(20, 30, 10)), 0);
+    }
+
+    #[test]
+    fn writes_frames() {
+        let frame = vec![vec![RGB(255, 0, 0), RGB(0, 0, 255)]; 3];
+        let mut encoder = GifEncoder::new(2, 3, Palette::new(vec![RGB(0, 0, 255)]), 50).unwrap();
+        encoder.add_frame(&frame);
+        encoder.add_frame(&frame);
+        let gif = encoder.finish();
+
+        assert!(gif.starts_with(b"GIF89a\x02\x00\x03\x00\xF7"));
+        assert_eq!(gif.last(), Some(&0x3B));
+        let frames = gif.windows(3).filter(|w| w == &[0x21, 0xF9, 4]).count();
+        assert_eq!(frames, 2);
+        // red isn't in the palette, blue is the closest
+        let descriptor = gif
+            .windows(5)
+            .position(|w| w == [0x2C, 0, 0, 0, 0])
+            .unwrap();
+        let data = &gif[descriptor + 10..];
+        assert_eq!(data[0], MIN_CODE_SIZE);
+        let len = data[1] as usize;
+        assert_eq!(unlzw(&data[2..2 + len]), vec![0; 6]);
+    }
+    #[test]
+    fn refuses_frames_too_large() {
+        let palette = || Palette::new(vec![RGB(0, 0, 255)]);
+        assert!(GifEncoder::new(65535, 65535, palette(), 50).is_ok());
+        assert_eq!(
+            GifEncoder::new(65536, 8, palette(), 50).err(),
+            Some(String::from(
+                "the frames are 65536x8 pixels, a GIF is at most 65535x65535"
+            ))
+        );
+        assert!(GifEncoder::new(8, 70000, palette(), 50).is_err());
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::VecDeque;
+
+use crate::blocks::BlockMap;
+use crate::interpreter::*;
+use crate::types::*;
+
+mod tests;
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::RIGHT,
+    Direction::DOWN,
+    Direction::LEFT,
+    Direction::UP,
+];
+
+/// A color block, or a single codel outside of blocks (white, black or any other color).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Node {
+    pub color: RGB,
+    /// Number of codels, 1 outside of blocks like `BlockMap::color_info`.
+    pub size: i32,
+    /// The codels of the node, the first one is its top left codel.
+    pub codels: Vec<Coordinates>,
+    /// Id of the block in the `BlockMap`.
+    pub block: Option<usize>,
+}
+
+/// Where the program is between two steps.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct State {
+    pub node: usize,
+    pub dp: Direction,
+    pub cc: CodelChooser,
+}
+
+impl State {
+    pub fn index(&self) -> usize {
+        let dp = DIRECTIONS.iter().position(|d| *d == self.dp).unwrap();
+        let cc = match self.cc {
+            CodelChooser::LEFT => 0,
+            CodelChooser::RIGHT => 1,
+        };
+        self.node * 8 + dp * 2 + cc
+    }
+
+    pub fn from_index(index: usize) -> State {
+        State {
+            node: index / 8,
+            dp: DIRECTIONS[index % 8 / 2],
+            cc: if index.is_multiple_of(2) {
+                CodelChooser::LEFT
+            } else {
+                CodelChooser::RIGHT
+            },
+        }
+    }
+}
+
+/// What a step from a state does.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Edge {
+    pub to: usize,
+    /// The codel the next node is entered at.
+    pub codel: Coordinates,
+    /// DP and CC after getting around obstacles, before the command changes them.
+    pub dp: Direction,
+    pub cc: CodelChooser,
+    pub command: Command,
+    /// Size of the node that is left, pushed by `push`.
+    pub size: i32,
+}
+
+impl Edge {
+    /// The states the command of the edge can lead to: four for `pointer`, two for `switch`.
+    pub fn targets(&self) -> Vec<State> {
+        let state = State {
+            node: self.to,
+            dp: self.dp,
+            cc: self.cc,
+        };
+        match self.command {
+            Command::Pointer => {
+                let mut dp = self.dp;
+                let mut targets = Vec::new();
+                for _ in 0..4 {
+                    targets.push(State { dp, ..state });
+                    dp = dp.next();
+                }
+                targets
+            }
+            Command::Switch => vec![
+                state,
+                State {
+                    cc: self.cc.toggle(),
+                    ..state
+                },
+            ],
+            _ => vec![state],
+        }
+    }
+}
+
+/// The program compiled into a graph of states, every state has a fixed successor and command
+/// since the blocks don't change. Running it doesn't depend on the size of the image.
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    /// Size of the program in codels.
+    pub width: i32,
+    pub height: i32,
+    /// Node of every codel, row by row.
+    by_codel: Vec<usize>,
+    /// Indexed by `State::index`, `None` where the program terminates.
+    edges: Vec<Option<Edge>>,
+}
+
+impl Graph {
+    pub fn compile(blocks: &BlockMap) -> Graph {
+        let mut nodes: Vec<Node> = blocks
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(id, block)| {
+                let mut codels = block.codels.clone();
+                codels.sort_by_key(|c| (c.y, c.x));
+                Node {
+                    color: block.color,
+                    size: block.size,
+                    codels,
+                    block: Some(id),
+                }
+            })
+            .collect();
+
+        let mut by_codel = Vec::with_capacity((blocks.width * blocks.height) as usize);
+        for y in 0..blocks.height {
+            for x in 0..blocks.width {
+                let codel = Coordinates { x, y };
+                match blocks.block_id(codel) {
+                    Some(id) => by_codel.push(id),
+                    None => {
+                        by_codel.push(nodes.len());
+                        nodes.push(Node {
+                            color: blocks.color(codel).unwrap(),
+                            size: 1,
+                            codels: vec![codel],
+                            block: None,
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut graph = Graph {
+            width: blocks.width,
+            height: blocks.height,
+            edges: Vec::with_capacity(nodes.len() * 8),
+            nodes,
+            by_codel,
+        };
+        for index in 0..graph.nodes.len() * 8 {
+            let state = State::from_index(index);
+            let node = &graph.nodes[state.node];
+            let (mut codel, mut dp, mut cc) = (node.codels[0], state.dp, state.cc);
+            let edge = blocks
+                .next_color(&mut codel, &mut dp, &mut cc)
+                .map(|next| Edge {
+                    to: graph.node(codel),
+                    codel,
+                    dp,
+                    cc,
+                    command: decode_command(node.color, next.color),
+                    size: node.size,
+                });
+            graph.edges.push(edge);
+        }
+        graph
+    }
+
+    /// The node containing `codel`.
+    pub fn node(&self, codel: Coordinates) -> usize {
+        self.by_codel[(codel.y * self.width + codel.x) as usize]
+    }
+
+    /// Where every program starts: the top left codel, DP right and CC left.
+    pub fn start(&self) -> State {
+        State {
+            node: self.node(Coordinates { x: 0, y: 0 }),
+            dp: Direction::RIGHT,
+            cc: CodelChooser::LEFT,
+        }
+    }
+
+    /// The step from `state`, `None` if the program terminates there.
+    pub fn edge(&self, state: State) -> Option<&Edge> {
+        self.edges[state.index()].as_ref()
+    }
+
+    pub fn states(&self) -> impl Iterator<Item = State> {
+        (0..self.edges.len()).map(State::from_index)
+    }
+
+    /// All states a step from `state` can lead to.
+    pub fn successors(&self, state: State) -> Vec<State> {
+        self.edge(state).map_or(Vec::new(), Edge::targets)
+    }
+
+    /// The states reachable from the start, in the order they are found.
+    pub fn reachable(&self) -> Vec<State> {
+        let mut seen = vec![false; self.edges.len()];
+        let mut order = Vec::new();
+        let mut todo = VecDeque::new();
+        todo.push_back(self.start());
+        seen[self.start().index()] = true;
+        while let Some(state) = todo.pop_front() {
+            order.push(state);
+            for next in self.successors(state) {
+                if !seen[next.index()] {
+                    seen[next.index()] = true;
+                    todo.push_back(next);
+                }
+            }
+        }
+        order
+    }
+}
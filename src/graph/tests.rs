@@ -0,0 +1,111 @@
+#[cfg(test)]
+mod tests {
+    use crate::blocks::BlockMap;
+    use crate::graph::*;
+    use crate::machine::*;
+    use crate::test_support::load;
+
+    // the compiled program has to take the same steps as the one running on the image
+    fn runs_like_the_image(path: &str, max_steps: u64) {
+        let limits = Limits {
+            max_steps: Some(max_steps),
+            ..Limits::default()
+        };
+        let (mut image, image_out) = load(path);
+        let mut image_steps = Vec::new();
+        image
+            .run(&limits, |m, step| image_steps.push((m.pos, step.command)))
+            .unwrap();
+
+        let (mut compiled, compiled_out) = load(path);
+        compiled.compile();
+        let mut compiled_steps = Vec::new();
+        compiled
+            .run(&limits, |m, step| {
+                compiled_steps.push((m.pos, step.command))
+            })
+            .unwrap();
+
+        assert_eq!(compiled_steps, image_steps);
+        assert_eq!(compiled.summary(), image.summary());
+        assert_eq!(compiled_out.contents(), image_out.contents());
+    }
+
+    #[test]
+    fn compiled_programs_run_like_the_image() {
+        runs_like_the_image("tests/fixtures/piet_hello_world.png", 1000);
+        runs_like_the_image("tests/fixtures/fizzbuzz.png", 3000);
+        runs_like_the_image("tests/fixtures/99bottles.png", 3000);
+        runs_like_the_image("tests/fixtures/artsy_hello_world.png", 3000);
+    }
+    #[test]
+    fn state_index_round_trips() {
+        for index in 0..80 {
+            assert_eq!(State::from_index(index).index(), index);
+        }
+        let state = State {
+            node: 3,
+            dp: Direction::LEFT,
+            cc: CodelChooser::RIGHT,
+        };
+        assert_eq!(State::from_index(state.index()), state);
+    }
+    #[test]
+    fn edges_carry_the_command() {
+        let (machine, _) = load("tests/fixtures/piet_hello_world.png");
+        let graph = Graph::compile(&machine.blocks);
+
+        let start = graph.start();
+        let edge = graph.edge(start).unwrap();
+        assert_eq!(edge.command, Command::Push);
+        assert_eq!(edge.size, 72);
+        assert_eq!(edge.codel, Coordinates { x: 11, y: 0 });
+        assert_eq!(
+            graph.nodes[start.node].codels[0],
+            Coordinates { x: 0, y: 0 }
+        );
+
+        let reachable = graph.reachable();
+        assert_eq!(reachable[0], start);
+        assert_eq!(reachable[1].node, edge.to);
+        // the program ends in a block surrounded by black
+        assert!(reachable.iter().any(|s| graph.edge(*s).is_none()));
+    }
+    #[test]
+    fn pointer_and_switch_branch() {
+        let edge = Edge {
+            to: 2,
+            codel: Coordinates { x: 0, y: 0 },
+            dp: Direction::DOWN,
+            cc: CodelChooser::LEFT,
+            command: Command::Pointer,
+            size: 1,
+        };
+        let dps: Vec<_> = edge.targets().iter().map(|s| s.dp).collect();
+        assert_eq!(
+            dps,
+            [
+                Direction::DOWN,
+                Direction::LEFT,
+                Direction::UP,
+                Direction::RIGHT
+            ]
+        );
+
+        let switch = Edge {
+            command: Command::Switch,
+            ..edge
+        };
+        let ccs: Vec<_> = switch.targets().iter().map(|s| s.cc).collect();
+        assert_eq!(ccs, [CodelChooser::LEFT, CodelChooser::RIGHT]);
+        assert_eq!(
+            Edge {
+                command: Command::Add,
+                ..edge
+            }
+            .targets()
+            .len(),
+            1
+        );
+    }
+}
@@ -1,10 +1,13 @@
 #![allow(unused)]
 mod tests;
+
+use std::fmt;
 use std::io;
-use std::io::Write;
+use std::str::FromStr;
 
+use crate::number::*;
+use crate::piet_io::*;
 use crate::types::*;
-use std::io::Read;
 
 macro_rules! unwrap_or_return {
     ($e:expr) => {
@@ -21,7 +24,8 @@ macro_rules! unwrap_or_return {
     };
 }
 
-const COLORS: [[RGB; 6]; 3] = [
+/// The 18 colors, a row per lightness from light to dark and a column per hue from red to magenta.
+pub const COLORS: [[RGB; 6]; 3] = [
     [
         RGB(255, 192, 192),
         RGB(255, 255, 192),
@@ -48,156 +52,549 @@ const COLORS: [[RGB; 6]; 3] = [
     ],
 ];
 
-const CMD: [[for<'r, 's, 't0> fn(i32, &'r mut Vec<i32>, &'s mut CodelChooser, &'t0 mut Direction);
-    3]; 6] = [
-    [none, push, pop],
-    [add, sub, mult],
-    [div, modulo, not],
-    [greater, pointer, switch],
-    [dup, roll, in_num],
-    [in_char, out_num, out_char],
+const HUE_NAMES: [&str; 6] = ["red", "yellow", "green", "cyan", "blue", "magenta"];
+const LIGHTNESS_NAMES: [&str; 3] = ["light ", "", "dark "];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Command {
+    None,
+    Push,
+    Pop,
+    Add,
+    Sub,
+    Mult,
+    Div,
+    Mod,
+    Not,
+    Greater,
+    Pointer,
+    Switch,
+    Dup,
+    Roll,
+    InNum,
+    InChar,
+    OutNum,
+    OutChar,
+}
+
+// indexed by hue change, then lightness change
+pub const CMD: [[Command; 3]; 6] = [
+    [Command::None, Command::Push, Command::Pop],
+    [Command::Add, Command::Sub, Command::Mult],
+    [Command::Div, Command::Mod, Command::Not],
+    [Command::Greater, Command::Pointer, Command::Switch],
+    [Command::Dup, Command::Roll, Command::InNum],
+    [Command::InChar, Command::OutNum, Command::OutChar],
 ];
 
-fn none(size: i32, stack: &mut Vec<i32>, cc: &mut CodelChooser, dp: &mut Direction) {}
-fn push(size: i32, stack: &mut Vec<i32>, cc: &mut CodelChooser, dp: &mut Direction) {
-    stack.push(size)
+type CommandFn = fn(&mut Interpreter, i32) -> Result<(), RuntimeError>;
+
+impl Command {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::None => "none",
+            Command::Push => "push",
+            Command::Pop => "pop",
+            Command::Add => "add",
+            Command::Sub => "sub",
+            Command::Mult => "mult",
+            Command::Div => "div",
+            Command::Mod => "mod",
+            Command::Not => "not",
+            Command::Greater => "greater",
+            Command::Pointer => "pointer",
+            Command::Switch => "switch",
+            Command::Dup => "dup",
+            Command::Roll => "roll",
+            Command::InNum => "in_num",
+            Command::InChar => "in_char",
+            Command::OutNum => "out_num",
+            Command::OutChar => "out_char",
+        }
+    }
+
+    /// How many values the command takes from the stack, it is ignored if there are fewer.
+    pub fn operands(&self) -> usize {
+        match self {
+            Command::None | Command::Push | Command::InNum | Command::InChar => 0,
+            Command::Pop
+            | Command::Not
+            | Command::Pointer
+            | Command::Switch
+            | Command::Dup
+            | Command::OutNum
+            | Command::OutChar => 1,
+            Command::Add
+            | Command::Sub
+            | Command::Mult
+            | Command::Div
+            | Command::Mod
+            | Command::Greater
+            | Command::Roll => 2,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Command> {
+        CMD.iter().flatten().copied().find(|cmd| cmd.name() == name)
+    }
+
+    fn function(&self) -> CommandFn {
+        match self {
+            Command::None => none,
+            Command::Push => push,
+            Command::Pop => pop,
+            Command::Add => add,
+            Command::Sub => sub,
+            Command::Mult => mult,
+            Command::Div => div,
+            Command::Mod => modulo,
+            Command::Not => not,
+            Command::Greater => greater,
+            Command::Pointer => pointer,
+            Command::Switch => switch,
+            Command::Dup => dup,
+            Command::Roll => roll,
+            Command::InNum => in_num,
+            Command::InChar => in_char,
+            Command::OutNum => out_num,
+            Command::OutChar => out_char,
+        }
+    }
 }
-fn pop(size: i32, stack: &mut Vec<i32>, cc: &mut CodelChooser, dp: &mut Direction) {
-    let _ = unwrap_or_return!(stack.pop());
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RuntimeError {
+    InvalidChar(Int),
+    Overflow,
+    Io(String),
+    /// A command was ignored with `Strict::Error`, at the codel of the block that was left.
+    Ignored {
+        step: u64,
+        codel: Coordinates,
+        why: Ignored,
+    },
 }
-fn add(size: i32, stack: &mut Vec<i32>, cc: &mut CodelChooser, dp: &mut Direction) {
-    let top = unwrap_or_return!(stack.pop());
-    let sec_top = unwrap_or_return!(stack.pop());
 
-    stack.push(top + sec_top)
+/// Why a command didn't do anything.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Ignored {
+    /// There were only `len` values on the stack.
+    Underflow { command: Command, len: usize },
+    /// `div` or `mod` with 0 on top.
+    DivisionByZero(Command),
+    /// `roll` to a depth that isn't positive or deeper than the `len` values below.
+    InvalidRoll { depth: Int, len: usize },
 }
 
-fn modulo(size: i32, stack: &mut Vec<i32>, cc: &mut CodelChooser, dp: &mut Direction) {
-    let top = unwrap_or_return!(stack.pop());
-    let sec_top = unwrap_or_return!(stack.pop());
-    // ignore command if div by 0 (recommended)
-    if top == 0 {
-        return;
+impl fmt::Display for Ignored {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Ignored::Underflow { command, len: 0 } if command.operands() == 1 => {
+                write!(f, "{} needs a value but the stack is empty", command.name())
+            }
+            Ignored::Underflow { command, len } => write!(
+                f,
+                "{} needs 2 values but the stack has {}",
+                command.name(),
+                len
+            ),
+            Ignored::DivisionByZero(command) => write!(f, "{} by zero", command.name()),
+            Ignored::InvalidRoll { depth, len } => {
+                write!(f, "roll to depth {} with {} values below", depth, len)
+            }
+        }
     }
+}
 
-    stack.push(sec_top.rem_euclid(top))
+/// What happens when a command is ignored because of an underflow, a division by zero or an
+/// invalid roll depth.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Strict {
+    /// Nothing, like the spec says.
+    Off,
+    /// The step reports it, `pint` prints a warning.
+    Warn,
+    /// Stops the program with an error.
+    Error,
 }
 
-fn not(size: i32, stack: &mut Vec<i32>, cc: &mut CodelChooser, dp: &mut Direction) {
-    let top = unwrap_or_return!(stack.pop());
-    if top != 0 {
-        stack.push(0)
-    } else {
-        stack.push(1)
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuntimeError::InvalidChar(n) => write!(f, "{} is not a valid character", n),
+            RuntimeError::Overflow => write!(f, "integer overflow"),
+            RuntimeError::Io(why) => write!(f, "io error: {}", why),
+            RuntimeError::Ignored { step, codel, why } => {
+                write!(f, "step {} at ({}, {}): {}", step, codel.x, codel.y, why)
+            }
+        }
     }
 }
-fn sub(size: i32, stack: &mut Vec<i32>, cc: &mut CodelChooser, dp: &mut Direction) {
-    let top = unwrap_or_return!(stack.pop());
-    let sec_top = unwrap_or_return!(stack.pop());
 
-    stack.push(sec_top - top)
+impl From<io::Error> for RuntimeError {
+    fn from(e: io::Error) -> RuntimeError {
+        RuntimeError::Io(e.to_string())
+    }
 }
-fn mult(size: i32, stack: &mut Vec<i32>, cc: &mut CodelChooser, dp: &mut Direction) {
-    let top = unwrap_or_return!(stack.pop());
-    let sec_top = unwrap_or_return!(stack.pop());
 
-    stack.push(sec_top * top)
+/// How `div` rounds and which sign the result of `mod` takes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Division {
+    /// `div` rounds towards negative infinity, `mod` has the sign of the divisor. The spec only
+    /// prescribes the sign of `mod`, `div` rounds to match it.
+    Floor,
+    /// `div` rounds towards zero, `mod` has the sign of the dividend (like C).
+    Truncate,
 }
-fn div(size: i32, stack: &mut Vec<i32>, cc: &mut CodelChooser, dp: &mut Direction) {
-    let top = unwrap_or_return!(stack.pop());
-    let sec_top = unwrap_or_return!(stack.pop());
-    // ignore command if div by 0 (recommended)
-    if top == 0 {
-        return;
+
+impl Division {
+    /// The name as `--division` takes it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Division::Floor => "floor",
+            Division::Truncate => "truncate",
+        }
     }
+}
+
+impl FromStr for Division {
+    type Err = String;
 
-    stack.push(sec_top / top)
+    fn from_str(s: &str) -> Result<Division, String> {
+        match s {
+            "floor" => Ok(Division::Floor),
+            "truncate" => Ok(Division::Truncate),
+            _ => Err(format!("{} is not a kind of division", s)),
+        }
+    }
 }
-fn greater(size: i32, stack: &mut Vec<i32>, cc: &mut CodelChooser, dp: &mut Direction) {
-    let top = unwrap_or_return!(stack.pop());
-    let sec_top = unwrap_or_return!(stack.pop());
 
-    if sec_top > top {
-        stack.push(1)
-    } else {
-        stack.push(0)
+/// What happens when a result doesn't fit into the range of the `IntBackend`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Overflow {
+    /// Wraps around like two's complement arithmetic.
+    Wrap,
+    /// Clamps to the smallest or biggest value.
+    Saturate,
+    /// Stops the program with an error.
+    Error,
+}
+
+impl FromStr for Overflow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Overflow, String> {
+        match s {
+            "wrap" => Ok(Overflow::Wrap),
+            "saturate" => Ok(Overflow::Saturate),
+            "error" => Ok(Overflow::Error),
+            _ => Err(format!("{} is not a kind of overflow", s)),
+        }
     }
 }
-fn pointer(size: i32, stack: &mut Vec<i32>, cc: &mut CodelChooser, dp: &mut Direction) {
-    let top = unwrap_or_return!(stack.pop());
-    for _ in 0..top {
-        *dp = dp.next();
+
+impl Overflow {
+    /// The name as `--overflow` takes it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Overflow::Wrap => "wrap",
+            Overflow::Saturate => "saturate",
+            Overflow::Error => "error",
+        }
+    }
+
+    /// Wrapping for 32-bit integers stays compatible to older versions,
+    /// 64-bit integers are checked.
+    pub fn default_for(backend: IntBackend) -> Overflow {
+        match backend {
+            IntBackend::I32 => Overflow::Wrap,
+            _ => Overflow::Error,
+        }
     }
 }
-fn switch(size: i32, stack: &mut Vec<i32>, cc: &mut CodelChooser, dp: &mut Direction) {
-    let top = unwrap_or_return!(stack.pop());
-    for _ in 0..top.abs() {
-        *cc = cc.toggle();
+
+/// Everything the commands operate on.
+pub struct Interpreter {
+    pub stack: Vec<Int>,
+    pub dp: Direction,
+    pub cc: CodelChooser,
+    pub io: Io,
+    pub backend: IntBackend,
+    pub overflow: Overflow,
+    pub division: Division,
+    pub strict: Strict,
+    /// Why the last command didn't do anything, taken by `Machine::step`.
+    pub ignored: Option<Ignored>,
+}
+
+impl Interpreter {
+    pub fn new(io: Io) -> Interpreter {
+        Interpreter {
+            stack: Vec::new(),
+            dp: Direction::RIGHT,
+            cc: CodelChooser::LEFT,
+            io,
+            backend: IntBackend::I32,
+            overflow: Overflow::Wrap,
+            division: Division::Floor,
+            strict: Strict::Off,
+            ignored: None,
+        }
+    }
+
+    // the top value for `command`, `None` if the stack is empty and the command is ignored
+    fn pop_operand(&mut self, command: Command) -> Option<Int> {
+        if self.stack.is_empty() {
+            self.ignored = Some(Ignored::Underflow { command, len: 0 });
+        }
+        self.stack.pop()
     }
+
+    // the second and the top value for `command`, nothing is popped if there is just one
+    fn pop_operands(&mut self, command: Command) -> Option<(Int, Int)> {
+        let len = self.stack.len();
+        if len < 2 {
+            self.ignored = Some(Ignored::Underflow { command, len });
+            return None;
+        }
+        let top = self.stack.pop().unwrap();
+        let sec_top = self.stack.pop().unwrap();
+        Some((sec_top, top))
+    }
+
+    // division by zero is ignored, so the operands go back
+    fn divisor_is_zero(&mut self, command: Command, sec_top: Int, top: Int) -> Option<(Int, Int)> {
+        if top.is_zero() {
+            self.stack.push(sec_top);
+            self.stack.push(top);
+            self.ignored = Some(Ignored::DivisionByZero(command));
+            return None;
+        }
+        Some((sec_top, top))
+    }
+
+    // every value computed by a command goes through here,
+    // so the overflow behaviour is the same for all of them
+    fn push_result(&mut self, n: Int) -> Result<(), RuntimeError> {
+        let (min, max) = match self.backend.bounds() {
+            Some(bounds) => bounds,
+            None => {
+                self.stack.push(n);
+                return Ok(());
+            }
+        };
+        let n = if n < min || n > max {
+            match self.overflow {
+                Overflow::Wrap => self.backend.wrap(&n),
+                Overflow::Saturate if n < min => min,
+                Overflow::Saturate => max,
+                Overflow::Error => return Err(RuntimeError::Overflow),
+            }
+        } else {
+            n
+        };
+        self.stack.push(n);
+        Ok(())
+    }
+
+    /// Executes the command given by the color change from `prev` to `current`.
+    pub fn execute(
+        &mut self,
+        prev: ColorInfo,
+        current: &ColorInfo,
+    ) -> Result<Command, RuntimeError> {
+        let command = decode_command(prev.color, current.color);
+        self.execute_command(command, prev.size)?;
+        Ok(command)
+    }
+
+    /// How many values from the top of the stack `command` may pop or change.
+    pub fn touched(&self, command: Command) -> usize {
+        let len = self.stack.len();
+        let n = match command {
+            Command::None | Command::Push | Command::Dup | Command::InNum | Command::InChar => 0,
+            Command::Pop
+            | Command::Not
+            | Command::Pointer
+            | Command::Switch
+            | Command::OutNum
+            | Command::OutChar => 1,
+            Command::Roll => {
+                let depth = len
+                    .checked_sub(2)
+                    .and_then(|i| self.stack[i].to_i64())
+                    .unwrap_or(0);
+                2 + depth.clamp(0, len as i64) as usize
+            }
+            _ => 2,
+        };
+        n.min(len)
+    }
+
+    pub fn execute_command(&mut self, command: Command, size: i32) -> Result<(), RuntimeError> {
+        self.ignored = None;
+        command.function()(self, size)
+    }
+}
+
+// All commands are ignored when there are too few values on the stack, leaving it as it was.
+// Results that don't fit into the range of the `IntBackend` are handled according to `Overflow`.
+
+/// no-op
+fn none(interp: &mut Interpreter, size: i32) -> Result<(), RuntimeError> {
+    Ok(())
+}
+/// pushes the size of the block that was just left
+fn push(interp: &mut Interpreter, size: i32) -> Result<(), RuntimeError> {
+    interp.push_result(Int::from(size))
 }
-fn dup(size: i32, stack: &mut Vec<i32>, cc: &mut CodelChooser, dp: &mut Direction) {
-    let top = unwrap_or_return!(stack.pop());
-    stack.push(top);
-    stack.push(top);
+/// discards the top value
+fn pop(interp: &mut Interpreter, size: i32) -> Result<(), RuntimeError> {
+    let _ = unwrap_or_return!(interp.pop_operand(Command::Pop), Ok(()));
+    Ok(())
 }
-fn roll(size: i32, stack: &mut Vec<i32>, cc: &mut CodelChooser, dp: &mut Direction) {
-    let rolls = unwrap_or_return!(stack.pop());
-    let depth = unwrap_or_return!(stack.pop());
-    let len = stack.len();
+/// second value + top value
+fn add(interp: &mut Interpreter, size: i32) -> Result<(), RuntimeError> {
+    let (sec_top, top) = unwrap_or_return!(interp.pop_operands(Command::Add), Ok(()));
 
-    if depth <= 0 || stack.len() < depth as usize {
-        // ignore command
-        stack.push(depth);
-        stack.push(rolls);
+    interp.push_result(sec_top.add(&top))
+}
+
+/// second value modulo top value, the sign of the result depends on `Division`.
+/// Ignored if the top value is 0.
+fn modulo(interp: &mut Interpreter, size: i32) -> Result<(), RuntimeError> {
+    let (sec_top, top) = unwrap_or_return!(interp.pop_operands(Command::Mod), Ok(()));
+    // ignore command if div by 0 (recommended)
+    let (sec_top, top) =
+        unwrap_or_return!(interp.divisor_is_zero(Command::Mod, sec_top, top), Ok(()));
+
+    let rem = match interp.division {
+        Division::Floor => sec_top.div_rem_floor(&top).1,
+        Division::Truncate => sec_top.div_rem(&top).1,
+    };
+    interp.push_result(rem)
+}
+
+/// 1 if the top value is 0, otherwise 0
+fn not(interp: &mut Interpreter, size: i32) -> Result<(), RuntimeError> {
+    let top = unwrap_or_return!(interp.pop_operand(Command::Not), Ok(()));
+    if !top.is_zero() {
+        interp.stack.push(Int::from(0))
     } else {
-        let rolls = rolls % depth;
-        let mut sub = stack.split_off(len - depth as usize);
-        if rolls > 0 {
-            sub.rotate_right(rolls as usize)
-        } else {
-            sub.rotate_left((rolls * -1) as usize)
-        }
-        stack.append(&mut sub)
+        interp.stack.push(Int::from(1))
     }
+    Ok(())
 }
+/// second value - top value
+fn sub(interp: &mut Interpreter, size: i32) -> Result<(), RuntimeError> {
+    let (sec_top, top) = unwrap_or_return!(interp.pop_operands(Command::Sub), Ok(()));
 
-fn in_num(size: i32, stack: &mut Vec<i32>, cc: &mut CodelChooser, dp: &mut Direction) {
-    print!("> ");
-    // forces to print everything everything before input
-    io::stdout().flush();
+    interp.push_result(sec_top.sub(&top))
+}
+/// second value * top value
+fn mult(interp: &mut Interpreter, size: i32) -> Result<(), RuntimeError> {
+    let (sec_top, top) = unwrap_or_return!(interp.pop_operands(Command::Mult), Ok(()));
 
-    let mut buffer = String::new();
-    let stdin = io::stdin()
-        .read_line(&mut buffer)
-        .expect("Utf-8 encoded input");
+    interp.push_result(sec_top.mul(&top))
+}
+/// second value / top value, rounded according to `Division`.
+/// Ignored if the top value is 0, MIN / -1 overflows.
+fn div(interp: &mut Interpreter, size: i32) -> Result<(), RuntimeError> {
+    let (sec_top, top) = unwrap_or_return!(interp.pop_operands(Command::Div), Ok(()));
+    // ignore command if div by 0 (recommended)
+    let (sec_top, top) =
+        unwrap_or_return!(interp.divisor_is_zero(Command::Div, sec_top, top), Ok(()));
 
-    match buffer.trim().parse::<i32>() {
-        Ok(n) => stack.push(n),
-        Err(e) => eprintln!("input not a number"),
+    let quotient = match interp.division {
+        Division::Floor => sec_top.div_rem_floor(&top).0,
+        Division::Truncate => sec_top.div_rem(&top).0,
+    };
+    interp.push_result(quotient)
+}
+/// 1 if the second value is greater than the top value, otherwise 0
+fn greater(interp: &mut Interpreter, size: i32) -> Result<(), RuntimeError> {
+    let (sec_top, top) = unwrap_or_return!(interp.pop_operands(Command::Greater), Ok(()));
+
+    if sec_top > top {
+        interp.stack.push(Int::from(1))
+    } else {
+        interp.stack.push(Int::from(0))
+    }
+    Ok(())
+}
+/// rotates the DP clockwise for positive and anticlockwise for negative values,
+/// so only the value modulo 4 matters no matter how big it is
+fn pointer(interp: &mut Interpreter, size: i32) -> Result<(), RuntimeError> {
+    let top = unwrap_or_return!(interp.pop_operand(Command::Pointer), Ok(()));
+    let rotations = top.rem_euclid(&Int::from(4)).to_i64().unwrap();
+    for _ in 0..rotations {
+        interp.dp = interp.dp.next();
     }
+    Ok(())
 }
-fn in_char(size: i32, stack: &mut Vec<i32>, cc: &mut CodelChooser, dp: &mut Direction) {
-    print!("> ");
-    io::stdout().flush();
+/// toggles the CC abs(top) times, so only whether the value is odd matters
+fn switch(interp: &mut Interpreter, size: i32) -> Result<(), RuntimeError> {
+    let top = unwrap_or_return!(interp.pop_operand(Command::Switch), Ok(()));
+    if !top.rem_euclid(&Int::from(2)).is_zero() {
+        interp.cc = interp.cc.toggle();
+    }
+    Ok(())
+}
+/// pushes a copy of the top value
+fn dup(interp: &mut Interpreter, size: i32) -> Result<(), RuntimeError> {
+    let top = unwrap_or_return!(interp.pop_operand(Command::Dup), Ok(()));
+    interp.stack.push(top.clone());
+    interp.stack.push(top);
+    Ok(())
+}
+/// rolls the values below the top two to a depth of the second value, top value times.
+/// A depth that isn't positive or bigger than the stack (no matter how big) ignores the command,
+/// the number of rolls is taken modulo the depth
+fn roll(interp: &mut Interpreter, size: i32) -> Result<(), RuntimeError> {
+    let (depth, rolls) = unwrap_or_return!(interp.pop_operands(Command::Roll), Ok(()));
+    let len = interp.stack.len();
 
-    let mut buffer = String::new();
-    let mut byte_unicode = io::stdin()
-        .bytes()
-        .next()
-        .and_then(|result| result.ok())
-        .map(|byte| byte as i32)
-        .expect("Utf-8 encoded input");
+    match depth.to_i64() {
+        Some(d) if d > 0 && d as u64 <= len as u64 => {
+            let rolls = rolls.rem_euclid(&depth).to_i64().unwrap();
+            let mut sub = interp.stack.split_off(len - d as usize);
+            sub.rotate_right(rolls as usize);
+            interp.stack.append(&mut sub)
+        }
+        _ => {
+            // ignore command
+            interp.ignored = Some(Ignored::InvalidRoll {
+                depth: depth.clone(),
+                len,
+            });
+            interp.stack.push(depth);
+            interp.stack.push(rolls);
+        }
+    }
+    Ok(())
+}
 
-    stack.push(byte_unicode)
+/// reads a number, nothing is pushed on end of input or invalid input
+fn in_num(interp: &mut Interpreter, size: i32) -> Result<(), RuntimeError> {
+    let n = unwrap_or_return!(interp.io.read_num()?, Ok(()));
+    interp.push_result(n)
+}
+/// reads a character according to `CharEncoding`, nothing is pushed on end of input
+fn in_char(interp: &mut Interpreter, size: i32) -> Result<(), RuntimeError> {
+    let c = unwrap_or_return!(interp.io.read_char()?, Ok(()));
+    interp.stack.push(Int::from(c));
+    Ok(())
 }
 
-fn out_num(size: i32, stack: &mut Vec<i32>, cc: &mut CodelChooser, dp: &mut Direction) {
-    let top = unwrap_or_return!(stack.pop());
-    print!("{}", top as i32)
+/// prints the top value as number
+fn out_num(interp: &mut Interpreter, size: i32) -> Result<(), RuntimeError> {
+    let top = unwrap_or_return!(interp.pop_operand(Command::OutNum), Ok(()));
+    interp.io.write_num(&top)
 }
-fn out_char(size: i32, stack: &mut Vec<i32>, cc: &mut CodelChooser, dp: &mut Direction) {
-    let top = unwrap_or_return!(stack.pop());
-    print!("{}", std::char::from_u32(top as u32).unwrap())
+/// prints the top value as character, values that aren't one are handled according to `InvalidChar`
+fn out_char(interp: &mut Interpreter, size: i32) -> Result<(), RuntimeError> {
+    let top = unwrap_or_return!(interp.pop_operand(Command::OutChar), Ok(()));
+    interp.io.write_char(&top)
 }
 
 pub fn get_color_index(color: RGB) -> Option<Coordinates> {
@@ -224,14 +621,35 @@ fn calculate_color_diff(prev_color: RGB, color: RGB) -> Coordinates {
     }
 }
 
-pub fn execute(
-    stack: &mut Vec<i32>,
-    dp: &mut Direction,
-    cc: &mut CodelChooser,
-    prev: ColorInfo,
-    current: &ColorInfo,
-) {
-    let color_diff = calculate_color_diff(prev.color, current.color);
+pub fn decode_command(prev_color: RGB, color: RGB) -> Command {
+    let color_diff = calculate_color_diff(prev_color, color);
+    CMD[color_diff.x as usize][color_diff.y as usize]
+}
+
+/// The color a block needs to have so that moving into it from a block of `prev_color` executes
+/// `command`, the inverse of `decode_command`. `prev_color` has to be one of the 18 colors.
+pub fn encode_command(prev_color: RGB, command: Command) -> RGB {
+    let prev = get_color_index(prev_color).expect("not one of the 18 colors");
+    let (hue, lightness) = CMD
+        .iter()
+        .enumerate()
+        .find_map(|(hue, row)| {
+            let lightness = row.iter().position(|c| *c == command)?;
+            Some((hue, lightness))
+        })
+        .unwrap();
+    COLORS[(prev.y as usize + lightness) % 3][(prev.x as usize + hue) % 6]
+}
 
-    CMD[color_diff.x as usize][color_diff.y as usize](prev.size, stack, cc, dp);
+/// Names like "light red", "blue" or "white", colors outside the palette are printed as hex.
+pub fn color_name(color: RGB) -> String {
+    match get_color_index(color) {
+        Some(index) => format!(
+            "{}{}",
+            LIGHTNESS_NAMES[index.y as usize], HUE_NAMES[index.x as usize]
+        ),
+        None if color == WHITE => String::from("white"),
+        None if color == BLACK => String::from("black"),
+        None => format!("#{:02x}{:02x}{:02x}", color.0, color.1, color.2),
+    }
 }
@@ -1,7 +1,10 @@
 #[cfg(test)]
 mod tests {
     use crate::interpreter::*;
+    use crate::number::*;
+    use crate::piet_io::*;
     use crate::types::*;
+    use std::io::{empty, sink};
 
     #[test]
     fn gets_correct_color_index() {
@@ -40,32 +43,158 @@ mod tests {
 
         assert_eq!(result, expected);
     }
+    fn interpreter_with_stack(stack: &[i64]) -> Interpreter {
+        let io = Io::new(Box::new(empty()), Box::new(sink()));
+        let mut interp = Interpreter::new(io);
+        interp.stack = stack.iter().map(|&n| Int::from(n)).collect();
+        interp.dp = Direction::UP;
+        interp
+    }
+    fn ints(values: &[i64]) -> Vec<Int> {
+        values.iter().map(|&n| Int::from(n)).collect()
+    }
+
+    #[test]
+    fn encodes_every_command() {
+        for prev in COLORS.iter().flatten() {
+            for command in CMD.iter().flatten() {
+                assert_eq!(
+                    decode_command(*prev, encode_command(*prev, *command)),
+                    *command
+                );
+            }
+        }
+    }
     #[test]
     fn roll_test1() {
-        let mut stack = vec![12, 3, 102, 33, 7, 4, 2];
-        let mut dp = Direction::UP;
-        let mut cc = CodelChooser::LEFT;
+        let mut interp = interpreter_with_stack(&[12, 3, 102, 33, 7, 4, 2]);
 
-        roll(3, &mut stack, &mut cc, &mut dp);
-        assert_eq!(stack, [12, 33, 7, 3, 102]);
+        roll(&mut interp, 3).unwrap();
+        assert_eq!(interp.stack, ints(&[12, 33, 7, 3, 102]));
     }
     #[test]
     fn roll_test2() {
-        let mut stack = vec![1, 2, 3, 3, 1];
-        let mut dp = Direction::UP;
-        let mut cc = CodelChooser::LEFT;
+        let mut interp = interpreter_with_stack(&[1, 2, 3, 3, 1]);
+
+        roll(&mut interp, 3).unwrap();
+        assert_eq!(interp.stack, ints(&[3, 1, 2]));
+    }
+    #[test]
+    fn roll_negative_and_huge() {
+        let mut interp = interpreter_with_stack(&[1, 2, 3, 3, -4]);
+        roll(&mut interp, 3).unwrap();
+        assert_eq!(interp.stack, ints(&[2, 3, 1]));
 
-        roll(3, &mut stack, &mut cc, &mut dp);
-        assert_eq!(stack, [3, 1, 2]);
+        interp.stack.push("100000000000000000000".parse().unwrap());
+        interp.stack.push(Int::from(1));
+        roll(&mut interp, 3).unwrap();
+        assert_eq!(interp.stack.len(), 5);
     }
     #[test]
     fn switch_test() {
-        let mut stack = vec![1, 2, 3, 3, 1];
-        let mut dp = Direction::UP;
-        let mut cc = CodelChooser::LEFT;
+        let mut interp = interpreter_with_stack(&[1, 2, 3, 3, 1]);
+
+        switch(&mut interp, 3).unwrap();
+        assert_eq!(interp.stack, ints(&[1, 2, 3, 3]));
+        assert_eq!(interp.cc, CodelChooser::RIGHT);
+    }
+    #[test]
+    fn pointer_rotates_anticlockwise_for_negative() {
+        let mut interp = interpreter_with_stack(&[-1]);
+
+        pointer(&mut interp, 3).unwrap();
+        assert_eq!(interp.dp, Direction::LEFT);
+
+        interp.stack.push("-100000000000000000001".parse().unwrap());
+        pointer(&mut interp, 3).unwrap();
+        assert_eq!(interp.dp, Direction::DOWN);
+    }
+    #[test]
+    fn backend_decides_on_overflow() {
+        let mut interp = interpreter_with_stack(&[i32::MAX as i64, 2]);
+        mult(&mut interp, 1).unwrap();
+        assert_eq!(interp.stack, ints(&[-2]));
+
+        let mut interp = interpreter_with_stack(&[i64::MAX, 2]);
+        interp.backend = IntBackend::I64;
+        interp.overflow = Overflow::Error;
+        assert_eq!(mult(&mut interp, 1), Err(RuntimeError::Overflow));
+
+        let mut interp = interpreter_with_stack(&[i32::MIN as i64, 1]);
+        interp.overflow = Overflow::Saturate;
+        sub(&mut interp, 1).unwrap();
+        assert_eq!(interp.stack, ints(&[i32::MIN as i64]));
+
+        let mut interp = interpreter_with_stack(&[i64::MAX, 2]);
+        interp.backend = IntBackend::Big;
+        mult(&mut interp, 1).unwrap();
+        assert_eq!(interp.stack[0].to_string(), "18446744073709551614");
+    }
+    #[test]
+    fn division_modes() {
+        let mut interp = interpreter_with_stack(&[-7, 2]);
+        div(&mut interp, 1).unwrap();
+        interp.stack.append(&mut ints(&[7, -2]));
+        modulo(&mut interp, 1).unwrap();
+        assert_eq!(interp.stack, ints(&[-4, -1]));
+
+        let mut interp = interpreter_with_stack(&[-7, 2]);
+        interp.division = Division::Truncate;
+        div(&mut interp, 1).unwrap();
+        interp.stack.append(&mut ints(&[-7, 2]));
+        modulo(&mut interp, 1).unwrap();
+        assert_eq!(interp.stack, ints(&[-3, -1]));
+    }
+    #[test]
+    fn min_div_minus_one_overflows() {
+        let mut interp = interpreter_with_stack(&[i32::MIN as i64, -1]);
+        div(&mut interp, 1).unwrap();
+        assert_eq!(interp.stack, ints(&[i32::MIN as i64]));
+
+        let mut interp = interpreter_with_stack(&[i32::MIN as i64, -1]);
+        interp.overflow = Overflow::Error;
+        assert_eq!(div(&mut interp, 1), Err(RuntimeError::Overflow));
+    }
+    #[test]
+    fn underflow_leaves_the_stack() {
+        for command in [Command::Add, Command::Div, Command::Greater, Command::Roll] {
+            let mut interp = interpreter_with_stack(&[5]);
+            interp.execute_command(command, 1).unwrap();
+            assert_eq!(interp.stack, ints(&[5]));
+            assert_eq!(interp.ignored, Some(Ignored::Underflow { command, len: 1 }));
+        }
+        let mut interp = interpreter_with_stack(&[]);
+        interp.execute_command(Command::OutNum, 1).unwrap();
+        assert_eq!(
+            interp.ignored.unwrap().to_string(),
+            "out_num needs a value but the stack is empty"
+        );
+    }
+    #[test]
+    fn ignored_commands_say_why() {
+        let mut interp = interpreter_with_stack(&[7, 0]);
+        interp.execute_command(Command::Mod, 1).unwrap();
+        assert_eq!(interp.stack, ints(&[7, 0]));
+        assert_eq!(interp.ignored, Some(Ignored::DivisionByZero(Command::Mod)));
+        assert_eq!(interp.ignored.clone().unwrap().to_string(), "mod by zero");
+
+        let mut interp = interpreter_with_stack(&[1, 2, 3, 1]);
+        interp.execute_command(Command::Roll, 1).unwrap();
+        assert_eq!(interp.stack, ints(&[1, 2, 3, 1]));
+        assert_eq!(
+            interp.ignored.clone().unwrap().to_string(),
+            "roll to depth 3 with 2 values below"
+        );
+
+        // the next command starts over
+        interp.execute_command(Command::Pop, 1).unwrap();
+        assert_eq!(interp.ignored, None);
+    }
+    #[test]
+    fn in_char_ignored_on_eof() {
+        let mut interp = interpreter_with_stack(&[1]);
 
-        switch(3, &mut stack, &mut cc, &mut dp);
-        assert_eq!(stack, [1, 2, 3, 3]);
-        assert_eq!(cc, CodelChooser::RIGHT);
+        in_char(&mut interp, 1).unwrap();
+        assert_eq!(interp.stack, ints(&[1]));
     }
 }
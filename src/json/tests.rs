@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod tests {
+    use crate::json::*;
+
+    #[test]
+    fn serializes_nested_values() {
+        let value = Json::object(vec![
+            ("name", Json::string("dark \"red\"\n")),
+            ("size", Json::number(12)),
+            ("stack", Json::Array(vec![Json::number(-1), Json::Null])),
+            ("ok", Json::Bool(true)),
+        ]);
+
+        assert_eq!(
+            value.to_string(),
+            r#"{"name":"dark \"red\"\n","size":12,"stack":[-1,null],"ok":true}"#
+        );
+    }
+    #[test]
+    fn escapes_control_chars() {
+        assert_eq!(Json::string("\u{1}").to_string(), r#""\u0001""#);
+    }
+    #[test]
+    fn parses_what_it_serializes() {
+        let text =
+            r#"{"name":"dark \"red\"\n","size":12,"stack":[-1,null,2.5e3],"ok":true,"empty":{}}"#;
+        let value: Json = text.parse().unwrap();
+
+        assert_eq!(value.to_string(), text);
+        assert_eq!(
+            value.get("name").and_then(Json::as_str),
+            Some("dark \"red\"\n")
+        );
+        assert_eq!(value.get("size").and_then(Json::as_i64), Some(12));
+        assert_eq!(value.get("ok").and_then(Json::as_bool), Some(true));
+        assert_eq!(
+            value.get("stack").and_then(Json::as_array).unwrap().len(),
+            3
+        );
+        assert_eq!(value.get("missing"), None);
+    }
+    #[test]
+    fn parses_whitespace_and_unicode_escapes() {
+        let value: Json = " [ \"\\u00e4\" , { \"a\" : false } ] ".parse().unwrap();
+        assert_eq!(
+            value,
+            Json::Array(vec![
+                Json::string("ä"),
+                Json::object(vec![("a", Json::Bool(false))])
+            ])
+        );
+    }
+    #[test]
+    fn rejects_malformed_input() {
+        assert!("{\"a\" 1}".parse::<Json>().is_err());
+        assert!("[1, 2".parse::<Json>().is_err());
+        assert!("tru".parse::<Json>().is_err());
+        assert!("1 2".parse::<Json>().is_err());
+    }
+}
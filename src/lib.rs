@@ -7,8 +7,38 @@
 // pub use types::*;
 
 #![allow(unused)]
+// every module keeps its unit-tests in a `tests` submodule
+#![allow(clippy::module_inception)]
+pub mod animation;
+pub mod assembler;
+pub mod blocks;
 pub mod cli_options;
+pub mod coverage;
+pub mod dap;
+pub mod debugger;
 pub mod decoder;
+pub mod disasm;
+pub mod dot;
+pub mod encoder;
+pub mod generator;
+pub mod gif;
+pub mod graph;
 pub mod interpreter;
+pub mod json;
+pub mod lint;
+pub mod machine;
 pub mod navigation;
+pub mod number;
+pub mod piet_io;
+pub mod profile;
+pub mod show;
+pub mod snapshot;
+pub mod stack_depth;
+// loads the programs in `tests/fixtures` for the unit tests
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod text_grid;
+pub mod trace;
+pub mod transpiler;
+pub mod tui;
 pub mod types;
@@ -0,0 +1,299 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::blocks::BlockMap;
+use crate::decoder::try_infer_codel_size;
+use crate::graph::*;
+use crate::interpreter::*;
+use crate::json::Json;
+use crate::stack_depth::*;
+use crate::types::*;
+
+mod tests;
+
+/// What a finding is about.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Check {
+    /// Pixels outside of the 18 colors, white and black.
+    Color,
+    /// Codels that aren't one color, or an image that isn't made of whole codels.
+    CodelSize,
+    /// Color blocks the program never gets to.
+    Unreachable,
+    /// Commands that never have enough values on the stack.
+    Underflow,
+    /// Commands that don't always have enough values on the stack.
+    MayUnderflow,
+    /// Where the program gets into a loop it can't leave.
+    Endless,
+}
+
+impl Check {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Check::Color => "color",
+            Check::CodelSize => "codel-size",
+            Check::Unreachable => "unreachable",
+            Check::Underflow => "underflow",
+            Check::MayUnderflow => "may-underflow",
+            Check::Endless => "endless",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub check: Check,
+    /// The codel the finding is about, the top left codel for blocks.
+    pub codel: Coordinates,
+    pub message: String,
+}
+
+impl Finding {
+    fn new(check: Check, codel: Coordinates, message: String) -> Finding {
+        Finding {
+            check,
+            codel,
+            message,
+        }
+    }
+
+    pub fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("check", Json::string(self.check.name())),
+            ("x", Json::number(self.codel.x)),
+            ("y", Json::number(self.codel.y)),
+            ("message", Json::string(&self.message)),
+        ])
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "({}, {}) {}: {}",
+            self.codel.x,
+            self.codel.y,
+            self.check.name(),
+            self.message
+        )
+    }
+}
+
+fn hex(RGB(r, g, b): RGB) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+// one finding per codel with pixels outside the palette and per codel that isn't one color
+fn check_pixels(rgb_img: &[Vec<RGB>], codel_size: i32, findings: &mut Vec<Finding>) {
+    let size = codel_size as usize;
+    let (width, height) = (rgb_img[0].len() / size, rgb_img.len() / size);
+    if width * size != rgb_img[0].len() || height * size != rgb_img.len() {
+        findings.push(Finding::new(
+            Check::CodelSize,
+            Coordinates { x: 0, y: 0 },
+            format!(
+                "the image is {}x{} pixels, which isn't a multiple of the codel size {}",
+                rgb_img[0].len(),
+                rgb_img.len(),
+                codel_size
+            ),
+        ));
+    }
+
+    for y in 0..height {
+        for x in 0..width {
+            let codel = Coordinates {
+                x: x as i32,
+                y: y as i32,
+            };
+            let pixels = || {
+                (y * size..(y + 1) * size)
+                    .flat_map(move |py| (x * size..(x + 1) * size).map(move |px| (px, py)))
+            };
+            let invalid = pixels().find(|(px, py)| {
+                let color = rgb_img[*py][*px];
+                get_color_index(color).is_none() && color != WHITE && color != BLACK
+            });
+            if let Some((px, py)) = invalid {
+                findings.push(Finding::new(
+                    Check::Color,
+                    codel,
+                    format!(
+                        "pixel ({}, {}) is {}, which isn't one of the 20 colors",
+                        px,
+                        py,
+                        hex(rgb_img[py][px])
+                    ),
+                ));
+            }
+            let first = rgb_img[y * size][x * size];
+            let differing = pixels().find(|(px, py)| rgb_img[*py][*px] != first);
+            if let Some((px, py)) = differing {
+                findings.push(Finding::new(
+                    Check::CodelSize,
+                    codel,
+                    format!(
+                        "the codel isn't one color, pixel ({}, {}) is {} instead of {}",
+                        px,
+                        py,
+                        hex(rgb_img[py][px]),
+                        hex(first)
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+fn describe(node: &Node) -> String {
+    let codels = if node.size == 1 { "codel" } else { "codels" };
+    format!(
+        "{} block of {} {}",
+        color_name(node.color),
+        node.size,
+        codels
+    )
+}
+
+fn check_unreachable(graph: &Graph, reachable: &[State], findings: &mut Vec<Finding>) {
+    let reached: HashSet<usize> = reachable.iter().map(|state| state.node).collect();
+    for (id, node) in graph.nodes.iter().enumerate() {
+        if node.block.is_some() && !reached.contains(&id) {
+            findings.push(Finding::new(
+                Check::Unreachable,
+                node.codels[0],
+                format!("the {} is never reached", describe(node)),
+            ));
+        }
+    }
+}
+
+fn check_underflow(graph: &Graph, depths: &StackDepths, findings: &mut Vec<Finding>) {
+    for underflow in depths.underflows(graph) {
+        let (state, depth) = (underflow.state, underflow.depth);
+        let edge = graph.edge(state).unwrap();
+        let needed = match underflow.command.operands() {
+            1 => "a value",
+            _ => "2 values",
+        };
+        let (check, problem) = match depth.max {
+            Some(0) => (Check::Underflow, String::from("the stack is always empty")),
+            Some(1) if underflow.always() => (
+                Check::Underflow,
+                String::from("there is at most 1 value on the stack"),
+            ),
+            _ if depth.min == 0 => (Check::MayUnderflow, String::from("the stack can be empty")),
+            _ => (
+                Check::MayUnderflow,
+                String::from("there can be just 1 value on the stack"),
+            ),
+        };
+        let what = if check == Check::Underflow {
+            "always underflows"
+        } else {
+            "can underflow"
+        };
+        findings.push(Finding::new(
+            check,
+            graph.nodes[state.node].codels[0],
+            format!(
+                "{} into {} with dp={:?} cc={:?} {}, it needs {} but {}",
+                underflow.command.name(),
+                color_name(graph.nodes[edge.to].color),
+                state.dp,
+                state.cc,
+                what,
+                needed,
+                problem
+            ),
+        ));
+    }
+}
+
+// states the program can't terminate from are reported where it gets into them
+fn check_endless(
+    graph: &Graph,
+    depths: &StackDepths,
+    reachable: &[State],
+    findings: &mut Vec<Finding>,
+) {
+    let mut predecessors = vec![Vec::new(); graph.nodes.len() * 8];
+    let mut terminates = vec![false; graph.nodes.len() * 8];
+    let mut todo = Vec::new();
+    for state in reachable {
+        let successors = depths.successors(graph, *state);
+        if successors.is_empty() {
+            terminates[state.index()] = true;
+            todo.push(*state);
+        }
+        for next in successors {
+            predecessors[next.index()].push(*state);
+        }
+    }
+    while let Some(state) = todo.pop() {
+        for prev in &predecessors[state.index()] {
+            if !terminates[prev.index()] {
+                terminates[prev.index()] = true;
+                todo.push(*prev);
+            }
+        }
+    }
+
+    for state in reachable {
+        if terminates[state.index()] {
+            continue;
+        }
+        let node = &graph.nodes[state.node];
+        if *state == graph.start() {
+            findings.push(Finding::new(
+                Check::Endless,
+                node.codels[0],
+                String::from("the program never terminates"),
+            ));
+        } else if predecessors[state.index()]
+            .iter()
+            .any(|prev| terminates[prev.index()])
+        {
+            findings.push(Finding::new(
+                Check::Endless,
+                node.codels[0],
+                format!(
+                    "the program never terminates once it enters the {} with dp={:?} cc={:?}",
+                    describe(node),
+                    state.dp,
+                    state.cc
+                ),
+            ));
+        }
+    }
+}
+
+/// Looks for problems in a program: pixels outside of the 20 colors, codels that aren't one color,
+/// blocks that are never reached, commands that (can) underflow the stack and states the program
+/// can't terminate from. Without `codel_size` it is inferred, if that fails it is 1.
+pub fn lint(rgb_img: &[Vec<RGB>], codel_size: Option<i32>) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let codel_size = match codel_size.map_or_else(|| try_infer_codel_size(rgb_img), Ok) {
+        Ok(size) => size,
+        Err(why) => {
+            findings.push(Finding::new(
+                Check::CodelSize,
+                Coordinates { x: 0, y: 0 },
+                format!("{}, using 1", why),
+            ));
+            1
+        }
+    };
+    check_pixels(rgb_img, codel_size, &mut findings);
+
+    let graph = Graph::compile(&BlockMap::new(rgb_img, codel_size));
+    // branches that are never taken don't count
+    let depths = StackDepths::analyze(&graph);
+    let reachable = depths.reachable(&graph);
+    check_unreachable(&graph, &reachable, &mut findings);
+    check_underflow(&graph, &depths, &mut findings);
+    check_endless(&graph, &depths, &reachable, &mut findings);
+    findings
+}
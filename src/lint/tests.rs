@@ -0,0 +1,143 @@
+#[cfg(test)]
+mod tests {
+    use crate::assembler::assemble;
+    use crate::lint::*;
+    use crate::test_support::{load_image, HELLO_WORLD};
+
+    const RED: RGB = RGB(255, 0, 0);
+    const BLUE: RGB = RGB(0, 0, 255);
+
+    // the assembler lays out turns for jumps from both sides, so some are never reached
+    fn lint_source(source: &str) -> Vec<Finding> {
+        lint(&assemble(source).unwrap(), Some(1))
+            .into_iter()
+            .filter(|finding| finding.check != Check::Unreachable)
+            .collect()
+    }
+
+    fn checks(findings: &[Finding]) -> Vec<Check> {
+        findings.iter().map(|finding| finding.check).collect()
+    }
+
+    #[test]
+    fn assembled_programs_are_clean() {
+        assert_eq!(lint_source("push 3\nout_num\n"), vec![]);
+        // the branches of jz can only be taken with 0 or 1
+        let countdown = "push 3\nloop:\ndup\nout_num\npush 1\nsub\ndup\njnz loop\npop\n";
+        assert_eq!(lint_source(countdown), vec![]);
+    }
+
+    #[test]
+    fn finds_commands_that_underflow() {
+        let findings = lint_source("push 1\nadd\nout_num\n");
+        // the ignored add leaves the 1 for out_num
+        assert_eq!(checks(&findings), vec![Check::Underflow]);
+        assert!(findings[0].message.starts_with("add into"));
+        assert!(findings[0]
+            .message
+            .ends_with("it needs 2 values but there is at most 1 value on the stack"));
+        let findings = lint_source("pop\nout_num\n");
+        assert_eq!(checks(&findings), vec![Check::Underflow; 2]);
+        assert!(findings[1].message.ends_with("the stack is always empty"));
+
+        // the loop can push any number of values, but ends right away without input
+        let growing = "push 1\nloop:\npush 1\nin_num\njnz loop\nadd\nadd\nout_num\n";
+        let findings = lint_source(growing);
+        assert_eq!(checks(&findings), vec![Check::MayUnderflow; 2]);
+        assert!(findings[0].message.starts_with("add into"));
+        assert!(findings[0].message.ends_with(
+            "can underflow, it needs 2 values but there can be just 1 value on the stack"
+        ));
+    }
+
+    #[test]
+    fn finds_loops_without_end() {
+        let findings = lint_source("loop:\npush 1\npop\njmp loop\n");
+        assert_eq!(
+            findings,
+            vec![Finding {
+                check: Check::Endless,
+                codel: Coordinates { x: 0, y: 0 },
+                message: String::from("the program never terminates"),
+            }]
+        );
+
+        // jz can't pop anything at the end of input
+        let findings = lint_source("in_num\njz forever\nhalt\nforever:\njmp forever\n");
+        assert_eq!(
+            checks(&findings),
+            vec![Check::MayUnderflow, Check::MayUnderflow, Check::Endless]
+        );
+        assert!(findings[2]
+            .message
+            .starts_with("the program never terminates once it enters the"));
+    }
+
+    #[test]
+    fn finds_unreachable_blocks() {
+        let img = assemble("jmp end\npush 2\nout_num\nend:\n").unwrap();
+        let findings = lint(&img, Some(1));
+        // the block pushing 2 comes right after the jump
+        assert!(findings.iter().any(|f| f.check == Check::Unreachable
+            && f.message == "the light red block of 2 codels is never reached"));
+
+        let (img, _) = load_image(HELLO_WORLD);
+        let findings = lint(&img, None);
+        assert_eq!(
+            findings.iter().map(|f| f.to_string()).collect::<Vec<_>>(),
+            vec!["(8, 1) unreachable: the cyan block of 9 codels is never reached"]
+        );
+    }
+
+    #[test]
+    fn finds_pixels_outside_the_palette() {
+        // 2x2 codels of 2 pixels, the bottom right one has a pixel that doesn't belong
+        let mut img = vec![vec![RED; 4]; 4];
+        img[3][3] = RGB(1, 2, 3);
+        let findings = lint(&img, Some(2));
+        let (color, codel) = (&findings[0], &findings[1]);
+        assert_eq!(color.check, Check::Color);
+        assert_eq!(color.codel, Coordinates { x: 1, y: 1 });
+        assert_eq!(
+            color.message,
+            "pixel (3, 3) is #010203, which isn't one of the 20 colors"
+        );
+        assert_eq!(codel.check, Check::CodelSize);
+        assert_eq!(codel.codel, Coordinates { x: 1, y: 1 });
+        assert_eq!(
+            codel.message,
+            "the codel isn't one color, pixel (3, 3) is #010203 instead of #ff0000"
+        );
+        assert_eq!(findings.len(), 2);
+    }
+
+    #[test]
+    fn finds_codel_size_problems() {
+        let findings = lint(&vec![vec![RED; 3]; 3], Some(2));
+        assert_eq!(findings[0].check, Check::CodelSize);
+        assert_eq!(
+            findings[0].message,
+            "the image is 3x3 pixels, which isn't a multiple of the codel size 2"
+        );
+
+        let findings = lint(&[vec![RED, RED, BLUE]], None);
+        assert_eq!(findings[0].check, Check::CodelSize);
+        assert_eq!(
+            findings[0].message,
+            "inferred codel-size doesnt fit image-dimensions, using 1"
+        );
+    }
+
+    #[test]
+    fn findings_as_json() {
+        let finding = Finding {
+            check: Check::Unreachable,
+            codel: Coordinates { x: 8, y: 1 },
+            message: String::from("the cyan block of 9 codels is never reached"),
+        };
+        assert_eq!(
+            finding.to_json().to_string(),
+            r#"{"check":"unreachable","x":8,"y":1,"message":"the cyan block of 9 codels is never reached"}"#
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use std::time::{Duration, Instant};
+
+use crate::blocks::BlockMap;
+use crate::graph::{Graph, State};
+use crate::interpreter::*;
+use crate::number::Int;
+use crate::types::*;
+
+mod tests;
+
+/// Why `Machine::run` stopped.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Halt {
+    /// The program terminated on its own.
+    Terminated,
+    StepLimit,
+    Timeout,
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Limits {
+    pub max_steps: Option<u64>,
+    /// Checked between steps, so a step waiting for input isn't interrupted.
+    pub timeout: Option<Duration>,
+}
+
+/// What happened during a single step.
+#[derive(Clone, Debug)]
+pub struct Step {
+    /// The position the block that was left was entered at.
+    pub from_pos: Coordinates,
+    /// The block that was left, its size is used by `push`.
+    pub from_color: ColorInfo,
+    /// DP and CC the block was left with, before getting around obstacles.
+    pub dp: Direction,
+    pub cc: CodelChooser,
+    pub command: Command,
+    /// Why the command didn't do anything, if it was ignored.
+    pub ignored: Option<Ignored>,
+}
+
+/// Everything needed to take back a step.
+struct Undo {
+    pos: Coordinates,
+    color: ColorInfo,
+    dp: Direction,
+    cc: CodelChooser,
+    /// The stack is truncated to this length and `saved` is put back on top.
+    keep: usize,
+    saved: Vec<Int>,
+    /// How much input had been read before the step.
+    input: usize,
+}
+
+/// A piet program together with the state of its execution.
+pub struct Machine {
+    pub rgb_img: Vec<Vec<RGB>>,
+    pub codel_size: i32,
+    /// The color blocks of the program, labeled once when it is loaded.
+    pub blocks: BlockMap,
+    /// Set by `compile`, steps are looked up in it instead of the blocks then.
+    pub graph: Option<Graph>,
+    pub pos: Coordinates,
+    pub current_color: ColorInfo,
+    pub interp: Interpreter,
+    /// Number of color blocks that were entered so far.
+    pub steps: u64,
+    /// One entry per step, only kept after `record_history` was called.
+    history: Option<Vec<Undo>>,
+}
+
+impl Machine {
+    pub fn new(rgb_img: Vec<Vec<RGB>>, codel_size: i32, interp: Interpreter) -> Machine {
+        let pos = Coordinates { x: 0, y: 0 };
+        let blocks = BlockMap::new(&rgb_img, codel_size);
+        let current_color = blocks.color_info(pos);
+        Machine {
+            rgb_img,
+            codel_size,
+            blocks,
+            graph: None,
+            pos,
+            current_color,
+            interp,
+            steps: 0,
+            history: None,
+        }
+    }
+
+    /// Compiles the program into a graph of states that following steps run on.
+    pub fn compile(&mut self) {
+        self.graph = Some(Graph::compile(&self.blocks));
+    }
+
+    // the next block from `codel`, taken from the graph if the program is compiled
+    fn next_color(
+        &self,
+        codel: &mut Coordinates,
+        dp: &mut Direction,
+        cc: &mut CodelChooser,
+    ) -> Option<ColorInfo> {
+        let graph = match &self.graph {
+            Some(graph) => graph,
+            None => return self.blocks.next_color(codel, dp, cc),
+        };
+        let state = State {
+            node: graph.node(*codel),
+            dp: *dp,
+            cc: *cc,
+        };
+        let edge = graph.edge(state)?;
+        *codel = edge.codel;
+        *dp = edge.dp;
+        *cc = edge.cc;
+        let node = &graph.nodes[edge.to];
+        Some(ColorInfo {
+            color: node.color,
+            size: node.size,
+        })
+    }
+
+    /// Keeps what is needed to undo every following step with `step_back`,
+    /// including the input the program reads.
+    pub fn record_history(&mut self) {
+        self.history.get_or_insert_with(Vec::new);
+        self.interp.io.record_input();
+    }
+
+    /// Forgets the recorded steps, for when the stack, DP or CC were changed by other means than
+    /// stepping. Taking back a step before the change would build a state that never existed.
+    pub fn clear_history(&mut self) {
+        if let Some(history) = &mut self.history {
+            history.clear();
+        }
+    }
+
+    /// Takes back the last recorded step, `false` if there is none.
+    /// Output that was written can't be taken back.
+    pub fn step_back(&mut self) -> bool {
+        let undo = match self.history.as_mut().and_then(Vec::pop) {
+            Some(undo) => undo,
+            None => return false,
+        };
+        self.pos = undo.pos;
+        self.current_color = undo.color;
+        self.interp.dp = undo.dp;
+        self.interp.cc = undo.cc;
+        self.interp.stack.truncate(undo.keep);
+        self.interp.stack.extend(undo.saved);
+        self.interp.io.unread(undo.input);
+        self.steps -= 1;
+        true
+    }
+
+    /// Moves to the next color block and executes the command.
+    /// Returns `None` if there is no next block, meaning the program terminated.
+    pub fn step(&mut self) -> Result<Option<Step>, RuntimeError> {
+        let prev_pos = self.pos;
+        let prev_color = self.current_color;
+        let (prev_dp, prev_cc) = (self.interp.dp, self.interp.cc);
+        let mut codel = self.codel();
+        let (mut dp, mut cc) = (self.interp.dp, self.interp.cc);
+        self.current_color = match self.next_color(&mut codel, &mut dp, &mut cc) {
+            Some(new_color) => new_color,
+            None => return Ok(None),
+        };
+        self.interp.dp = dp;
+        self.interp.cc = cc;
+        self.pos = self.to_pixel(codel);
+        self.steps += 1;
+        if let Some(history) = &mut self.history {
+            let command = decode_command(prev_color.color, self.current_color.color);
+            let keep = self.interp.stack.len() - self.interp.touched(command);
+            history.push(Undo {
+                pos: prev_pos,
+                color: prev_color,
+                dp: prev_dp,
+                cc: prev_cc,
+                keep,
+                saved: self.interp.stack[keep..].to_vec(),
+                input: self.interp.io.consumed_len(),
+            });
+        }
+        let command = self.interp.execute(prev_color, &self.current_color)?;
+        let ignored = self.interp.ignored.take();
+        if let (Some(why), Strict::Error) = (&ignored, self.interp.strict) {
+            return Err(RuntimeError::Ignored {
+                step: self.steps,
+                codel: self.to_codel(prev_pos),
+                why: why.clone(),
+            });
+        }
+        Ok(Some(Step {
+            from_pos: prev_pos,
+            from_color: prev_color,
+            dp: prev_dp,
+            cc: prev_cc,
+            command,
+            ignored,
+        }))
+    }
+
+    /// Steps until the program terminates or a limit is reached,
+    /// `on_step` is called after every step.
+    pub fn run<F: FnMut(&Machine, &Step)>(
+        &mut self,
+        limits: &Limits,
+        mut on_step: F,
+    ) -> Result<Halt, RuntimeError> {
+        let start = Instant::now();
+        loop {
+            if limits.max_steps.is_some_and(|max| self.steps >= max) {
+                return Ok(Halt::StepLimit);
+            }
+            if limits.timeout.is_some_and(|t| start.elapsed() >= t) {
+                return Ok(Halt::Timeout);
+            }
+            match self.step()? {
+                Some(step) => on_step(self, &step),
+                None => return Ok(Halt::Terminated),
+            }
+        }
+    }
+
+    /// The position the next step will move to and the command it will execute,
+    /// without changing anything. `None` if the program is about to terminate.
+    pub fn peek(&self) -> Option<(Coordinates, Command)> {
+        let mut codel = self.codel();
+        let mut dp = self.interp.dp;
+        let mut cc = self.interp.cc;
+        let next = self.next_color(&mut codel, &mut dp, &mut cc)?;
+        Some((
+            self.to_pixel(codel),
+            decode_command(self.current_color.color, next.color),
+        ))
+    }
+
+    /// All codels (in pixels) of the block the machine is currently in.
+    pub fn current_block(&self) -> Vec<Coordinates> {
+        self.blocks
+            .block_codels(self.codel(), self.interp.dp)
+            .into_iter()
+            .map(|codel| self.to_pixel(codel))
+            .collect()
+    }
+
+    /// The current position in codels instead of pixels.
+    pub fn codel(&self) -> Coordinates {
+        self.to_codel(self.pos)
+    }
+
+    pub fn to_codel(&self, pos: Coordinates) -> Coordinates {
+        Coordinates {
+            x: pos.x / self.codel_size,
+            y: pos.y / self.codel_size,
+        }
+    }
+
+    pub fn to_pixel(&self, codel: Coordinates) -> Coordinates {
+        Coordinates {
+            x: codel.x * self.codel_size,
+            y: codel.y * self.codel_size,
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        let stack: Vec<String> = self.interp.stack.iter().map(|n| n.to_string()).collect();
+        format!(
+            "steps: {}\nposition: ({}, {})\ndp: {:?}, cc: {:?}\nstack: [{}]",
+            self.steps,
+            self.codel().x,
+            self.codel().y,
+            self.interp.dp,
+            self.interp.cc,
+            stack.join(", ")
+        )
+    }
+}
@@ -0,0 +1,123 @@
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::Interpreter;
+    use crate::machine::*;
+    use crate::piet_io::*;
+    use crate::test_support::load;
+    use std::io::{empty, Cursor};
+    use std::time::Duration;
+
+    #[test]
+    fn runs_until_terminated() {
+        let (mut machine, out) = load("tests/fixtures/piet_hello_world.png");
+
+        let halt = machine.run(&Limits::default(), |_, _| ()).unwrap();
+        assert_eq!(halt, Halt::Terminated);
+        assert_eq!(String::from_utf8(out.contents()).unwrap(), "Hello world!");
+        assert!(machine.step().unwrap().is_none());
+    }
+    #[test]
+    fn stops_at_step_limit() {
+        let (mut machine, out) = load("tests/fixtures/piet_hello_world.png");
+        let limits = Limits {
+            max_steps: Some(10),
+            ..Limits::default()
+        };
+
+        assert_eq!(machine.run(&limits, |_, _| ()).unwrap(), Halt::StepLimit);
+        assert_eq!(machine.steps, 10);
+        assert!(out.contents().len() < "Hello world!".len());
+        assert!(machine.summary().starts_with("steps: 10\n"));
+    }
+    #[test]
+    fn stops_at_timeout() {
+        let (mut machine, _) = load("tests/fixtures/piet_hello_world.png");
+        let limits = Limits {
+            timeout: Some(Duration::from_secs(0)),
+            ..Limits::default()
+        };
+
+        assert_eq!(machine.run(&limits, |_, _| ()).unwrap(), Halt::Timeout);
+        assert_eq!(machine.steps, 0);
+    }
+    #[test]
+    fn steps_back_to_every_earlier_state() {
+        let (mut machine, _) = load("tests/fixtures/99bottles.png");
+        machine.record_history();
+
+        let state = |m: &Machine| {
+            (
+                m.pos,
+                m.current_color,
+                m.interp.dp,
+                m.interp.cc,
+                m.interp.stack.clone(),
+            )
+        };
+        let mut states = vec![state(&machine)];
+        for _ in 0..500 {
+            machine.step().unwrap().unwrap();
+            states.push(state(&machine));
+        }
+        while let Some(expected) = states.pop() {
+            assert_eq!(state(&machine), expected);
+            assert_eq!(machine.steps as usize, states.len());
+            machine.step_back();
+        }
+        assert!(!machine.step_back());
+    }
+    #[test]
+    fn stepping_back_gives_back_input() {
+        // light red -> light magenta is in_char, back to light red is add
+        let rgb_img = vec![vec![RGB(255, 192, 192), RGB(255, 192, 255)]];
+        let io = Io::new(
+            Box::new(Cursor::new(b"ab".to_vec())),
+            Box::new(SharedBuffer::default()),
+        );
+        let mut machine = Machine::new(rgb_img, 1, Interpreter::new(io));
+        machine.record_history();
+
+        machine.step().unwrap();
+        assert_eq!(machine.interp.stack, [Int::from('a' as i32)]);
+        assert!(machine.step_back());
+        assert!(machine.interp.stack.is_empty());
+        machine.step().unwrap();
+        assert_eq!(machine.interp.stack, [Int::from('a' as i32)]);
+        // add is ignored, then the next character is read
+        machine.step().unwrap();
+        machine.step().unwrap();
+        assert_eq!(
+            machine.interp.stack,
+            [Int::from('a' as i32), Int::from('b' as i32)]
+        );
+    }
+    #[test]
+    fn strict_reports_ignored_commands() {
+        // red -> light red is pop, with nothing on the stack
+        let rgb_img = vec![vec![RGB(255, 0, 0), RGB(255, 192, 192)]];
+        let why = Ignored::Underflow {
+            command: Command::Pop,
+            len: 0,
+        };
+        let io = || Io::new(Box::new(empty()), Box::new(SharedBuffer::default()));
+
+        let mut machine = Machine::new(rgb_img.clone(), 1, Interpreter::new(io()));
+        assert_eq!(machine.step().unwrap().unwrap().ignored, Some(why.clone()));
+
+        let mut machine = Machine::new(rgb_img, 1, Interpreter::new(io()));
+        machine.interp.strict = Strict::Error;
+        let error = machine.step().unwrap_err();
+        assert_eq!(
+            error,
+            RuntimeError::Ignored {
+                step: 1,
+                codel: Coordinates { x: 0, y: 0 },
+                why,
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "step 1 at (0, 0): pop needs a value but the stack is empty"
+        );
+    }
+}
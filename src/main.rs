@@ -1,48 +1,457 @@
+use clap::ArgMatches;
 use std::fs::File;
+use std::io::{self, BufReader, BufWriter, IsTerminal, Write};
+use std::time::Duration;
 
+use pint::animation::*;
+use pint::assembler::*;
 use pint::cli_options::*;
+use pint::coverage::*;
+use pint::dap::*;
+use pint::debugger::*;
 use pint::decoder::*;
+use pint::disasm::*;
+use pint::dot::*;
+use pint::encoder::*;
+use pint::generator::*;
+use pint::gif::*;
+use pint::graph::*;
 use pint::interpreter::*;
-use pint::navigation::*;
-use pint::types::*;
+use pint::json::Json;
+use pint::lint::*;
+use pint::machine::*;
+use pint::number::*;
+use pint::piet_io::*;
+use pint::profile::*;
+use pint::show::*;
+use pint::snapshot::*;
+use pint::text_grid::*;
+use pint::trace::*;
+use pint::transpiler::*;
+use pint::tui::*;
+use pint::types::RGB;
+
+// exit code when --max-steps or --timeout stopped the program
+const EXIT_LIMIT: i32 = 3;
 
 fn main() {
     let opt = cli_options();
 
-    let mut codel_size = match opt.value_of("codel_size") {
-        Some(v) => v.parse::<i32>().unwrap(),
-        None => -1,
+    match opt.subcommand() {
+        Some(("debug", sub)) if sub.is_present("tui") => {
+            // the output of the program is shown in a pane of the screen
+            let output = SharedBuffer::default();
+            let io = Io::new(Box::new(io::stdin()), Box::new(output.clone()));
+            Tui::new(Debugger::new(load_machine(sub, io)), output).run()
+        }
+        Some(("debug", sub)) => Debugger::new(load_machine(sub, Io::stdio())).repl(),
+        Some(("dap", sub)) => {
+            let sub = sub.clone();
+            let load = Box::new(move |path: &str, io| open_machine(&sub, path, io));
+            let mut adapter = Adapter::new(io::stdout(), load);
+            adapter.serve(spawn_reader(BufReader::new(io::stdin())));
+        }
+        Some(("graph", sub)) => {
+            let machine = load_machine(sub, Io::stdio());
+            print!(
+                "{}",
+                to_dot(
+                    &Graph::compile(&machine.blocks),
+                    sub.is_present("reachable")
+                )
+            );
+        }
+        Some(("disasm", sub)) => {
+            let machine = load_machine(sub, Io::stdio());
+            print!("{}", Listing::new(&Graph::compile(&machine.blocks)));
+        }
+        Some(("asm", sub)) => {
+            if let Err(why) = assemble_file(sub) {
+                eprintln!("pint: {}", why);
+                std::process::exit(1);
+            }
+        }
+        Some(("gen-print", sub)) => {
+            let img = print_program(sub.value_of("text").unwrap());
+            if let Err(why) = write_png(sub, &img) {
+                eprintln!("pint: {}", why);
+                std::process::exit(1);
+            }
+        }
+        Some(("transpile", sub)) => {
+            let machine = load_machine(sub, Io::stdio());
+            let interp = &machine.interp;
+            // the generated runtimes only know the default integers and characters
+            if interp.backend != IntBackend::I32
+                || interp.overflow != Overflow::Wrap
+                || interp.io.encoding != CharEncoding::Utf8
+                || interp.io.invalid_char != InvalidChar::Replace
+                || interp.strict != Strict::Off
+            {
+                eprintln!("pint: transpile only supports --int i32, --overflow wrap, --char-encoding utf8 and --invalid-char replace, without --strict");
+                std::process::exit(1);
+            }
+            let language = match sub.value_of("to") {
+                Some("rust") => Language::Rust,
+                _ => Language::C,
+            };
+            let graph = Graph::compile(&machine.blocks);
+            print!("{}", transpile(&graph, language, interp.division));
+        }
+        Some(("lint", sub)) => {
+            let rgb_img = match read_image(sub.value_of("file").unwrap()) {
+                Ok(rgb_img) => rgb_img,
+                Err(why) => {
+                    eprintln!("pint: {}", why);
+                    std::process::exit(1);
+                }
+            };
+            let codel_size = match sub.value_of("file").unwrap() {
+                path if is_text_grid(path) => Some(1),
+                _ => sub.value_of("codel_size").map(|v| v.parse().unwrap()),
+            };
+            let findings = lint(&rgb_img, codel_size);
+            if sub.is_present("json") {
+                println!(
+                    "{}",
+                    Json::Array(findings.iter().map(Finding::to_json).collect())
+                );
+            } else {
+                for finding in &findings {
+                    println!("{}", finding);
+                }
+            }
+            if !findings.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        Some(("render-trace", sub)) => {
+            if let Err(why) = render_trace(sub) {
+                eprintln!("pint: {}", why);
+                std::process::exit(1);
+            }
+        }
+        Some(("show", sub)) => {
+            // the output of the program would get in between the lines
+            let io = Io::new(Box::new(io::stdin()), Box::new(io::sink()));
+            let mut machine = load_machine(sub, io);
+            let style = match sub.value_of("color") {
+                Some("always") => Style::Color,
+                Some("never") => Style::Letters,
+                _ if io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none() => {
+                    Style::Color
+                }
+                _ => Style::Letters,
+            };
+            let mut path = Vec::new();
+            if sub.is_present("path") {
+                let limits = Limits {
+                    max_steps: sub.value_of("max_steps").map(|n| n.parse().unwrap()),
+                    timeout: None,
+                };
+                let (codels, halt) = execution_path(&mut machine, &limits);
+                match halt {
+                    Ok(Halt::StepLimit) => eprintln!("pint: step limit reached"),
+                    Err(why) => eprintln!("pint: {}", why),
+                    _ => (),
+                }
+                path = codels;
+            }
+            print!(
+                "{}",
+                show(&machine.blocks, style, sub.is_present("ids"), &path)
+            );
+        }
+        Some(("convert", sub)) => {
+            if let Err(why) = convert(sub) {
+                eprintln!("pint: {}", why);
+                std::process::exit(1);
+            }
+        }
+        _ => run(&opt),
+    }
+}
+
+fn assemble_file(opt: &ArgMatches) -> Result<(), String> {
+    let source = std::fs::read_to_string(opt.value_of("source").unwrap())
+        .map_err(|why| format!("couldn't read source: {}", why))?;
+    write_png(opt, &assemble(&source)?)
+}
+
+// runs the program and writes a frame per step to a GIF or a directory of pngs
+fn render_trace(opt: &ArgMatches) -> Result<(), String> {
+    let mut machine = open_machine(opt, opt.value_of("file").unwrap(), Io::stdio())?;
+    let scale = opt.value_of("scale").unwrap().parse().unwrap();
+    let max_frames: usize = opt.value_of("max_frames").unwrap().parse().unwrap();
+    // GIFs count in hundredths of a second
+    let delay = opt.value_of("delay").unwrap().parse::<u64>().unwrap() / 10;
+    let delay = delay.min(u16::MAX as u64) as u16;
+    let out = opt.value_of("out").unwrap();
+    let is_gif = out.ends_with(".gif");
+    if is_gif {
+        // before drawing the program, which may be too large to keep
+        let (width, height) = Renderer::frame_size(&machine, scale);
+        check_size(width, height)?;
+    }
+    let renderer = Renderer::new(&machine, scale);
+
+    let mut gif = is_gif
+        .then(|| GifEncoder::new(renderer.width, renderer.height, renderer.palette(), delay))
+        .transpose()?;
+    if gif.is_none() {
+        std::fs::create_dir_all(out).map_err(|why| format!("couldn't create {}: {}", out, why))?;
+    }
+    let mut frames = 0;
+    let mut add = |frame: Vec<Vec<RGB>>| -> Result<(), String> {
+        match &mut gif {
+            Some(gif) => gif.add_frame(&frame),
+            None => {
+                let path = std::path::Path::new(out).join(format!("frame-{:05}.png", frames));
+                std::fs::write(path, encode_png(&frame, 1))
+                    .map_err(|why| format!("couldn't write frame: {}", why))?;
+            }
+        }
+        frames += 1;
+        Ok(())
+    };
+
+    add(renderer.frame(&machine, None))?;
+    let mut halt = Ok(());
+    for _ in 1..max_frames {
+        match machine.step() {
+            Ok(Some(step)) => add(renderer.frame(&machine, Some(&step)))?,
+            Ok(None) => break,
+            Err(why) => {
+                halt = Err(why.to_string());
+                break;
+            }
+        }
+    }
+    let _ = machine.interp.io.flush();
+    if let Some(gif) = gif {
+        std::fs::write(out, gif.finish())
+            .map_err(|why| format!("couldn't write {}: {}", out, why))?;
+    }
+    halt
+}
+
+// turns a png into a codel grid or the other way round
+fn convert(opt: &ArgMatches) -> Result<(), String> {
+    let (input, output) = (
+        opt.value_of("file").unwrap(),
+        opt.value_of("output").unwrap(),
+    );
+    if is_text_grid(input) == is_text_grid(output) {
+        return Err(format!(
+            "{} and {} have the same format, there is nothing to convert",
+            input, output
+        ));
+    }
+    let rgb_img = read_image(input)?;
+    let converted = if is_text_grid(output) {
+        let codel_size = match opt.value_of("codel_size") {
+            Some(v) => v.parse().unwrap(),
+            None => infer_codel_size(&rgb_img),
+        };
+        write_text_grid(&rgb_img, codel_size)?.into_bytes()
+    } else {
+        let codel_size = opt.value_of("codel_size").map_or(1, |v| v.parse().unwrap());
+        encode_png(&rgb_img, codel_size)
     };
-    let path = opt.value_of("file").unwrap();
-    let mut file = match File::open(path) {
+    std::fs::write(output, converted).map_err(|why| format!("couldn't write {}: {}", output, why))
+}
+
+// writes to --output with codels of --codel-size pixels
+fn write_png(opt: &ArgMatches, img: &[Vec<RGB>]) -> Result<(), String> {
+    let codel_size = opt.value_of("codel_size").map_or(1, |v| v.parse().unwrap());
+    std::fs::write(opt.value_of("output").unwrap(), encode_png(img, codel_size))
+        .map_err(|why| format!("couldn't write image: {}", why))
+}
+
+// merges the counts into --coverage-data and renders them to --coverage
+fn write_coverage(
+    opt: &ArgMatches,
+    machine: &Machine,
+    coverage: &mut Coverage,
+) -> Result<(), String> {
+    if let Some(path) = opt.value_of("coverage_data") {
+        if std::path::Path::new(path).exists() {
+            let earlier = std::fs::read_to_string(path)
+                .map_err(|why| why.to_string())
+                .and_then(|text| text.parse::<Json>())
+                .and_then(|json| Coverage::from_json(&json, machine))
+                .map_err(|why| format!("{}: {}", path, why))?;
+            coverage
+                .merge(&earlier)
+                .map_err(|why| format!("{}: {}", path, why))?;
+        }
+        std::fs::write(path, coverage.to_json(machine).to_string() + "\n")
+            .map_err(|why| why.to_string())?;
+    }
+    if let Some(path) = opt.value_of("coverage") {
+        std::fs::write(path, encode_png(&coverage.render(machine), 1))
+            .map_err(|why| why.to_string())?;
+    }
+    Ok(())
+}
+
+fn load_machine(opt: &ArgMatches, io: Io) -> Machine {
+    match open_machine(opt, opt.value_of("file").unwrap(), io) {
+        Ok(machine) => machine,
         Err(why) => {
-            eprintln!("pint: couldn't open file: {}", why);
+            eprintln!("pint: {}", why);
             std::process::exit(1);
         }
-        Ok(val) => val,
-    };
+    }
+}
 
+// a codel grid is read as an image with a codel size of 1
+fn read_image(path: &str) -> Result<Vec<Vec<RGB>>, String> {
+    if is_text_grid(path) {
+        let text =
+            std::fs::read_to_string(path).map_err(|why| format!("couldn't open file: {}", why))?;
+        return parse_text_grid(&text).map_err(|why| format!("{}: {}", path, why));
+    }
+    let mut file = File::open(path).map_err(|why| format!("couldn't open file: {}", why))?;
     check_valid_png(&mut file);
-    let rgb_img = decode_png(file);
-    if codel_size == -1 {
+    Ok(decode_png(file))
+}
+
+// decodes the image and sets up the interpreter according to the options
+fn open_machine(opt: &ArgMatches, path: &str, mut io: Io) -> Result<Machine, String> {
+    let mut codel_size = match opt.value_of("codel_size") {
+        Some(v) => v.parse::<i32>().unwrap(),
+        None => -1,
+    };
+    let rgb_img = read_image(path)?;
+    if is_text_grid(path) {
+        codel_size = 1;
+    } else if codel_size == -1 {
         codel_size = infer_codel_size(&rgb_img);
     }
 
-    let mut dp = Direction::RIGHT;
-    let mut cc = CodelChooser::LEFT;
-    let mut pos = Coordinates { x: 0, y: 0 };
+    io.encoding = match opt.value_of("char_encoding") {
+        Some("bytes") => CharEncoding::Bytes,
+        _ => CharEncoding::Utf8,
+    };
+    io.invalid_char = match opt.value_of("invalid_char") {
+        Some("skip") => InvalidChar::Skip,
+        Some("error") => InvalidChar::Error,
+        _ => InvalidChar::Replace,
+    };
+
+    let mut interp = Interpreter::new(io);
+    interp.backend = opt.value_of("int").unwrap().parse()?;
+    interp.overflow = match opt.value_of("overflow") {
+        Some(overflow) => overflow.parse()?,
+        None => Overflow::default_for(interp.backend),
+    };
+    if interp.backend == IntBackend::Big && opt.is_present("overflow") {
+        return Err(String::from(
+            "--overflow has no effect with --int big, big integers don't overflow",
+        ));
+    }
+    interp.division = opt.value_of("division").unwrap().parse()?;
+    interp.strict = match opt.value_of("strict") {
+        Some("warn") => Strict::Warn,
+        Some(_) => Strict::Error,
+        None => Strict::Off,
+    };
+
+    let mut machine = Machine::new(rgb_img, codel_size, interp);
+    if opt.is_present("compile") {
+        machine.compile();
+    }
+    Ok(machine)
+}
 
-    let mut stack = Vec::new();
-    let mut current_color = ColorInfo {
-        color: rgb_img[pos.y as usize][pos.x as usize],
-        size: get_size(&get_block(&rgb_img, pos, codel_size, dp)),
+fn run(opt: &ArgMatches) {
+    let limits = Limits {
+        max_steps: opt.value_of("max_steps").map(|n| n.parse().unwrap()),
+        timeout: opt
+            .value_of("timeout")
+            .map(|secs| Duration::from_secs_f64(secs.parse().unwrap())),
     };
-    loop {
-        let prev_color = current_color;
-        current_color = match next_color(&rgb_img, &mut pos, codel_size, &mut dp, &mut cc) {
-            Some(new_color) => new_color,
-            None => break,
-        };
-        execute(&mut stack, &mut dp, &mut cc, prev_color, &current_color);
+    let trace = match opt.value_of("trace") {
+        Some("json") => Some(TraceFormat::Json),
+        Some(_) => Some(TraceFormat::Text),
+        None => None,
+    };
+    let profile_format = match opt.value_of("profile") {
+        Some("json") => Some(ProfileFormat::Json),
+        Some(_) => Some(ProfileFormat::Text),
+        None => None,
+    };
+    let mut trace_out = BufWriter::new(io::stderr());
+
+    let mut machine = load_machine(opt, Io::stdio());
+    if let Some(path) = opt.value_of("resume") {
+        let resumed = std::fs::read_to_string(path)
+            .map_err(|why| format!("couldn't read state: {}", why))
+            .and_then(|text| text.parse::<Json>())
+            .and_then(|json| Snapshot::from_json(&json))
+            .and_then(|snapshot| snapshot.restore(&mut machine));
+        if let Err(why) = resumed {
+            eprintln!("pint: can't resume: {}", why);
+            std::process::exit(1);
+        }
+    }
+    let warn = machine.interp.strict == Strict::Warn;
+    let mut profile = profile_format.map(|_| Profile::new(&machine));
+    let mut coverage = (opt.is_present("coverage") || opt.is_present("coverage_data"))
+        .then(|| Coverage::new(&machine));
+    let halt = machine.run(&limits, |machine, step| {
+        if let Some(profile) = &mut profile {
+            profile.record(machine, step);
+        }
+        if let Some(coverage) = &mut coverage {
+            coverage.record(machine, step);
+        }
+        if let Some(format) = trace {
+            let _ = writeln!(trace_out, "{}", trace_line(machine, step, format));
+        }
+        if let (Some(why), true) = (&step.ignored, warn) {
+            let codel = machine.to_codel(step.from_pos);
+            let _ = writeln!(
+                trace_out,
+                "pint: warning: step {} at ({}, {}): {}",
+                machine.steps, codel.x, codel.y, why
+            );
+        }
+    });
+    if let (Some(profile), Some(format)) = (&profile, profile_format) {
+        let _ = write!(trace_out, "{}", profile.report(&machine.blocks, format));
+    }
+    let _ = trace_out.flush();
+    let _ = machine.interp.io.flush();
+
+    if let Some(coverage) = &mut coverage {
+        if let Err(why) = write_coverage(opt, &machine, coverage) {
+            eprintln!("pint: couldn't write coverage: {}", why);
+            std::process::exit(1);
+        }
+    }
+    if let Some(path) = opt.value_of("save_state") {
+        let state = Snapshot::take(&machine).to_json().to_string();
+        if let Err(why) = std::fs::write(path, state + "\n") {
+            eprintln!("pint: couldn't save state: {}", why);
+            std::process::exit(1);
+        }
+    }
+
+    match halt {
+        Ok(Halt::Terminated) => (),
+        Ok(Halt::StepLimit) => {
+            eprintln!("\npint: step limit reached\n{}", machine.summary());
+            std::process::exit(EXIT_LIMIT);
+        }
+        Ok(Halt::Timeout) => {
+            eprintln!("\npint: timeout reached\n{}", machine.summary());
+            std::process::exit(EXIT_LIMIT);
+        }
+        Err(why) => {
+            eprintln!("pint: {}", why);
+            std::process::exit(1);
+        }
     }
 }
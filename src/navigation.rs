@@ -44,27 +44,29 @@ fn block_dp_corners(dp: &Direction, block: &[Coordinates]) -> (Coordinates, Coor
     }
 }
 
+/// The codel of the block the program leaves it from, given DP and CC.
+pub fn exit_codel(dp: &Direction, cc: &CodelChooser, block: &[Coordinates]) -> Coordinates {
+    let block_corners = block_dp_corners(dp, block);
+    match cc {
+        CodelChooser::LEFT => block_corners.0,
+        CodelChooser::RIGHT => block_corners.1,
+    }
+}
+
 fn next_pos(
     dp: &Direction,
     cc: &CodelChooser,
     block: &[Coordinates],
     codel_size: i32,
-    rgb_img: &Vec<Vec<RGB>>,
+    rgb_img: &[Vec<RGB>],
 ) -> Option<Coordinates> {
-    let block_corners = block_dp_corners(dp, block);
-
-    let new_pos = match cc {
-        CodelChooser::LEFT => Coordinates {
-            x: block_corners.0.x + dp.cords().x * codel_size,
-            y: block_corners.0.y + dp.cords().y * codel_size,
-        },
-        CodelChooser::RIGHT => Coordinates {
-            x: block_corners.1.x + dp.cords().x * codel_size,
-            y: block_corners.1.y + dp.cords().y * codel_size,
-        },
+    let exit = exit_codel(dp, cc, block);
+    let new_pos = Coordinates {
+        x: exit.x + dp.cords().x * codel_size,
+        y: exit.y + dp.cords().y * codel_size,
     };
 
-    if !in_range(&new_pos, rgb_img) || is_color(&new_pos, rgb_img, RGB(0, 0, 0)) {
+    if !in_range(&new_pos, rgb_img) || is_color(&new_pos, rgb_img, BLACK) {
         None
     } else {
         Some(new_pos)
@@ -75,7 +77,7 @@ fn remove_all<T: Eq>(arr: &mut Vec<T>, element: &T) {
     arr.retain(|e| e != element);
 }
 
-fn in_range(new_pos: &Coordinates, rgb_img: &Vec<Vec<RGB>>) -> bool {
+fn in_range(new_pos: &Coordinates, rgb_img: &[Vec<RGB>]) -> bool {
     let width = rgb_img[0].len() as i32;
     let height = rgb_img.len() as i32;
     let x_pos = new_pos.x;
@@ -97,7 +99,7 @@ const CORDS: [Coordinates; 4] = [
 fn check_adjacent_codels(
     current_pos: Coordinates,
     codel_size: i32,
-    rgb_img: &Vec<Vec<RGB>>,
+    rgb_img: &[Vec<RGB>],
     counted: &mut [Coordinates],
     not_counted: &mut Vec<Coordinates>,
     color: RGB,
@@ -119,7 +121,7 @@ fn check_adjacent_codels(
 
 fn get_last_codel_in_dir(
     current_pos: &mut Coordinates,
-    rgb_img: &Vec<Vec<RGB>>,
+    rgb_img: &[Vec<RGB>],
     color: RGB,
     dp: Direction,
 ) -> Coordinates {
@@ -137,7 +139,7 @@ fn get_last_codel_in_dir(
 }
 
 pub fn get_block(
-    rgb_img: &Vec<Vec<RGB>>,
+    rgb_img: &[Vec<RGB>],
     pos: Coordinates,
     codel_size: i32,
     dp: Direction,
@@ -146,7 +148,7 @@ pub fn get_block(
     let color = rgb_img[pos.y as usize][pos.x as usize];
     let mut current_pos = pos;
 
-    if get_color_index(rgb_img[pos.y as usize][pos.x as usize]) == None {
+    if get_color_index(rgb_img[pos.y as usize][pos.x as usize]).is_none() {
         counted.push(get_last_codel_in_dir(&mut current_pos, rgb_img, color, dp));
         return counted;
     }
@@ -182,12 +184,12 @@ pub fn get_block(
     counted
 }
 
-pub fn get_size(block: &Vec<Coordinates>) -> i32 {
+pub fn get_size(block: &[Coordinates]) -> i32 {
     block.len() as i32
 }
 
 pub fn next_color(
-    rgb_img: &Vec<Vec<RGB>>,
+    rgb_img: &[Vec<RGB>],
     pos: &mut Coordinates,
     codel_size: i32,
     dp: &mut Direction,
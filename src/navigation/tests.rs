@@ -115,7 +115,7 @@ mod tests {
         let coordinates = Coordinates { x: 150, y: 0 };
         let result = in_range(&coordinates, &rgb_img);
 
-        assert_eq!(result, false);
+        assert!(!result);
     }
     #[test]
     fn navigates_test_img() {
@@ -197,12 +197,8 @@ mod tests {
         let mut dp = Direction::RIGHT;
         let mut cc = CodelChooser::LEFT;
 
-        loop {
-            let color = match next_color(&rgb_img, &mut pos, codel_size, &mut dp, &mut cc) {
-                Some(new_color) => new_color.color,
-                None => break,
-            };
-            result.push(color);
+        while let Some(new_color) = next_color(&rgb_img, &mut pos, codel_size, &mut dp, &mut cc) {
+            result.push(new_color.color);
         }
         // let result = next_color(&rgb_img, &mut pos, codel_size, &mut dp, &mut cc)
         let expected = vec![
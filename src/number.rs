@@ -0,0 +1,438 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+mod tests;
+
+// every limb stores 9 decimal digits, which makes printing and parsing trivial
+const BASE: u64 = 1_000_000_000;
+
+/// Integer of arbitrary size, the magnitude is stored little-endian in base 10^9.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    mag: Vec<u32>,
+}
+
+fn trim(mag: &mut Vec<u32>) {
+    while mag.last() == Some(&0) {
+        mag.pop();
+    }
+}
+
+fn cmp_mag(a: &[u32], b: &[u32]) -> Ordering {
+    if a.len() != b.len() {
+        return a.len().cmp(&b.len());
+    }
+    for (x, y) in a.iter().rev().zip(b.iter().rev()) {
+        if x != y {
+            return x.cmp(y);
+        }
+    }
+    Ordering::Equal
+}
+
+fn add_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0;
+    for i in 0..a.len().max(b.len()) {
+        let sum = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        result.push((sum % BASE) as u32);
+        carry = sum / BASE;
+    }
+    if carry > 0 {
+        result.push(carry as u32);
+    }
+    result
+}
+
+// a has to be greater or equal to b
+fn sub_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0;
+    for (i, &limb) in a.iter().enumerate() {
+        let mut diff = limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if diff < 0 {
+            diff += BASE as i64;
+            borrow = 1;
+        }
+        result.push(diff as u32);
+    }
+    trim(&mut result);
+    result
+}
+
+fn mul_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = vec![0u64; a.len() + b.len()];
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0;
+        for (j, &y) in b.iter().enumerate() {
+            let cur = result[i + j] + x as u64 * y as u64 + carry;
+            result[i + j] = cur % BASE;
+            carry = cur / BASE;
+        }
+        result[i + b.len()] += carry;
+    }
+    let mut result = result.into_iter().map(|l| l as u32).collect();
+    trim(&mut result);
+    result
+}
+
+fn mul_small(a: &[u32], n: u32) -> Vec<u32> {
+    mul_mag(a, &[n])
+}
+
+// schoolbook long division, every quotient digit is found by binary search
+fn divrem_mag(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    let mut quotient = vec![0; a.len()];
+    let mut rem: Vec<u32> = Vec::new();
+    for i in (0..a.len()).rev() {
+        rem.insert(0, a[i]);
+        trim(&mut rem);
+
+        let (mut lo, mut hi) = (0u32, (BASE - 1) as u32);
+        while lo < hi {
+            let mid = lo + (hi - lo).div_ceil(2);
+            if cmp_mag(&mul_small(b, mid), &rem) == Ordering::Greater {
+                hi = mid - 1;
+            } else {
+                lo = mid;
+            }
+        }
+        quotient[i] = lo;
+        rem = sub_mag(&rem, &mul_small(b, lo));
+    }
+    trim(&mut quotient);
+    (quotient, rem)
+}
+
+impl BigInt {
+    fn new(negative: bool, mut mag: Vec<u32>) -> BigInt {
+        trim(&mut mag);
+        BigInt {
+            negative: negative && !mag.is_empty(),
+            mag,
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.mag.is_empty()
+    }
+
+    fn neg(&self) -> BigInt {
+        BigInt::new(!self.negative, self.mag.clone())
+    }
+
+    fn add(&self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(self.negative, add_mag(&self.mag, &other.mag));
+        }
+        match cmp_mag(&self.mag, &other.mag) {
+            Ordering::Less => BigInt::new(other.negative, sub_mag(&other.mag, &self.mag)),
+            _ => BigInt::new(self.negative, sub_mag(&self.mag, &other.mag)),
+        }
+    }
+
+    fn sub(&self, other: &BigInt) -> BigInt {
+        self.add(&other.neg())
+    }
+
+    fn mul(&self, other: &BigInt) -> BigInt {
+        BigInt::new(
+            self.negative != other.negative,
+            mul_mag(&self.mag, &other.mag),
+        )
+    }
+
+    // truncating division, the remainder has the sign of the dividend
+    fn divrem(&self, other: &BigInt) -> (BigInt, BigInt) {
+        let (q, r) = divrem_mag(&self.mag, &other.mag);
+        (
+            BigInt::new(self.negative != other.negative, q),
+            BigInt::new(self.negative, r),
+        )
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        let mut n: i128 = 0;
+        for &limb in self.mag.iter().rev() {
+            n = n.checked_mul(BASE as i128)?.checked_add(limb as i128)?;
+        }
+        if self.negative {
+            n = -n;
+        }
+        i64::try_from(n).ok()
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(n: i64) -> BigInt {
+        let mut abs = n.unsigned_abs();
+        let mut mag = Vec::new();
+        while abs > 0 {
+            mag.push((abs % BASE) as u32);
+            abs /= BASE;
+        }
+        BigInt::new(n < 0, mag)
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_mag(&self.mag, &other.mag),
+            (true, true) => cmp_mag(&other.mag, &self.mag),
+        }
+    }
+}
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut digits = match self.mag.last() {
+            Some(most_significant) => most_significant.to_string(),
+            None => return write!(f, "0"),
+        };
+        for limb in self.mag.iter().rev().skip(1) {
+            digits.push_str(&format!("{:09}", limb));
+        }
+        if self.negative {
+            write!(f, "-{}", digits)
+        } else {
+            write!(f, "{}", digits)
+        }
+    }
+}
+
+/// A value on the stack. Stays a machine integer as long as it fits into an `i64`
+/// and only falls back to a `BigInt` when it doesn't.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Int {
+    Small(i64),
+    Big(BigInt),
+}
+
+impl Int {
+    fn from_big(n: BigInt) -> Int {
+        match n.to_i64() {
+            Some(small) => Int::Small(small),
+            None => Int::Big(n),
+        }
+    }
+
+    fn to_big(&self) -> BigInt {
+        match self {
+            Int::Small(n) => BigInt::from(*n),
+            Int::Big(n) => n.clone(),
+        }
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        match self {
+            Int::Small(n) => Some(*n),
+            Int::Big(_) => None,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Int::Small(n) => *n == 0,
+            Int::Big(n) => n.is_zero(),
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        match self {
+            Int::Small(n) => *n < 0,
+            Int::Big(n) => n.negative,
+        }
+    }
+
+    pub fn add(&self, other: &Int) -> Int {
+        if let (Int::Small(a), Int::Small(b)) = (self, other) {
+            if let Some(n) = a.checked_add(*b) {
+                return Int::Small(n);
+            }
+        }
+        Int::from_big(self.to_big().add(&other.to_big()))
+    }
+
+    pub fn sub(&self, other: &Int) -> Int {
+        if let (Int::Small(a), Int::Small(b)) = (self, other) {
+            if let Some(n) = a.checked_sub(*b) {
+                return Int::Small(n);
+            }
+        }
+        Int::from_big(self.to_big().sub(&other.to_big()))
+    }
+
+    pub fn mul(&self, other: &Int) -> Int {
+        if let (Int::Small(a), Int::Small(b)) = (self, other) {
+            if let Some(n) = a.checked_mul(*b) {
+                return Int::Small(n);
+            }
+        }
+        Int::from_big(self.to_big().mul(&other.to_big()))
+    }
+
+    /// Truncating division and its remainder, `other` must not be zero.
+    pub fn div_rem(&self, other: &Int) -> (Int, Int) {
+        if let (Int::Small(a), Int::Small(b)) = (self, other) {
+            if let (Some(q), Some(r)) = (a.checked_div(*b), a.checked_rem(*b)) {
+                return (Int::Small(q), Int::Small(r));
+            }
+        }
+        let (q, r) = self.to_big().divrem(&other.to_big());
+        (Int::from_big(q), Int::from_big(r))
+    }
+
+    /// Division rounding towards negative infinity and its remainder,
+    /// which has the sign of `other`. `other` must not be zero.
+    pub fn div_rem_floor(&self, other: &Int) -> (Int, Int) {
+        let (q, r) = self.div_rem(other);
+        if !r.is_zero() && r.is_negative() != other.is_negative() {
+            (q.sub(&Int::Small(1)), r.add(other))
+        } else {
+            (q, r)
+        }
+    }
+
+    /// Remainder that is never negative, `other` must not be zero.
+    pub fn rem_euclid(&self, other: &Int) -> Int {
+        let (_, r) = self.div_rem(other);
+        if !r.is_negative() {
+            r
+        } else if other.is_negative() {
+            r.sub(other)
+        } else {
+            r.add(other)
+        }
+    }
+}
+
+impl From<i32> for Int {
+    fn from(n: i32) -> Int {
+        Int::Small(n as i64)
+    }
+}
+impl From<i64> for Int {
+    fn from(n: i64) -> Int {
+        Int::Small(n)
+    }
+}
+
+impl Ord for Int {
+    fn cmp(&self, other: &Int) -> Ordering {
+        match (self, other) {
+            (Int::Small(a), Int::Small(b)) => a.cmp(b),
+            _ => self.to_big().cmp(&other.to_big()),
+        }
+    }
+}
+impl PartialOrd for Int {
+    fn partial_cmp(&self, other: &Int) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Int {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Int::Small(n) => write!(f, "{}", n),
+            Int::Big(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+impl FromStr for Int {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Int, String> {
+        if let Ok(n) = s.parse::<i64>() {
+            return Ok(Int::Small(n));
+        }
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(format!("{} is not a number", s));
+        }
+        let mut mag = Vec::new();
+        let mut end = digits.len();
+        while end > 0 {
+            let start = end.saturating_sub(9);
+            mag.push(digits[start..end].parse::<u32>().unwrap());
+            end = start;
+        }
+        Ok(Int::from_big(BigInt::new(negative, mag)))
+    }
+}
+
+/// The range of values the stack can hold.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IntBackend {
+    /// 32-bit integers.
+    I32,
+    /// 64-bit integers.
+    I64,
+    /// Integers of unlimited size.
+    Big,
+}
+
+impl FromStr for IntBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<IntBackend, String> {
+        match s {
+            "i32" => Ok(IntBackend::I32),
+            "i64" => Ok(IntBackend::I64),
+            "big" => Ok(IntBackend::Big),
+            _ => Err(format!("{} is not a kind of integers", s)),
+        }
+    }
+}
+
+impl IntBackend {
+    /// The name of the backend as `--int` takes it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            IntBackend::I32 => "i32",
+            IntBackend::I64 => "i64",
+            IntBackend::Big => "big",
+        }
+    }
+
+    /// Smallest and biggest value of the backend, `None` if it is unbounded.
+    pub fn bounds(&self) -> Option<(Int, Int)> {
+        match self {
+            IntBackend::I32 => Some((Int::from(i32::MIN), Int::from(i32::MAX))),
+            IntBackend::I64 => Some((Int::from(i64::MIN), Int::from(i64::MAX))),
+            IntBackend::Big => None,
+        }
+    }
+
+    /// Wraps a value into the range of the backend like two's complement arithmetic would.
+    pub fn wrap(&self, n: &Int) -> Int {
+        let bits = match self {
+            IntBackend::I32 => 32,
+            IntBackend::I64 => 64,
+            IntBackend::Big => return n.clone(),
+        };
+        let half = Int::Small(1 << (bits / 2));
+        let modulus = half.mul(&half);
+        let low = n.rem_euclid(&modulus);
+        match self.bounds() {
+            Some((_, max)) if low > max => low.sub(&modulus),
+            _ => low,
+        }
+    }
+}
@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod tests {
+    use crate::number::*;
+
+    fn int(s: &str) -> Int {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_and_prints_big_numbers() {
+        for s in ["0", "-7", "1000000000", "-123456789012345678901234567890"] {
+            assert_eq!(int(s).to_string(), s);
+        }
+        assert_eq!(int("+42"), Int::Small(42));
+        assert!("12a".parse::<Int>().is_err());
+        assert!("-".parse::<Int>().is_err());
+    }
+    #[test]
+    fn promotes_on_overflow() {
+        let max = Int::from(i64::MAX);
+
+        assert_eq!(max.add(&Int::from(1)).to_string(), "9223372036854775808");
+        assert_eq!(
+            max.mul(&max).to_string(),
+            "85070591730234615847396907784232501249"
+        );
+        assert_eq!(
+            Int::from(i64::MIN).sub(&Int::from(1)).to_string(),
+            "-9223372036854775809"
+        );
+    }
+    #[test]
+    fn demotes_small_results() {
+        let big = int("100000000000000000000");
+
+        assert_eq!(big.sub(&big), Int::Small(0));
+        assert_eq!(big.div_rem(&int("10000000000000000000")).0, Int::Small(10));
+    }
+    #[test]
+    fn divides_big_numbers() {
+        let (q, r) = int("-123456789012345678901234567890").div_rem(&int("987654321987654321"));
+
+        assert_eq!(q.to_string(), "-124999998748");
+        assert_eq!(r.to_string(), "-432099904777777782");
+        assert_eq!(
+            int("-123456789012345678901234567890").rem_euclid(&Int::from(4)),
+            Int::Small(2)
+        );
+        assert_eq!(
+            Int::from(i64::MIN).div_rem(&Int::from(-1)).0.to_string(),
+            "9223372036854775808"
+        );
+    }
+    #[test]
+    fn compares_mixed_sizes() {
+        assert!(int("-100000000000000000000") < Int::from(-5));
+        assert!(int("100000000000000000000") > Int::from(i64::MAX));
+        assert!(int("100000000000000000001") > int("100000000000000000000"));
+    }
+    #[test]
+    fn floor_division_rounds_down() {
+        assert_eq!(
+            Int::from(-7).div_rem_floor(&Int::from(2)),
+            (Int::from(-4), Int::from(1))
+        );
+        assert_eq!(
+            Int::from(7).div_rem_floor(&Int::from(-2)),
+            (Int::from(-4), Int::from(-1))
+        );
+        assert_eq!(
+            Int::from(-6).div_rem_floor(&Int::from(2)),
+            (Int::from(-3), Int::from(0))
+        );
+        assert_eq!(
+            int("-100000000000000000001").div_rem_floor(&int("100000000000000000000")),
+            (Int::from(-2), int("99999999999999999999"))
+        );
+    }
+    #[test]
+    fn backends_wrap_values() {
+        let big = int("4294967297"); // 2^32 + 1
+
+        assert_eq!(IntBackend::I32.wrap(&big), Int::Small(1));
+        assert_eq!(
+            IntBackend::I32.wrap(&Int::from(2147483648i64)),
+            Int::Small(-2147483648)
+        );
+        assert_eq!(
+            IntBackend::I32.wrap(&int("-36893488147419103231")),
+            Int::Small(1)
+        );
+        assert_eq!(
+            IntBackend::I64.wrap(&int("9223372036854775808")),
+            Int::from(i64::MIN)
+        );
+        assert_eq!(IntBackend::I64.wrap(&big), big);
+        assert_eq!(
+            IntBackend::Big.wrap(&int("9223372036854775808")),
+            int("9223372036854775808")
+        );
+    }
+}
@@ -0,0 +1,255 @@
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::rc::Rc;
+
+use crate::interpreter::RuntimeError;
+use crate::number::Int;
+
+mod tests;
+
+/// How `in_char` and `out_char` map stack values to the byte streams.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CharEncoding {
+    /// Whole unicode scalar values, encoded as UTF-8.
+    Utf8,
+    /// Single raw bytes (0..=255).
+    Bytes,
+}
+
+/// What `out_char` does with values that are not a valid character.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InvalidChar {
+    /// Pop the value and print nothing.
+    Skip,
+    /// Print U+FFFD instead.
+    Replace,
+    /// Stop the program with an error.
+    Error,
+}
+
+/// A `Write` that keeps everything in memory and can be cloned,
+/// so the output of a program can be inspected after running it.
+#[derive(Clone, Default)]
+pub struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl SharedBuffer {
+    pub fn contents(&self) -> Vec<u8> {
+        self.0.borrow().clone()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub struct Io {
+    reader: BufReader<Box<dyn Read>>,
+    writer: Box<dyn Write>,
+    pub encoding: CharEncoding,
+    pub invalid_char: InvalidChar,
+    /// Input given back by `unread`, read again before the rest of the input (last byte first).
+    unread: Vec<u8>,
+    /// Every byte the program read, only kept after `record_input` was called.
+    consumed: Option<Vec<u8>>,
+}
+
+const REPLACEMENT_CHAR: char = '\u{FFFD}';
+
+fn utf8_len(lead: u8) -> usize {
+    match lead {
+        0x00..=0x7F => 1,
+        0xC2..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF4 => 4,
+        _ => 0,
+    }
+}
+
+impl Io {
+    pub fn new(reader: Box<dyn Read>, writer: Box<dyn Write>) -> Io {
+        Io {
+            reader: BufReader::new(reader),
+            writer,
+            encoding: CharEncoding::Utf8,
+            invalid_char: InvalidChar::Replace,
+            unread: Vec::new(),
+            consumed: None,
+        }
+    }
+
+    pub fn stdio() -> Io {
+        Io::new(Box::new(io::stdin()), Box::new(io::stdout()))
+    }
+
+    fn prompt(&mut self) -> io::Result<()> {
+        write!(self.writer, "> ")?;
+        // forces to print everything everything before input
+        self.writer.flush()
+    }
+
+    /// Keeps everything the program reads from now on, so it can be given back with `unread`.
+    pub fn record_input(&mut self) {
+        self.consumed.get_or_insert_with(Vec::new);
+    }
+
+    /// Number of bytes the program read since `record_input`.
+    pub fn consumed_len(&self) -> usize {
+        self.consumed.as_ref().map_or(0, Vec::len)
+    }
+
+    /// Gives back everything read after the first `len` recorded bytes,
+    /// the program reads it again next.
+    pub fn unread(&mut self, len: usize) {
+        if let Some(consumed) = &mut self.consumed {
+            if len < consumed.len() {
+                self.unread.extend(consumed.drain(len..).rev());
+            }
+        }
+    }
+
+    /// Input that was read ahead or given back but not consumed by the program yet.
+    pub fn pending_input(&self) -> Vec<u8> {
+        let mut pending: Vec<u8> = self.unread.iter().rev().copied().collect();
+        pending.extend_from_slice(self.reader.buffer());
+        pending
+    }
+
+    /// Makes the program read `input` before the rest of its input.
+    pub fn prepend_input(&mut self, input: &[u8]) {
+        self.unread.extend(input.iter().rev());
+    }
+
+    fn peek_byte(&mut self) -> io::Result<Option<u8>> {
+        if let Some(&b) = self.unread.last() {
+            return Ok(Some(b));
+        }
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    fn next_byte(&mut self) -> io::Result<Option<u8>> {
+        let byte = match self.unread.pop() {
+            Some(b) => Some(b),
+            None => {
+                let byte = self.reader.fill_buf()?.first().copied();
+                if byte.is_some() {
+                    self.reader.consume(1);
+                }
+                byte
+            }
+        };
+        if let (Some(b), Some(consumed)) = (byte, &mut self.consumed) {
+            consumed.push(b);
+        }
+        Ok(byte)
+    }
+
+    /// Reads a single character, `None` on end of input.
+    /// Malformed UTF-8 is read as U+FFFD without consuming the byte that broke the sequence.
+    pub fn read_char(&mut self) -> Result<Option<i32>, RuntimeError> {
+        self.prompt()?;
+
+        let lead = match self.next_byte()? {
+            Some(b) => b,
+            None => return Ok(None),
+        };
+        if self.encoding == CharEncoding::Bytes {
+            return Ok(Some(lead as i32));
+        }
+
+        let len = utf8_len(lead);
+        if len == 0 {
+            return Ok(Some(REPLACEMENT_CHAR as i32));
+        }
+        let mut bytes = vec![lead];
+        while bytes.len() < len {
+            match self.peek_byte()? {
+                Some(b) if b & 0xC0 == 0x80 => {
+                    self.next_byte()?;
+                    bytes.push(b);
+                }
+                _ => return Ok(Some(REPLACEMENT_CHAR as i32)),
+            }
+        }
+        let c = std::str::from_utf8(&bytes)
+            .ok()
+            .and_then(|s| s.chars().next())
+            .unwrap_or(REPLACEMENT_CHAR);
+
+        Ok(Some(c as i32))
+    }
+
+    /// Reads a line and parses it as number, `None` on end of input or if it isn't one.
+    pub fn read_num(&mut self) -> Result<Option<Int>, RuntimeError> {
+        self.prompt()?;
+
+        let mut line = Vec::new();
+        while let Some(b) = self.next_byte()? {
+            line.push(b);
+            if b == b'\n' {
+                break;
+            }
+        }
+        if line.is_empty() {
+            return Ok(None);
+        }
+        match String::from_utf8_lossy(&line).trim().parse::<Int>() {
+            Ok(n) => Ok(Some(n)),
+            Err(_) => {
+                eprintln!("input not a number");
+                Ok(None)
+            }
+        }
+    }
+
+    /// Reads a line without prompting, `None` on end of input.
+    /// Lets the debugger share the input with the program it is debugging,
+    /// input given back to the program is skipped.
+    pub fn read_line(&mut self) -> Result<Option<String>, RuntimeError> {
+        let mut buffer = String::new();
+        if self.reader.read_line(&mut buffer)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(buffer))
+    }
+
+    pub fn write_char(&mut self, value: &Int) -> Result<(), RuntimeError> {
+        let n = value.to_i64().unwrap_or(-1);
+        match self.encoding {
+            CharEncoding::Bytes if (0..=255).contains(&n) => {
+                self.writer.write_all(&[n as u8])?;
+                return Ok(());
+            }
+            CharEncoding::Utf8 => {
+                if let Some(c) = u32::try_from(n).ok().and_then(std::char::from_u32) {
+                    write!(self.writer, "{}", c)?;
+                    return Ok(());
+                }
+            }
+            _ => (),
+        }
+
+        match self.invalid_char {
+            InvalidChar::Skip => (),
+            InvalidChar::Replace => write!(self.writer, "{}", REPLACEMENT_CHAR)?,
+            InvalidChar::Error => return Err(RuntimeError::InvalidChar(value.clone())),
+        }
+        Ok(())
+    }
+
+    pub fn write_num(&mut self, value: &Int) -> Result<(), RuntimeError> {
+        write!(self.writer, "{}", value)?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), RuntimeError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
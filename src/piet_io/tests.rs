@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::RuntimeError;
+    use crate::number::Int;
+    use crate::piet_io::*;
+    use std::io::Cursor;
+
+    fn io_with_input(input: &[u8]) -> (Io, SharedBuffer) {
+        let out = SharedBuffer::default();
+        let io = Io::new(Box::new(Cursor::new(input.to_vec())), Box::new(out.clone()));
+        (io, out)
+    }
+
+    #[test]
+    fn reads_multibyte_utf8_char() {
+        let (mut io, _) = io_with_input("ä€😀".as_bytes());
+
+        assert_eq!(io.read_char(), Ok(Some('ä' as i32)));
+        assert_eq!(io.read_char(), Ok(Some('€' as i32)));
+        assert_eq!(io.read_char(), Ok(Some('😀' as i32)));
+        assert_eq!(io.read_char(), Ok(None));
+    }
+    #[test]
+    fn reads_raw_bytes() {
+        let (mut io, _) = io_with_input("ä".as_bytes());
+        io.encoding = CharEncoding::Bytes;
+
+        assert_eq!(io.read_char(), Ok(Some(0xC3)));
+        assert_eq!(io.read_char(), Ok(Some(0xA4)));
+        assert_eq!(io.read_char(), Ok(None));
+    }
+    #[test]
+    fn malformed_utf8_keeps_next_char() {
+        let (mut io, _) = io_with_input(&[0xC3, b'a', 0xFF]);
+
+        assert_eq!(io.read_char(), Ok(Some(0xFFFD)));
+        assert_eq!(io.read_char(), Ok(Some('a' as i32)));
+        assert_eq!(io.read_char(), Ok(Some(0xFFFD)));
+    }
+    #[test]
+    fn reads_num_until_eof() {
+        let (mut io, _) = io_with_input(b"42\nfoo\n");
+
+        assert_eq!(io.read_num(), Ok(Some(Int::from(42))));
+        assert_eq!(io.read_num(), Ok(None));
+        assert_eq!(io.read_num(), Ok(None));
+    }
+    #[test]
+    fn invalid_chars_follow_policy() {
+        let (mut io, out) = io_with_input(b"");
+
+        io.write_char(&Int::from('ß' as i32)).unwrap();
+        io.write_char(&Int::from(-1)).unwrap();
+        io.invalid_char = InvalidChar::Skip;
+        io.write_char(&Int::from(0xD800)).unwrap();
+        io.invalid_char = InvalidChar::Error;
+        assert_eq!(
+            io.write_char(&Int::from(0x110000)),
+            Err(RuntimeError::InvalidChar(Int::from(0x110000)))
+        );
+
+        assert_eq!(String::from_utf8(out.contents()).unwrap(), "ß\u{FFFD}");
+    }
+    #[test]
+    fn writes_raw_bytes() {
+        let (mut io, out) = io_with_input(b"");
+        io.encoding = CharEncoding::Bytes;
+        io.invalid_char = InvalidChar::Skip;
+
+        io.write_char(&Int::from(0xC3)).unwrap();
+        io.write_char(&Int::from(0xA4)).unwrap();
+        io.write_char(&Int::from(256)).unwrap();
+
+        assert_eq!(out.contents(), "ä".as_bytes());
+    }
+    #[test]
+    fn gives_back_recorded_input() {
+        let (mut io, _) = io_with_input("a€12\n".as_bytes());
+        io.record_input();
+
+        assert_eq!(io.read_char(), Ok(Some('a' as i32)));
+        let len = io.consumed_len();
+        assert_eq!(io.read_char(), Ok(Some('€' as i32)));
+        assert_eq!(io.read_num(), Ok(Some(Int::from(12))));
+        assert_eq!(io.consumed_len(), 7);
+
+        io.unread(len);
+        assert_eq!(io.consumed_len(), 1);
+        assert_eq!(io.read_char(), Ok(Some('€' as i32)));
+        assert_eq!(io.read_num(), Ok(Some(Int::from(12))));
+        assert_eq!(io.read_char(), Ok(None));
+    }
+}
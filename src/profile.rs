@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::blocks::BlockMap;
+use crate::interpreter::*;
+use crate::json::Json;
+use crate::machine::*;
+use crate::types::*;
+
+mod tests;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ProfileFormat {
+    /// A human readable report.
+    Text,
+    /// One JSON object.
+    Json,
+}
+
+/// How often a block was left with a DP and CC.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Transition {
+    /// The codel the next block is entered at, which is always the same for a block, DP and CC.
+    to: Coordinates,
+    command: Command,
+    count: u64,
+}
+
+/// Counts what a program does while it runs: the commands, how often every color block is left
+/// and with which DP and CC, and how deep the stack gets.
+pub struct Profile {
+    pub steps: u64,
+    pub max_depth: usize,
+    commands: HashMap<Command, u64>,
+    /// Indexed by the block id in the `BlockMap`, steps from outside of blocks aren't counted.
+    blocks: HashMap<usize, u64>,
+    transitions: HashMap<(usize, Direction, CodelChooser), Transition>,
+}
+
+impl Profile {
+    /// Starts with what is on the stack of `machine`, which isn't empty after resuming.
+    pub fn new(machine: &Machine) -> Profile {
+        Profile {
+            steps: 0,
+            max_depth: machine.interp.stack.len(),
+            commands: HashMap::new(),
+            blocks: HashMap::new(),
+            transitions: HashMap::new(),
+        }
+    }
+
+    /// Counts a step after it was executed, like `trace_line`.
+    pub fn record(&mut self, machine: &Machine, step: &Step) {
+        self.steps += 1;
+        self.max_depth = self.max_depth.max(machine.interp.stack.len());
+        *self.commands.entry(step.command).or_insert(0) += 1;
+
+        if let Some(id) = machine.blocks.block_id(machine.to_codel(step.from_pos)) {
+            *self.blocks.entry(id).or_insert(0) += 1;
+            self.transitions
+                .entry((id, step.dp, step.cc))
+                .or_insert(Transition {
+                    to: machine.codel(),
+                    command: step.command,
+                    count: 0,
+                })
+                .count += 1;
+        }
+    }
+
+    /// How often every command was executed, the most frequent first.
+    pub fn commands(&self) -> Vec<(Command, u64)> {
+        let mut commands: Vec<(Command, u64)> =
+            self.commands.iter().map(|(c, n)| (*c, *n)).collect();
+        commands.sort_by_key(|(command, count)| (u64::MAX - count, command.name()));
+        commands
+    }
+
+    // (count, top left codel, block id), the most frequent first
+    fn blocks(&self, blocks: &BlockMap) -> Vec<(u64, Coordinates, usize)> {
+        let mut counts: Vec<(u64, Coordinates, usize)> = self
+            .blocks
+            .iter()
+            .map(|(id, count)| (*count, blocks.blocks[*id].top_left(), *id))
+            .collect();
+        counts.sort_by_key(|(count, codel, _)| (u64::MAX - count, codel.y, codel.x));
+        counts
+    }
+
+    // (top left codel, block id, DP, CC, transition), the most frequent first
+    fn transitions(
+        &self,
+        blocks: &BlockMap,
+    ) -> Vec<(Coordinates, usize, Direction, CodelChooser, Transition)> {
+        let mut transitions: Vec<_> = self
+            .transitions
+            .iter()
+            .map(|((id, dp, cc), transition)| {
+                (blocks.blocks[*id].top_left(), *id, *dp, *cc, *transition)
+            })
+            .collect();
+        transitions.sort_by_key(|(codel, _, dp, cc, transition)| {
+            (
+                u64::MAX - transition.count,
+                codel.y,
+                codel.x,
+                format!("{:?} {:?}", dp, cc),
+            )
+        });
+        transitions
+    }
+
+    /// The counts of the commands, blocks and transitions, each sorted with the most frequent
+    /// first. Blocks are given by their top left codel, color and size. Ends with a newline.
+    pub fn report(&self, blocks: &BlockMap, format: ProfileFormat) -> String {
+        match format {
+            ProfileFormat::Text => self.text(blocks),
+            ProfileFormat::Json => self.to_json(blocks).to_string() + "\n",
+        }
+    }
+
+    fn text(&self, blocks: &BlockMap) -> String {
+        let block = |codel: Coordinates, id: usize| {
+            let block = &blocks.blocks[id];
+            format!(
+                "({}, {}) {}/{}",
+                codel.x,
+                codel.y,
+                color_name(block.color),
+                block.size
+            )
+        };
+        let mut text = format!(
+            "steps: {}\nmax stack depth: {}\n",
+            self.steps, self.max_depth
+        );
+        text += "\ncommands:\n";
+        for (command, count) in self.commands() {
+            let _ = writeln!(text, "{:>10} {}", count, command.name());
+        }
+        text += "\nblocks:\n";
+        for (count, codel, id) in self.blocks(blocks) {
+            let _ = writeln!(text, "{:>10} {}", count, block(codel, id));
+        }
+        text += "\ntransitions:\n";
+        for (codel, id, dp, cc, transition) in self.transitions(blocks) {
+            let to = blocks.color_info(transition.to);
+            let _ = writeln!(
+                text,
+                "{:>10} {} dp={:?} cc={:?} -> ({}, {}) {}/{} {}",
+                transition.count,
+                block(codel, id),
+                dp,
+                cc,
+                transition.to.x,
+                transition.to.y,
+                color_name(to.color),
+                to.size,
+                transition.command.name()
+            );
+        }
+        text
+    }
+
+    pub fn to_json(&self, blocks: &BlockMap) -> Json {
+        let block = |codel: Coordinates, color: ColorInfo| {
+            vec![
+                ("x", Json::number(codel.x)),
+                ("y", Json::number(codel.y)),
+                ("color", Json::string(color_name(color.color))),
+                ("size", Json::number(color.size)),
+            ]
+        };
+        let commands = self
+            .commands()
+            .into_iter()
+            .map(|(command, count)| {
+                Json::object(vec![
+                    ("command", Json::string(command.name())),
+                    ("count", Json::number(count)),
+                ])
+            })
+            .collect();
+        let counts = self
+            .blocks(blocks)
+            .into_iter()
+            .map(|(count, codel, _)| {
+                let mut fields = block(codel, blocks.color_info(codel));
+                fields.push(("count", Json::number(count)));
+                Json::object(fields)
+            })
+            .collect();
+        let transitions = self
+            .transitions(blocks)
+            .into_iter()
+            .map(|(codel, _, dp, cc, transition)| {
+                let mut fields = block(codel, blocks.color_info(codel));
+                fields.extend([
+                    ("dp", Json::string(format!("{:?}", dp).to_lowercase())),
+                    ("cc", Json::string(format!("{:?}", cc).to_lowercase())),
+                    (
+                        "to",
+                        Json::object(block(transition.to, blocks.color_info(transition.to))),
+                    ),
+                    ("command", Json::string(transition.command.name())),
+                    ("count", Json::number(transition.count)),
+                ]);
+                Json::object(fields)
+            })
+            .collect();
+        Json::object(vec![
+            ("steps", Json::number(self.steps)),
+            ("max_stack_depth", Json::number(self.max_depth)),
+            ("commands", Json::Array(commands)),
+            ("blocks", Json::Array(counts)),
+            ("transitions", Json::Array(transitions)),
+        ])
+    }
+}
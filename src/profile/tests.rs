@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::*;
+    use crate::json::Json;
+    use crate::machine::*;
+    use crate::profile::*;
+    use crate::test_support::hello_world;
+
+    fn profile(machine: &mut Machine) -> Profile {
+        let mut profile = Profile::new(machine);
+        let limits = Limits {
+            max_steps: None,
+            timeout: None,
+        };
+        machine
+            .run(&limits, |machine, step| profile.record(machine, step))
+            .unwrap();
+        profile
+    }
+
+    #[test]
+    fn counts_every_step() {
+        let mut machine = hello_world();
+        let profile = profile(&mut machine);
+
+        assert_eq!(profile.steps, machine.steps);
+        let commands = profile.commands();
+        assert_eq!(commands.iter().map(|(_, n)| n).sum::<u64>(), machine.steps);
+        assert!(commands.windows(2).all(|w| w[0].1 >= w[1].1));
+        let out_chars = commands.iter().find(|(c, _)| *c == Command::OutChar);
+        assert_eq!(out_chars, Some(&(Command::OutChar, 12)));
+    }
+
+    #[test]
+    fn reports_sorted_counts() {
+        let mut machine = hello_world();
+        let profile = profile(&mut machine);
+
+        let report = profile.report(&machine.blocks, ProfileFormat::Text);
+        assert!(report.starts_with(&format!(
+            "steps: {}\nmax stack depth: {}\n\ncommands:\n",
+            machine.steps, profile.max_depth
+        )));
+        assert!(report.contains("\nblocks:\n"));
+        assert!(report.contains("\ntransitions:\n"));
+    }
+
+    #[test]
+    fn reports_json() {
+        let mut machine = hello_world();
+        let profile = profile(&mut machine);
+
+        let json: Json = profile
+            .report(&machine.blocks, ProfileFormat::Json)
+            .parse()
+            .unwrap();
+        assert_eq!(
+            json.get("steps").and_then(Json::as_i64),
+            Some(machine.steps as i64)
+        );
+        let transitions = json.get("transitions").and_then(Json::as_array).unwrap();
+        let counts: Vec<i64> = transitions
+            .iter()
+            .map(|t| t.get("count").and_then(Json::as_i64).unwrap())
+            .collect();
+        assert!(counts.windows(2).all(|w| w[0] >= w[1]));
+    }
+}
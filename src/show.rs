@@ -0,0 +1,121 @@
+use crate::blocks::BlockMap;
+use crate::interpreter::{get_color_index, RuntimeError};
+use crate::machine::*;
+use crate::types::*;
+
+mod tests;
+
+const RESET: &str = "\x1b[0m";
+const LIGHTNESS_LETTERS: [char; 3] = ['l', 'n', 'd'];
+const HUE_LETTERS: [char; 6] = ['R', 'Y', 'G', 'C', 'B', 'M'];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Style {
+    /// Every codel is two spaces with the color of the codel as 24-bit background color.
+    Color,
+    /// Every codel is its token, see `token`.
+    Letters,
+}
+
+/// Two letters for the color of a codel: `l`, `n` or `d` for light, normal and dark and the first
+/// letter of the hue, like `lR` for light red and `dB` for dark blue. White is `WW`, black is `KK`
+/// and colors outside of the 20 are `??`.
+pub fn token(color: RGB) -> String {
+    match get_color_index(color) {
+        Some(index) => format!(
+            "{}{}",
+            LIGHTNESS_LETTERS[index.y as usize], HUE_LETTERS[index.x as usize]
+        ),
+        None if color == WHITE => String::from("WW"),
+        None if color == BLACK => String::from("KK"),
+        None => String::from("??"),
+    }
+}
+
+/// Runs the program, the codel it starts at and every codel it enters a block at.
+/// Stops at the limits or at an error, with the path up to there.
+pub fn execution_path(
+    machine: &mut Machine,
+    limits: &Limits,
+) -> (Vec<Coordinates>, Result<Halt, RuntimeError>) {
+    let mut path = vec![machine.codel()];
+    let halt = machine.run(limits, |machine, _| path.push(machine.codel()));
+    (path, halt)
+}
+
+/// Prints the codel grid of a program, a line per row of codels. With `ids` the top left codel of
+/// every color block shows the id of the block (its index in `BlockMap::blocks`), the codels on
+/// `path` are marked with `*`.
+pub fn show(blocks: &BlockMap, style: Style, ids: bool, path: &[Coordinates]) -> String {
+    let mut on_path = vec![false; (blocks.width * blocks.height) as usize];
+    for codel in path {
+        on_path[(codel.y * blocks.width + codel.x) as usize] = true;
+    }
+    let mut labels = vec![None; on_path.len()];
+    if ids {
+        for (id, block) in blocks.blocks.iter().enumerate() {
+            let codel = block.top_left();
+            labels[(codel.y * blocks.width + codel.x) as usize] = Some(id);
+        }
+    }
+    let width = if ids {
+        blocks
+            .blocks
+            .len()
+            .saturating_sub(1)
+            .to_string()
+            .len()
+            .max(2)
+    } else {
+        2
+    };
+
+    let mut out = String::new();
+    for y in 0..blocks.height {
+        let mut cells = Vec::new();
+        for x in 0..blocks.width {
+            let index = (y * blocks.width + x) as usize;
+            let color = blocks.color(Coordinates { x, y }).unwrap();
+            let text = match (labels[index], style) {
+                (Some(id), _) => id.to_string(),
+                (None, Style::Color) if on_path[index] => String::from("*"),
+                (None, Style::Color) => String::new(),
+                // the hue is kept, it says more than the lightness
+                (None, Style::Letters) if on_path[index] => format!("*{}", &token(color)[1..]),
+                (None, Style::Letters) => token(color),
+            };
+            let cell = format!("{:<width$}", text, width = width);
+            match style {
+                // the colors are only set again where they change
+                Style::Color
+                    if x > 0 && blocks.color(Coordinates { x: x - 1, y }) == Some(color) =>
+                {
+                    cells.push(cell)
+                }
+                Style::Color => {
+                    let RGB(r, g, b) = color;
+                    // dark text on light colors and the other way round
+                    let fg = if (r as u32 * 3 + g as u32 * 6 + b as u32) / 10 > 128 {
+                        "0;0;0"
+                    } else {
+                        "255;255;255"
+                    };
+                    cells.push(format!(
+                        "\x1b[48;2;{};{};{}m\x1b[38;2;{}m{}",
+                        r, g, b, fg, cell
+                    ))
+                }
+                Style::Letters => cells.push(cell),
+            }
+        }
+        match style {
+            Style::Color => {
+                out += &cells.concat();
+                out += RESET;
+            }
+            Style::Letters => out += cells.join(" ").trim_end(),
+        }
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::Interpreter;
+    use crate::piet_io::*;
+    use crate::show::*;
+    use std::io::{empty, sink};
+
+    const RED: RGB = RGB(255, 0, 0);
+    const DARK_BLUE: RGB = RGB(0, 0, 192);
+    const LIGHT_GREEN: RGB = RGB(192, 255, 192);
+    const WHITE: RGB = RGB(255, 255, 255);
+    const BLACK: RGB = RGB(0, 0, 0);
+
+    fn program() -> Vec<Vec<RGB>> {
+        vec![
+            vec![RED, RED, DARK_BLUE, BLACK],
+            vec![LIGHT_GREEN, WHITE, DARK_BLUE, BLACK],
+        ]
+    }
+
+    #[test]
+    fn names_colors_with_two_letters() {
+        assert_eq!(token(RED), "nR");
+        assert_eq!(token(DARK_BLUE), "dB");
+        assert_eq!(token(LIGHT_GREEN), "lG");
+        assert_eq!(token(RGB(192, 255, 255)), "lC");
+        assert_eq!(token(WHITE), "WW");
+        assert_eq!(token(BLACK), "KK");
+        assert_eq!(token(RGB(1, 2, 3)), "??");
+    }
+
+    #[test]
+    fn shows_codels_as_letters() {
+        let blocks = BlockMap::new(&program(), 1);
+        assert_eq!(
+            show(&blocks, Style::Letters, false, &[]),
+            "nR nR dB KK\nlG WW dB KK\n"
+        );
+    }
+
+    #[test]
+    fn shows_codels_in_color() {
+        let blocks = BlockMap::new(&program(), 1);
+        let lines: Vec<String> = show(&blocks, Style::Color, false, &[])
+            .lines()
+            .map(String::from)
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert!(
+            lines[0].starts_with("\x1b[48;2;255;0;0m\x1b[38;2;255;255;255m    \x1b[48;2;0;0;192m")
+        );
+        assert!(lines[1].contains("\x1b[48;2;192;255;192m\x1b[38;2;0;0;0m  "));
+        assert!(lines[1].ends_with(RESET));
+    }
+
+    #[test]
+    fn overlays_block_ids() {
+        let blocks = BlockMap::new(&program(), 1);
+        assert_eq!(
+            show(&blocks, Style::Letters, true, &[]),
+            "0  nR 1  KK\n2  WW dB KK\n"
+        );
+    }
+
+    #[test]
+    fn overlays_the_execution_path() {
+        let io = Io::new(Box::new(empty()), Box::new(sink()));
+        let mut machine = Machine::new(program(), 1, Interpreter::new(io));
+        let limits = Limits {
+            max_steps: Some(3),
+            timeout: None,
+        };
+        let (path, halt) = execution_path(&mut machine, &limits);
+        assert!(halt.is_ok());
+        assert_eq!(path.len(), machine.steps as usize + 1);
+        assert_eq!(
+            path[..2],
+            [Coordinates { x: 0, y: 0 }, Coordinates { x: 2, y: 0 }]
+        );
+
+        let shown = show(&machine.blocks, Style::Letters, false, &path[..2]);
+        assert_eq!(shown.lines().next(), Some("*R nR *B KK"));
+    }
+}
@@ -0,0 +1,248 @@
+use crate::interpreter::{Division, Overflow};
+use crate::json::Json;
+use crate::machine::Machine;
+use crate::number::{Int, IntBackend};
+use crate::types::*;
+
+mod tests;
+
+const FORMAT: &str = "pint-state";
+/// Version of the state file format, files of newer versions are refused.
+pub const FORMAT_VERSION: i64 = 2;
+
+/// Everything needed to continue a program later, saved as JSON.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    /// Identifies the image the state belongs to, see `image_hash`.
+    pub image_hash: String,
+    pub codel_size: i32,
+    /// Position in codels.
+    pub position: Coordinates,
+    pub dp: Direction,
+    pub cc: CodelChooser,
+    pub stack: Vec<Int>,
+    pub steps: u64,
+    /// Input that was read ahead but not consumed by the program.
+    pub input: Vec<u8>,
+    /// The arithmetic the stack was computed with, resuming with another one is refused.
+    pub backend: IntBackend,
+    pub overflow: Overflow,
+    pub division: Division,
+}
+
+/// 64 bit FNV-1a hash over the size and the pixels of the image.
+pub fn image_hash(rgb_img: &[Vec<RGB>]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |bytes: &[u8]| {
+        for b in bytes {
+            hash ^= *b as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    };
+    feed(&(rgb_img.len() as u32).to_le_bytes());
+    feed(&(rgb_img.first().map_or(0, Vec::len) as u32).to_le_bytes());
+    for row in rgb_img {
+        for RGB(r, g, b) in row {
+            feed(&[*r, *g, *b]);
+        }
+    }
+    format!("fnv1a64:{:016x}", hash)
+}
+
+impl Snapshot {
+    /// The state of `machine`, including the input it has read ahead.
+    pub fn take(machine: &Machine) -> Snapshot {
+        Snapshot {
+            image_hash: image_hash(&machine.rgb_img),
+            codel_size: machine.codel_size,
+            position: machine.codel(),
+            dp: machine.interp.dp,
+            cc: machine.interp.cc,
+            stack: machine.interp.stack.clone(),
+            steps: machine.steps,
+            input: machine.interp.io.pending_input(),
+            backend: machine.interp.backend,
+            overflow: machine.interp.overflow,
+            division: machine.interp.division,
+        }
+    }
+
+    /// Puts `machine` into the saved state, the pending input is read before any new input.
+    /// Fails if the state was saved for another image, codel size or arithmetic.
+    pub fn restore(&self, machine: &mut Machine) -> Result<(), String> {
+        if image_hash(&machine.rgb_img) != self.image_hash {
+            return Err(String::from("the state was saved for a different image"));
+        }
+        if machine.codel_size != self.codel_size {
+            return Err(format!(
+                "the state was saved with codel size {}, not {}",
+                self.codel_size, machine.codel_size
+            ));
+        }
+        let interp = &machine.interp;
+        let options = [
+            ("--int", self.backend.name(), interp.backend.name()),
+            ("--overflow", self.overflow.name(), interp.overflow.name()),
+            ("--division", self.division.name(), interp.division.name()),
+        ];
+        for (option, saved, current) in options.iter() {
+            if saved != current {
+                return Err(format!(
+                    "the state was saved with {} {}, not {}",
+                    option, saved, current
+                ));
+            }
+        }
+        if machine.blocks.color(self.position).is_none() {
+            return Err(String::from("the saved position is outside of the image"));
+        }
+
+        machine.pos = machine.to_pixel(self.position);
+        machine.interp.dp = self.dp;
+        machine.interp.cc = self.cc;
+        machine.current_color = machine.blocks.color_info(self.position);
+        machine.interp.stack = self.stack.clone();
+        machine.steps = self.steps;
+        machine.interp.io.prepend_input(&self.input);
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("format", Json::string(FORMAT)),
+            ("version", Json::number(FORMAT_VERSION)),
+            (
+                "image",
+                Json::object(vec![
+                    ("hash", Json::string(&self.image_hash)),
+                    ("codel_size", Json::number(self.codel_size)),
+                ]),
+            ),
+            (
+                "position",
+                Json::object(vec![
+                    ("x", Json::number(self.position.x)),
+                    ("y", Json::number(self.position.y)),
+                ]),
+            ),
+            ("dp", Json::string(format!("{:?}", self.dp).to_lowercase())),
+            ("cc", Json::string(format!("{:?}", self.cc).to_lowercase())),
+            (
+                "stack",
+                Json::Array(self.stack.iter().map(Json::number).collect()),
+            ),
+            ("steps", Json::number(self.steps)),
+            (
+                "arithmetic",
+                Json::object(vec![
+                    ("int", Json::string(self.backend.name())),
+                    ("overflow", Json::string(self.overflow.name())),
+                    ("division", Json::string(self.division.name())),
+                ]),
+            ),
+            (
+                "input",
+                Json::Array(self.input.iter().map(Json::number).collect()),
+            ),
+        ])
+    }
+
+    pub fn from_json(json: &Json) -> Result<Snapshot, String> {
+        let field = |key: &str| json.get(key).ok_or(format!("missing `{}`", key));
+        let string = |value: &Json, key: &str| {
+            value
+                .as_str()
+                .map(String::from)
+                .ok_or(format!("`{}` is not a string", key))
+        };
+        let number =
+            |value: &Json, key: &str| value.as_i64().ok_or(format!("`{}` is not an integer", key));
+        let count = |value: &Json, key: &str| {
+            value
+                .as_i64()
+                .filter(|n| *n >= 0)
+                .ok_or(format!("`{}` is not a positive integer", key))
+        };
+
+        if json.get("format").and_then(Json::as_str) != Some(FORMAT) {
+            return Err(String::from("not a pint state file"));
+        }
+        let version = number(field("version")?, "version")?;
+        if version < 1 {
+            return Err(format!("{} is not a state file version", version));
+        }
+        if version > FORMAT_VERSION {
+            return Err(format!(
+                "state file version {} is newer than the supported version {}",
+                version, FORMAT_VERSION
+            ));
+        }
+        if version < FORMAT_VERSION {
+            return Err(format!(
+                "state file version {} doesn't record the arithmetic of the stack, it can't be resumed",
+                version
+            ));
+        }
+
+        let image = field("image")?;
+        let arithmetic = field("arithmetic")?;
+        let option = |key: &str| {
+            arithmetic
+                .get(key)
+                .and_then(Json::as_str)
+                .ok_or(format!("`arithmetic.{}` is not a string", key))
+        };
+        let position = field("position")?;
+        let coordinate = |key: &str| {
+            position
+                .get(key)
+                .and_then(Json::as_i64)
+                .map(|n| n as i32)
+                .ok_or(format!("`position.{}` is not an integer", key))
+        };
+        let array = |key: &str| {
+            field(key)?
+                .as_array()
+                .ok_or(format!("`{}` is not an array", key))
+        };
+
+        Ok(Snapshot {
+            image_hash: string(
+                image.get("hash").ok_or("missing `image.hash`")?,
+                "image.hash",
+            )?,
+            codel_size: number(
+                image
+                    .get("codel_size")
+                    .ok_or("missing `image.codel_size`")?,
+                "image.codel_size",
+            )? as i32,
+            position: Coordinates {
+                x: coordinate("x")?,
+                y: coordinate("y")?,
+            },
+            dp: string(field("dp")?, "dp")?.parse()?,
+            cc: string(field("cc")?, "cc")?.parse()?,
+            stack: array("stack")?
+                .iter()
+                .map(|n| match n {
+                    Json::Number(n) => n.parse::<Int>(),
+                    _ => Err(String::from("`stack` may only contain integers")),
+                })
+                .collect::<Result<_, _>>()?,
+            steps: count(field("steps")?, "steps")? as u64,
+            input: array("input")?
+                .iter()
+                .map(|b| {
+                    b.as_i64()
+                        .filter(|b| (0..=255).contains(b))
+                        .map(|b| b as u8)
+                        .ok_or(String::from("`input` may only contain bytes"))
+                })
+                .collect::<Result<_, _>>()?,
+            backend: option("int")?.parse()?,
+            overflow: option("overflow")?.parse()?,
+            division: option("division")?.parse()?,
+        })
+    }
+}
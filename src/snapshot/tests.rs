@@ -0,0 +1,142 @@
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::Interpreter;
+    use crate::json::Json;
+    use crate::machine::*;
+    use crate::piet_io::*;
+    use crate::snapshot::*;
+    use crate::test_support::load;
+    use crate::types::RGB;
+    use std::io::Cursor;
+
+    fn save_and_load(snapshot: &Snapshot) -> Snapshot {
+        let text = snapshot.to_json().to_string();
+        Snapshot::from_json(&text.parse::<Json>().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn resumed_program_continues_where_it_stopped() {
+        let (mut original, original_out) = load("tests/fixtures/99bottles.png");
+        let limits = Limits {
+            max_steps: Some(300),
+            ..Limits::default()
+        };
+        original.run(&limits, |_, _| ()).unwrap();
+        let snapshot = save_and_load(&Snapshot::take(&original));
+        assert_eq!(snapshot, Snapshot::take(&original));
+
+        let (mut resumed, resumed_out) = load("tests/fixtures/99bottles.png");
+        snapshot.restore(&mut resumed).unwrap();
+        assert_eq!(resumed.summary(), original.summary());
+
+        let before = original_out.contents().len();
+        let limits = Limits {
+            max_steps: Some(1000),
+            ..Limits::default()
+        };
+        original.run(&limits, |_, _| ()).unwrap();
+        resumed.run(&limits, |_, _| ()).unwrap();
+        assert!(!resumed_out.contents().is_empty());
+        assert_eq!(resumed_out.contents(), original_out.contents()[before..]);
+        assert_eq!(resumed.steps, original.steps);
+    }
+    #[test]
+    fn keeps_pending_input() {
+        // light red -> light magenta is in_char
+        let rgb_img = vec![vec![RGB(255, 192, 192), RGB(255, 192, 255)]];
+        let io = Io::new(
+            Box::new(Cursor::new(b"abc".to_vec())),
+            Box::new(std::io::sink()),
+        );
+        let mut machine = Machine::new(rgb_img.clone(), 1, Interpreter::new(io));
+        machine.step().unwrap();
+
+        let snapshot = save_and_load(&Snapshot::take(&machine));
+        assert_eq!(snapshot.input, b"bc");
+
+        let io = Io::new(
+            Box::new(Cursor::new(b"d".to_vec())),
+            Box::new(std::io::sink()),
+        );
+        let mut resumed = Machine::new(rgb_img, 1, Interpreter::new(io));
+        snapshot.restore(&mut resumed).unwrap();
+        let mut read = Vec::new();
+        while let Ok(Some(c)) = resumed.interp.io.read_char() {
+            read.push(c as u8);
+        }
+        assert_eq!(read, b"bcd");
+    }
+    #[test]
+    fn refuses_other_image() {
+        let (hello, _) = load("tests/fixtures/piet_hello_world.png");
+        let (mut bottles, _) = load("tests/fixtures/99bottles.png");
+
+        let snapshot = Snapshot::take(&hello);
+        assert_eq!(
+            snapshot.restore(&mut bottles),
+            Err(String::from("the state was saved for a different image"))
+        );
+        assert_ne!(image_hash(&hello.rgb_img), image_hash(&bottles.rgb_img));
+    }
+    #[test]
+    fn refuses_newer_versions() {
+        let (hello, _) = load("tests/fixtures/piet_hello_world.png");
+        let text = Snapshot::take(&hello)
+            .to_json()
+            .to_string()
+            .replace("\"version\":2", "\"version\":3");
+
+        let result = Snapshot::from_json(&text.parse().unwrap());
+        assert_eq!(
+            result,
+            Err(String::from(
+                "state file version 3 is newer than the supported version 2"
+            ))
+        );
+        assert!(Snapshot::from_json(&Json::object(vec![])).is_err());
+    }
+    #[test]
+    fn refuses_invalid_versions_and_steps() {
+        let (hello, _) = load("tests/fixtures/piet_hello_world.png");
+        let text = Snapshot::take(&hello).to_json().to_string();
+        let error = |from: &str, to: &str| {
+            let json = text.replace(from, to).parse().unwrap();
+            Snapshot::from_json(&json).unwrap_err()
+        };
+
+        assert_eq!(
+            error("\"version\":2", "\"version\":0"),
+            "0 is not a state file version"
+        );
+        assert!(error("\"version\":2", "\"version\":1").contains("can't be resumed"));
+        assert_eq!(
+            error("\"steps\":0", "\"steps\":-5"),
+            "`steps` is not a positive integer"
+        );
+    }
+    #[test]
+    fn refuses_other_arithmetic() {
+        let (hello, _) = load("tests/fixtures/piet_hello_world.png");
+        let mut big = Snapshot::take(&hello);
+        big.backend = IntBackend::Big;
+        big.overflow = Overflow::Error;
+        let big = save_and_load(&big);
+        assert_eq!(big.backend, IntBackend::Big);
+        assert_eq!(big.overflow, Overflow::Error);
+
+        let (mut resumed, _) = load("tests/fixtures/piet_hello_world.png");
+        assert_eq!(
+            big.restore(&mut resumed),
+            Err(String::from("the state was saved with --int big, not i32"))
+        );
+        resumed.interp.backend = IntBackend::Big;
+        assert_eq!(
+            big.restore(&mut resumed),
+            Err(String::from(
+                "the state was saved with --overflow error, not wrap"
+            ))
+        );
+        resumed.interp.overflow = Overflow::Error;
+        assert_eq!(big.restore(&mut resumed), Ok(()));
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::VecDeque;
+
+use crate::graph::*;
+use crate::interpreter::*;
+
+mod tests;
+
+/// What is known about the stack in a state, over every way the program can get there.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Depth {
+    /// The fewest values there can be.
+    pub min: usize,
+    /// The most values there can be, `None` if there is no bound.
+    pub max: Option<usize>,
+    /// The lowest and highest the top value can be if that is known, like after `push` or `not`.
+    pub top: Option<(i64, i64)>,
+}
+
+impl Depth {
+    fn join(self, other: Depth) -> Depth {
+        Depth {
+            min: self.min.min(other.min),
+            max: self.max.zip(other.max).map(|(a, b)| a.max(b)),
+            top: self
+                .top
+                .zip(other.top)
+                .map(|(a, b)| (a.0.min(b.0), a.1.max(b.1))),
+        }
+    }
+
+    fn top_can_be_zero(&self) -> bool {
+        self.top.is_none_or(|(low, high)| low <= 0 && high >= 0)
+    }
+
+    // what is known after `command` leaves `size` codels, deeper than `bound` means the stack grows in a loop
+    fn after(&self, command: Command, size: i32, bound: usize) -> Depth {
+        let needed = command.operands();
+        // the fewest and most values after the command runs with at least `needed` values
+        let fewest = |depth: usize| match command {
+            Command::Push | Command::Dup => depth + 1,
+            // nothing is read at the end of input
+            Command::None | Command::Not | Command::InNum | Command::InChar => depth,
+            // a roll that is done takes both values
+            Command::Roll => depth - 2,
+            _ => depth - 1,
+        };
+        let most = |depth: usize| match command {
+            Command::Push | Command::Dup | Command::InNum | Command::InChar => depth + 1,
+            Command::None | Command::Not | Command::Roll => depth,
+            // dividing by 0 is ignored
+            Command::Div | Command::Mod if self.top_can_be_zero() => depth,
+            _ => depth - 1,
+        };
+        let always_underflows = self.max.is_some_and(|max| max < needed);
+
+        // a command that underflows leaves the stack as it was
+        let min = if always_underflows {
+            self.min
+        } else if self.min < needed {
+            self.min.min(fewest(needed))
+        } else {
+            fewest(self.min)
+        };
+        let max = self
+            .max
+            .map(|max| if max < needed { max } else { most(max) })
+            .filter(|max| *max <= bound);
+        let top = match command {
+            Command::Push => Some((size as i64, size as i64)),
+            Command::Dup => self.top,
+            Command::Not => match self.top {
+                Some((0, 0)) => Some((1, 1)),
+                Some(_) if !self.top_can_be_zero() => Some((0, 0)),
+                _ => Some((0, 1)),
+            },
+            Command::Greater => Some((0, 1)),
+            _ => None,
+        };
+        // a range that doesn't contain 0 means there is a value, an empty stack leaves the DP and CC
+        // like a 0 on top would
+        let top = if always_underflows {
+            self.top
+        } else if self.min < needed && needed == 1 {
+            top.map(|(low, high)| (low.min(0), high.max(0)))
+        } else if self.min < needed {
+            top.zip(self.top).map(|(a, b)| (a.0.min(b.0), a.1.max(b.1)))
+        } else {
+            top
+        };
+        Depth { min, max, top }
+    }
+}
+
+/// A command that can run with fewer values on the stack than it takes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Underflow {
+    /// The state the command is executed from.
+    pub state: State,
+    pub command: Command,
+    pub depth: Depth,
+}
+
+impl Underflow {
+    /// Whether the command underflows every time it runs.
+    pub fn always(&self) -> bool {
+        self.depth
+            .max
+            .is_some_and(|max| max < self.command.operands())
+    }
+}
+
+/// The stack depth in every state of a program, found by running the graph with what is known
+/// about the stack instead of values. `pointer` and `switch` only lead to the states the known
+/// range of the top value allows, so `push 1, pointer` takes one branch.
+pub struct StackDepths {
+    /// Indexed by `State::index`, `None` for states the program can't get to.
+    depths: Vec<Option<Depth>>,
+}
+
+impl StackDepths {
+    pub fn analyze(graph: &Graph) -> StackDepths {
+        // a path through every state can't get deeper than this without going around a loop
+        let bound = graph.reachable().len();
+        let mut depths: Vec<Option<Depth>> = vec![None; graph.nodes.len() * 8];
+        depths[graph.start().index()] = Some(Depth {
+            min: 0,
+            max: Some(0),
+            top: None,
+        });
+
+        let mut todo = VecDeque::from([graph.start()]);
+        while let Some(state) = todo.pop_front() {
+            let edge = match graph.edge(state) {
+                Some(edge) => edge,
+                None => continue,
+            };
+            let before = depths[state.index()].unwrap();
+            let after = before.after(edge.command, edge.size, bound);
+            for next in taken(edge, before) {
+                let known = &mut depths[next.index()];
+                let joined = known.map_or(after, |known| known.join(after));
+                if *known != Some(joined) {
+                    *known = Some(joined);
+                    todo.push_back(next);
+                }
+            }
+        }
+        StackDepths { depths }
+    }
+
+    /// What is known about the stack in `state`, `None` if the program can't get there.
+    pub fn at(&self, state: State) -> Option<Depth> {
+        self.depths[state.index()]
+    }
+
+    /// The states a step from `state` can lead to with what is known about the stack.
+    pub fn successors(&self, graph: &Graph, state: State) -> Vec<State> {
+        match (graph.edge(state), self.at(state)) {
+            (Some(edge), Some(depth)) => taken(edge, depth),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The states the program can get to, in the order of `Graph::reachable`.
+    pub fn reachable(&self, graph: &Graph) -> Vec<State> {
+        graph
+            .reachable()
+            .into_iter()
+            .filter(|state| self.at(*state).is_some())
+            .collect()
+    }
+
+    /// The commands that can run with too few values on the stack.
+    pub fn underflows(&self, graph: &Graph) -> Vec<Underflow> {
+        self.reachable(graph)
+            .into_iter()
+            .filter_map(|state| {
+                let edge = graph.edge(state)?;
+                let depth = self.at(state)?;
+                (depth.min < edge.command.operands()).then_some(Underflow {
+                    state,
+                    command: edge.command,
+                    depth,
+                })
+            })
+            .collect()
+    }
+}
+
+// the states a step can lead to, branches only to those the top value allows
+fn taken(edge: &Edge, before: Depth) -> Vec<State> {
+    let targets = edge.targets();
+    let count = targets.len() as i64;
+    match before.top {
+        Some((low, high)) if high - low < count => (low..=high)
+            .map(|top| targets[top.rem_euclid(count) as usize])
+            .collect(),
+        _ => targets,
+    }
+}
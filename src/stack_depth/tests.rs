@@ -0,0 +1,130 @@
+#[cfg(test)]
+mod tests {
+    use crate::assembler::assemble;
+    use crate::blocks::BlockMap;
+    use crate::stack_depth::*;
+
+    fn compile(source: &str) -> Graph {
+        Graph::compile(&BlockMap::new(&assemble(source).unwrap(), 1))
+    }
+
+    // what is known before every `command` the program can get to
+    fn before(graph: &Graph, depths: &StackDepths, command: Command) -> Vec<Depth> {
+        depths
+            .reachable(graph)
+            .into_iter()
+            .filter(|state| {
+                graph
+                    .edge(*state)
+                    .is_some_and(|edge| edge.command == command)
+            })
+            .map(|state| depths.at(state).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn counts_values_on_straight_programs() {
+        let graph = compile("push 3\npush 2\nadd\nout_num\n");
+        let depths = StackDepths::analyze(&graph);
+        assert_eq!(
+            depths.at(graph.start()),
+            Some(Depth {
+                min: 0,
+                max: Some(0),
+                top: None
+            })
+        );
+        assert_eq!(
+            before(&graph, &depths, Command::Add),
+            vec![Depth {
+                min: 2,
+                max: Some(2),
+                top: Some((2, 2))
+            }]
+        );
+        assert_eq!(
+            before(&graph, &depths, Command::OutNum),
+            vec![Depth {
+                min: 1,
+                max: Some(1),
+                top: None
+            }]
+        );
+        assert_eq!(depths.underflows(&graph), vec![]);
+    }
+
+    #[test]
+    fn branches_follow_the_top_value() {
+        let graph = compile("in_num\njz zero\npush 1\nzero:\nout_num\n");
+        let depths = StackDepths::analyze(&graph);
+        let branches: Vec<usize> = depths
+            .reachable(&graph)
+            .into_iter()
+            .filter(|state| {
+                graph
+                    .edge(*state)
+                    .is_some_and(|edge| edge.command == Command::Pointer)
+            })
+            .map(|state| depths.successors(&graph, state).len())
+            .collect();
+        // jz can go two ways, the turns of the jumps only one
+        assert!(branches.contains(&2));
+        assert!(branches.iter().all(|n| *n <= 2));
+        assert!(
+            depths
+                .reachable(&graph)
+                .iter()
+                .all(|state| depths.successors(&graph, *state).len()
+                    <= graph.successors(*state).len())
+        );
+    }
+
+    #[test]
+    fn finds_commands_that_can_underflow() {
+        // nothing is read at the end of input, and out_num has nothing to print if the input was 0
+        let graph = compile("in_num\njz zero\npush 1\nzero:\nout_num\n");
+        let depths = StackDepths::analyze(&graph);
+        let underflows = depths.underflows(&graph);
+        let commands: Vec<Command> = underflows.iter().map(|u| u.command).collect();
+        assert_eq!(
+            commands,
+            vec![Command::Not, Command::Pointer, Command::OutNum]
+        );
+        assert!(underflows.iter().all(|u| !u.always()));
+        assert_eq!(underflows[2].depth.min, 0);
+        assert_eq!(underflows[2].depth.max, Some(1));
+
+        let graph = compile("push 1\nadd\n");
+        let underflows = StackDepths::analyze(&graph).underflows(&graph);
+        assert_eq!(underflows.len(), 1);
+        assert_eq!(underflows[0].command, Command::Add);
+        assert!(underflows[0].always());
+    }
+
+    #[test]
+    fn loops_can_grow_the_stack_without_bound() {
+        let graph = compile("loop:\npush 1\nin_num\njnz loop\npop\n");
+        let depths = StackDepths::analyze(&graph);
+        for depth in before(&graph, &depths, Command::Pop) {
+            assert_eq!(depth.max, None);
+            // without input jnz takes the 1 that was pushed
+            assert_eq!(depth.min, 0);
+        }
+    }
+
+    #[test]
+    fn division_by_zero_keeps_both_values() {
+        let graph = compile("push 5\npush 1\ndup\nsub\ndiv\npush 2\npush 1\ndiv\nout_num\n");
+        let depths = StackDepths::analyze(&graph);
+        let divs = before(&graph, &depths, Command::Div);
+        assert_eq!(divs.len(), 2);
+        assert_eq!(divs[0].min, 2);
+        assert_eq!(divs[0].top, None);
+        // 1 - 1 isn't known to be 0, so the first div leaves one or two values
+        assert_eq!(divs[1].min, 3);
+        assert_eq!(divs[1].max, Some(4));
+        assert_eq!(divs[1].top, Some((1, 1)));
+        let out = before(&graph, &depths, Command::OutNum);
+        assert_eq!((out[0].min, out[0].max), (2, Some(3)));
+    }
+}
@@ -0,0 +1,32 @@
+use std::fs::File;
+use std::io::empty;
+
+use crate::decoder;
+use crate::interpreter::Interpreter;
+use crate::machine::Machine;
+use crate::piet_io::*;
+use crate::types::RGB;
+
+pub(crate) const HELLO_WORLD: &str = "tests/fixtures/piet_hello_world.png";
+
+/// The pixels of a png and the codel size inferred from them.
+pub(crate) fn load_image(path: &str) -> (Vec<Vec<RGB>>, i32) {
+    let mut file = File::open(path).unwrap();
+    decoder::check_valid_png(&mut file);
+    let rgb_img = decoder::decode_png(file);
+    let codel_size = decoder::infer_codel_size(&rgb_img);
+    (rgb_img, codel_size)
+}
+
+/// A machine for a png without input and the buffer it writes its output to.
+pub(crate) fn load(path: &str) -> (Machine, SharedBuffer) {
+    let (rgb_img, codel_size) = load_image(path);
+    let out = SharedBuffer::default();
+    let io = Io::new(Box::new(empty()), Box::new(out.clone()));
+    (Machine::new(rgb_img, codel_size, Interpreter::new(io)), out)
+}
+
+/// A machine for the hello world program, for tests that don't look at the output.
+pub(crate) fn hello_world() -> Machine {
+    load(HELLO_WORLD).0
+}
@@ -0,0 +1,83 @@
+use crate::interpreter::COLORS;
+use crate::show::token;
+use crate::types::{BLACK, RGB, WHITE};
+
+mod tests;
+
+/// Whether `path` is a program in the text format instead of a png.
+pub fn is_text_grid(path: &str) -> bool {
+    path.ends_with(".txt")
+}
+
+// the color a token of `show::token` stands for
+fn color(text: &str) -> Option<RGB> {
+    COLORS
+        .iter()
+        .flatten()
+        .chain([WHITE, BLACK].iter())
+        .copied()
+        .find(|color| token(*color) == text)
+}
+
+/// Reads a program written as codel grid: a line per row of codels, the codels separated by
+/// whitespace as tokens like `show::token` writes them (`lR` is light red, `dB` dark blue, `WW`
+/// white and `KK` black). Everything after `#` is a comment, empty lines are left out.
+/// Every codel is a pixel of the image.
+pub fn parse_text_grid(text: &str) -> Result<Vec<Vec<RGB>>, String> {
+    let mut rows: Vec<Vec<RGB>> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap();
+        let row = line
+            .split_whitespace()
+            .map(|token| {
+                color(token).ok_or(format!(
+                    "line {}: `{}` isn't a codel, codels are like `lR`, `nG`, `dB`, `WW` or `KK`",
+                    number + 1,
+                    token
+                ))
+            })
+            .collect::<Result<Vec<RGB>, String>>()?;
+        if row.is_empty() {
+            continue;
+        }
+        if let Some(first) = rows.first() {
+            if row.len() != first.len() {
+                return Err(format!(
+                    "line {}: {} instead of {} codels like the first row",
+                    number + 1,
+                    row.len(),
+                    first.len()
+                ));
+            }
+        }
+        rows.push(row);
+    }
+    if rows.is_empty() {
+        return Err(String::from("there are no codels"));
+    }
+    Ok(rows)
+}
+
+/// Writes the codels of an image as grid `parse_text_grid` reads, taking the top left pixel of
+/// every codel. Fails for colors outside of the 20, they have no token.
+pub fn write_text_grid(rgb_img: &[Vec<RGB>], codel_size: i32) -> Result<String, String> {
+    let size = codel_size as usize;
+    let (width, height) = (rgb_img[0].len() / size, rgb_img.len() / size);
+    let mut text = String::new();
+    for y in 0..height {
+        let mut tokens = Vec::new();
+        for x in 0..width {
+            let pixel = rgb_img[y * size][x * size];
+            if color(&token(pixel)).is_none() {
+                return Err(format!(
+                    "codel ({}, {}) is #{:02x}{:02x}{:02x}, which isn't one of the 20 colors",
+                    x, y, pixel.0, pixel.1, pixel.2
+                ));
+            }
+            tokens.push(token(pixel));
+        }
+        text += &tokens.join(" ");
+        text.push('\n');
+    }
+    Ok(text)
+}
@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod tests {
+    use crate::text_grid::*;
+
+    const RED: RGB = RGB(255, 0, 0);
+    const DARK_BLUE: RGB = RGB(0, 0, 192);
+    const LIGHT_GREEN: RGB = RGB(192, 255, 192);
+
+    #[test]
+    fn reads_codels() {
+        let text = "# a comment\nnR nR   dB KK # red, dark blue and black\n\nlG WW dB KK\n";
+        assert_eq!(
+            parse_text_grid(text),
+            Ok(vec![
+                vec![RED, RED, DARK_BLUE, BLACK],
+                vec![LIGHT_GREEN, WHITE, DARK_BLUE, BLACK],
+            ])
+        );
+    }
+
+    #[test]
+    fn says_what_is_wrong() {
+        assert_eq!(
+            parse_text_grid("nR nR\nnR xx\n"),
+            Err(String::from(
+                "line 2: `xx` isn't a codel, codels are like `lR`, `nG`, `dB`, `WW` or `KK`"
+            ))
+        );
+        assert_eq!(
+            parse_text_grid("nR nR\n\nnR\n"),
+            Err(String::from(
+                "line 3: 1 instead of 2 codels like the first row"
+            ))
+        );
+        assert_eq!(
+            parse_text_grid("# nothing\n"),
+            Err(String::from("there are no codels"))
+        );
+    }
+
+    #[test]
+    fn writes_what_it_reads() {
+        let img = vec![
+            vec![RED, RED, DARK_BLUE, BLACK],
+            vec![LIGHT_GREEN, WHITE, DARK_BLUE, BLACK],
+        ];
+        let text = write_text_grid(&img, 1).unwrap();
+        assert_eq!(text, "nR nR dB KK\nlG WW dB KK\n");
+        assert_eq!(parse_text_grid(&text), Ok(img.clone()));
+
+        // every codel is written once
+        let scaled: Vec<Vec<RGB>> = img
+            .iter()
+            .flat_map(|row| {
+                let row: Vec<RGB> = row.iter().flat_map(|c| [*c, *c]).collect();
+                vec![row.clone(), row]
+            })
+            .collect();
+        assert_eq!(write_text_grid(&scaled, 2).unwrap(), text);
+
+        assert_eq!(
+            write_text_grid(&[vec![RED, RGB(1, 2, 3)]], 1),
+            Err(String::from(
+                "codel (1, 0) is #010203, which isn't one of the 20 colors"
+            ))
+        );
+    }
+}
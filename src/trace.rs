@@ -0,0 +1,69 @@
+use crate::interpreter::color_name;
+use crate::json::Json;
+use crate::machine::*;
+use crate::types::*;
+
+mod tests;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// One human readable line per step.
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+fn block_json(pos: Coordinates, color: ColorInfo) -> Json {
+    Json::object(vec![
+        ("x", Json::number(pos.x)),
+        ("y", Json::number(pos.y)),
+        ("color", Json::string(color_name(color.color))),
+        ("size", Json::number(color.size)),
+    ])
+}
+
+/// Describes a step after it was executed: the block that was left and the one that was entered
+/// (codel position, color and size), DP and CC, the command and the stack afterwards.
+pub fn trace_line(machine: &Machine, step: &Step, format: TraceFormat) -> String {
+    let from = machine.to_codel(step.from_pos);
+    let to = machine.codel();
+    let stack: Vec<String> = machine.interp.stack.iter().map(|n| n.to_string()).collect();
+
+    match format {
+        TraceFormat::Text => format!(
+            "step {}: ({}, {}) {}/{} -> ({}, {}) {}/{} dp={:?} cc={:?} {} stack=[{}]",
+            machine.steps,
+            from.x,
+            from.y,
+            color_name(step.from_color.color),
+            step.from_color.size,
+            to.x,
+            to.y,
+            color_name(machine.current_color.color),
+            machine.current_color.size,
+            machine.interp.dp,
+            machine.interp.cc,
+            step.command.name(),
+            stack.join(", ")
+        ),
+        TraceFormat::Json => Json::object(vec![
+            ("step", Json::number(machine.steps)),
+            ("from", block_json(from, step.from_color)),
+            ("to", block_json(to, machine.current_color)),
+            (
+                "dp",
+                Json::string(format!("{:?}", machine.interp.dp).to_lowercase()),
+            ),
+            (
+                "cc",
+                Json::string(format!("{:?}", machine.interp.cc).to_lowercase()),
+            ),
+            ("command", Json::string(step.command.name())),
+            (
+                "stack",
+                Json::Array(stack.into_iter().map(Json::Number).collect()),
+            ),
+        ])
+        .to_string(),
+    }
+}
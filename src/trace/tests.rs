@@ -0,0 +1,28 @@
+#[cfg(test)]
+mod tests {
+    use crate::machine::*;
+    use crate::test_support::hello_world;
+    use crate::trace::*;
+
+    #[test]
+    fn traces_steps_as_text() {
+        let mut machine = hello_world();
+
+        let step = machine.step().unwrap().unwrap();
+        assert_eq!(
+            trace_line(&machine, &step, TraceFormat::Text),
+            "step 1: (0, 0) red/72 -> (11, 0) dark red/1 dp=RIGHT cc=LEFT push stack=[72]"
+        );
+    }
+    #[test]
+    fn traces_steps_as_json() {
+        let mut machine = hello_world();
+
+        machine.step().unwrap();
+        let step = machine.step().unwrap().unwrap();
+        assert_eq!(
+            trace_line(&machine, &step, TraceFormat::Json),
+            r#"{"step":2,"from":{"x":11,"y":0,"color":"dark red","size":1},"to":{"x":12,"y":0,"color":"magenta","size":101},"dp":"right","cc":"left","command":"out_char","stack":[]}"#
+        );
+    }
+}
@@ -0,0 +1,465 @@
+use std::collections::HashMap;
+
+use crate::graph::*;
+use crate::interpreter::*;
+use crate::types::*;
+
+mod tests;
+
+/// The language to transpile to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Language {
+    C,
+    Rust,
+}
+
+// Both runtimes do what the commands in interpreter.rs and the reading and writing in piet_io.rs do
+// with 32-bit integers wrapping on overflow, UTF-8 characters and invalid ones printed as U+FFFD.
+// A command that runs out of values or divides by 0 leaves the stack as it was, like the interpreter.
+
+const C_RUNTIME: &str = r#"#include <inttypes.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+static int32_t *stack;
+static size_t len, cap;
+
+static void grow(void) {
+    cap = cap ? cap * 2 : 64;
+    stack = realloc(stack, cap * sizeof *stack);
+    if (!stack) {
+        perror("out of memory");
+        exit(1);
+    }
+}
+/* wraps around like two's complement arithmetic */
+static void push(int64_t n) {
+    if (len == cap) grow();
+    stack[len++] = (int32_t)(uint32_t)(uint64_t)n;
+}
+static int pop(int32_t *n) {
+    if (len == 0) return 0;
+    *n = stack[--len];
+    return 1;
+}
+/* the top value into a and the second one into b, nothing is popped if there is just one */
+static int pop2(int32_t *a, int32_t *b) {
+    if (len < 2) return 0;
+    *a = stack[--len];
+    *b = stack[--len];
+    return 1;
+}
+static void prompt(void) {
+    fputs("> ", stdout);
+    fflush(stdout);
+}
+
+static void cmd_pop(void) { int32_t a; pop(&a); }
+static void cmd_add(void) { int32_t a, b; if (pop2(&a, &b)) push((int64_t)b + a); }
+static void cmd_sub(void) { int32_t a, b; if (pop2(&a, &b)) push((int64_t)b - a); }
+static void cmd_mult(void) { int32_t a, b; if (pop2(&a, &b)) push((int64_t)b * a); }
+static void cmd_div(void) {
+    int32_t a, b;
+    if (!pop2(&a, &b)) return;
+    if (a == 0) {
+        push(b);
+        push(a);
+        return;
+    }
+    int64_t q = (int64_t)b / a;
+    if (FLOOR && (int64_t)b % a != 0 && (b < 0) != (a < 0)) q--;
+    push(q);
+}
+static void cmd_mod(void) {
+    int32_t a, b;
+    if (!pop2(&a, &b)) return;
+    if (a == 0) {
+        push(b);
+        push(a);
+        return;
+    }
+    int64_t r = (int64_t)b % a;
+    if (FLOOR && r != 0 && (r < 0) != (a < 0)) r += a;
+    push(r);
+}
+static void cmd_not(void) { int32_t a; if (pop(&a)) push(a == 0); }
+static void cmd_greater(void) { int32_t a, b; if (pop2(&a, &b)) push(b > a); }
+/* the number of clockwise rotations of DP */
+static int cmd_pointer(void) { int32_t a; return pop(&a) ? ((a % 4) + 4) % 4 : 0; }
+/* whether CC toggles */
+static int cmd_switch(void) { int32_t a; return pop(&a) ? a % 2 != 0 : 0; }
+static void cmd_dup(void) { int32_t a; if (pop(&a)) { push(a); push(a); } }
+static void cmd_roll(void) {
+    int32_t rolls, depth;
+    if (!pop2(&rolls, &depth)) return;
+    if (depth <= 0 || (size_t)depth > len) {
+        push(depth);
+        push(rolls);
+        return;
+    }
+    int64_t n = (((int64_t)rolls % depth) + depth) % depth;
+    int32_t *top = stack + len - depth;
+    int32_t *rolled = malloc(depth * sizeof *rolled);
+    if (!rolled) {
+        perror("out of memory");
+        exit(1);
+    }
+    for (int64_t i = 0; i < depth; i++) rolled[(i + n) % depth] = top[i];
+    memcpy(top, rolled, depth * sizeof *rolled);
+    free(rolled);
+}
+static void cmd_in_num(void) {
+    prompt();
+    size_t n = 0, size = 16;
+    char *line = malloc(size);
+    int c;
+    while (line && (c = getchar()) != EOF) {
+        if (n + 1 == size) line = realloc(line, size *= 2);
+        if (!line) break;
+        line[n++] = (char)c;
+        if (c == '\n') break;
+    }
+    if (!line) {
+        perror("out of memory");
+        exit(1);
+    }
+    char *start = line, *end = line + n;
+    while (start < end && strchr(" \t\n\v\f\r", *start)) start++;
+    while (end > start && strchr(" \t\n\v\f\r", end[-1])) end--;
+    int negative = start < end && *start == '-';
+    if (start < end && (*start == '-' || *start == '+')) start++;
+    uint32_t value = 0;
+    int valid = start < end;
+    for (char *p = start; p < end; p++) {
+        if (*p < '0' || *p > '9') valid = 0;
+        value = value * 10 + (uint32_t)(*p - '0');
+    }
+    if (n > 0 && !valid) fprintf(stderr, "input not a number\n");
+    if (valid) push(negative ? -(int64_t)value : (int64_t)value);
+    free(line);
+}
+static void cmd_in_char(void) {
+    prompt();
+    int lead = getchar();
+    if (lead == EOF) return;
+    int more = lead < 0x80 ? 0 : lead >= 0xC2 && lead <= 0xDF ? 1 : lead >= 0xE0 && lead <= 0xEF ? 2 : lead >= 0xF0 && lead <= 0xF4 ? 3 : -1;
+    if (more < 0) {
+        push(0xFFFD);
+        return;
+    }
+    int32_t c = more ? lead & (0x3F >> more) : lead;
+    for (int i = 0; i < more; i++) {
+        int b = getchar();
+        if (b == EOF || (b & 0xC0) != 0x80) {
+            if (b != EOF) ungetc(b, stdin);
+            push(0xFFFD);
+            return;
+        }
+        c = (c << 6) | (b & 0x3F);
+    }
+    int overlong = (more == 2 && c < 0x800) || (more == 3 && c < 0x10000);
+    if (overlong || c > 0x10FFFF || (c >= 0xD800 && c <= 0xDFFF)) c = 0xFFFD;
+    push(c);
+}
+static void cmd_out_num(void) { int32_t a; if (pop(&a)) printf("%" PRId32, a); }
+static void cmd_out_char(void) {
+    int32_t c;
+    if (!pop(&c)) return;
+    if (c < 0 || c > 0x10FFFF || (c >= 0xD800 && c <= 0xDFFF)) c = 0xFFFD;
+    if (c < 0x80) {
+        putchar(c);
+    } else if (c < 0x800) {
+        putchar(0xC0 | (c >> 6));
+        putchar(0x80 | (c & 0x3F));
+    } else if (c < 0x10000) {
+        putchar(0xE0 | (c >> 12));
+        putchar(0x80 | ((c >> 6) & 0x3F));
+        putchar(0x80 | (c & 0x3F));
+    } else {
+        putchar(0xF0 | (c >> 18));
+        putchar(0x80 | ((c >> 12) & 0x3F));
+        putchar(0x80 | ((c >> 6) & 0x3F));
+        putchar(0x80 | (c & 0x3F));
+    }
+}
+"#;
+
+const RUST_RUNTIME: &str = r#"use std::io::{self, BufRead, Write};
+
+struct Piet {
+    stack: Vec<i32>,
+    input: io::StdinLock<'static>,
+    output: io::BufWriter<io::Stdout>,
+}
+
+impl Piet {
+    // wraps around like two's complement arithmetic
+    fn push(&mut self, n: i64) {
+        self.stack.push(n as i32);
+    }
+    fn prompt(&mut self) {
+        let _ = write!(self.output, "> ");
+        let _ = self.output.flush();
+    }
+    fn peek_byte(&mut self) -> Option<u8> {
+        self.input.fill_buf().ok()?.first().copied()
+    }
+    fn next_byte(&mut self) -> Option<u8> {
+        let b = self.peek_byte()?;
+        self.input.consume(1);
+        Some(b)
+    }
+
+    // the top and the second value, nothing is popped if there is just one
+    fn pop2(&mut self) -> Option<(i32, i32)> {
+        if self.stack.len() < 2 {
+            return None;
+        }
+        let a = self.stack.pop().unwrap();
+        let b = self.stack.pop().unwrap();
+        Some((a, b))
+    }
+
+    fn pop(&mut self) {
+        self.stack.pop();
+    }
+    fn add(&mut self) {
+        let Some((a, b)) = self.pop2() else { return };
+        self.push(b as i64 + a as i64);
+    }
+    fn sub(&mut self) {
+        let Some((a, b)) = self.pop2() else { return };
+        self.push(b as i64 - a as i64);
+    }
+    fn mult(&mut self) {
+        let Some((a, b)) = self.pop2() else { return };
+        self.push(b as i64 * a as i64);
+    }
+    fn div(&mut self) {
+        let Some((a, b)) = self.pop2() else { return };
+        if a == 0 {
+            self.stack.extend([b, a]);
+            return;
+        }
+        let (a, b) = (a as i64, b as i64);
+        let q = if FLOOR && b % a != 0 && (b < 0) != (a < 0) { b / a - 1 } else { b / a };
+        self.push(q);
+    }
+    fn modulo(&mut self) {
+        let Some((a, b)) = self.pop2() else { return };
+        if a == 0 {
+            self.stack.extend([b, a]);
+            return;
+        }
+        let (a, b) = (a as i64, b as i64);
+        let r = b % a;
+        self.push(if FLOOR && r != 0 && (r < 0) != (a < 0) { r + a } else { r });
+    }
+    fn not(&mut self) {
+        if let Some(a) = self.stack.pop() {
+            self.push((a == 0) as i64);
+        }
+    }
+    fn greater(&mut self) {
+        let Some((a, b)) = self.pop2() else { return };
+        self.push((b > a) as i64);
+    }
+    // the number of clockwise rotations of DP
+    fn pointer(&mut self) -> usize {
+        self.stack.pop().map_or(0, |a| a.rem_euclid(4) as usize)
+    }
+    // whether CC toggles
+    fn switch(&mut self) -> usize {
+        self.stack.pop().map_or(0, |a| a.rem_euclid(2) as usize)
+    }
+    fn dup(&mut self) {
+        if let Some(&a) = self.stack.last() {
+            self.stack.push(a);
+        }
+    }
+    fn roll(&mut self) {
+        let Some((rolls, depth)) = self.pop2() else { return };
+        let len = self.stack.len();
+        if depth <= 0 || depth as usize > len {
+            self.stack.push(depth);
+            self.stack.push(rolls);
+            return;
+        }
+        let n = (rolls as i64).rem_euclid(depth as i64) as usize;
+        self.stack[len - depth as usize..].rotate_right(n);
+    }
+    fn in_num(&mut self) {
+        self.prompt();
+        let mut line = Vec::new();
+        while let Some(b) = self.next_byte() {
+            line.push(b);
+            if b == b'\n' {
+                break;
+            }
+        }
+        if line.is_empty() {
+            return;
+        }
+        let text = String::from_utf8_lossy(&line);
+        let text = text.trim();
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            eprintln!("input not a number");
+            return;
+        }
+        let value = digits
+            .bytes()
+            .fold(0u32, |n, d| n.wrapping_mul(10).wrapping_add((d - b'0') as u32));
+        self.push(if negative { -(value as i64) } else { value as i64 });
+    }
+    fn in_char(&mut self) {
+        self.prompt();
+        let Some(lead) = self.next_byte() else { return };
+        let len = match lead {
+            0x00..=0x7F => 1,
+            0xC2..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF4 => 4,
+            _ => 0,
+        };
+        if len == 0 {
+            self.push(0xFFFD);
+            return;
+        }
+        let mut bytes = vec![lead];
+        while bytes.len() < len {
+            match self.peek_byte() {
+                Some(b) if b & 0xC0 == 0x80 => {
+                    self.next_byte();
+                    bytes.push(b);
+                }
+                _ => {
+                    self.push(0xFFFD);
+                    return;
+                }
+            }
+        }
+        let c = std::str::from_utf8(&bytes).ok().and_then(|s| s.chars().next());
+        self.push(c.map_or(0xFFFD, |c| c as i64));
+    }
+    fn out_num(&mut self) {
+        if let Some(a) = self.stack.pop() {
+            let _ = write!(self.output, "{}", a);
+        }
+    }
+    fn out_char(&mut self) {
+        if let Some(a) = self.stack.pop() {
+            let c = u32::try_from(a).ok().and_then(char::from_u32).unwrap_or('\u{FFFD}');
+            let _ = write!(self.output, "{}", c);
+        }
+    }
+}
+"#;
+
+/// Runtime function of `command` in the generated code.
+fn function(command: Command, language: Language) -> String {
+    match (command, language) {
+        (Command::Mod, Language::Rust) => String::from("modulo"),
+        (_, Language::Rust) => command.name().to_string(),
+        (_, Language::C) => format!("cmd_{}", command.name()),
+    }
+}
+
+/// Transpiles the reachable part of the program into a C or Rust program, running the commands on an
+/// explicit stack and dispatching on the (block, DP, CC) state of the program.
+/// Integers have 32 bits and wrap around, `div` and `mod` round like `division`.
+pub fn transpile(graph: &Graph, language: Language, division: Division) -> String {
+    let states = graph.reachable();
+    let numbers: HashMap<State, usize> = states.iter().enumerate().map(|(i, s)| (*s, i)).collect();
+    let floor = division == Division::Floor;
+
+    let mut code = String::new();
+    match language {
+        Language::C => {
+            code += &format!("#define FLOOR {}\n\n", floor as i32);
+            code += C_RUNTIME;
+            code += "\nint main(void) {\n    int state = 0;\n    for (;;) {\n        switch (state) {\n";
+        }
+        Language::Rust => {
+            code += &format!("#![allow(dead_code)]\n\nconst FLOOR: bool = {};\n\n", floor);
+            code += RUST_RUNTIME;
+            code += "\nfn main() {\n    let mut p = Piet {\n        stack: Vec::new(),\n        input: io::stdin().lock(),\n        output: io::BufWriter::new(io::stdout()),\n    };\n";
+            code += "    let mut state = 0;\n    loop {\n        state = match state {\n";
+        }
+    }
+
+    for (i, state) in states.iter().enumerate() {
+        let node = &graph.nodes[state.node];
+        let comment = format!(
+            "({}, {}) {} dp={:?} cc={:?}",
+            node.codels[0].x,
+            node.codels[0].y,
+            color_name(node.color),
+            state.dp,
+            state.cc
+        );
+        let edge = match graph.edge(*state) {
+            Some(edge) => edge,
+            None => {
+                code += &match language {
+                    Language::C => format!(
+                        "        case {}: /* {} */\n            return 0;\n",
+                        i, comment
+                    ),
+                    Language::Rust => {
+                        format!("            // {}\n            {} => break,\n", comment, i)
+                    }
+                };
+                continue;
+            }
+        };
+        let targets: Vec<String> = edge
+            .targets()
+            .iter()
+            .map(|t| numbers[t].to_string())
+            .collect();
+        let call = function(edge.command, language);
+        let next = match (edge.command, language) {
+            (Command::Pointer | Command::Switch, Language::C) => format!(
+                "state = (const int[]){{{}}}[{}()];",
+                targets.join(", "),
+                call
+            ),
+            (Command::Pointer | Command::Switch, Language::Rust) => {
+                format!("[{}][p.{}()]", targets.join(", "), call)
+            }
+            (_, Language::C) => format!("state = {};", targets[0]),
+            (_, Language::Rust) => targets[0].clone(),
+        };
+        let statement = match (edge.command, language) {
+            (Command::None | Command::Pointer | Command::Switch, _) => String::new(),
+            (Command::Push, Language::C) => format!("push({}); ", edge.size),
+            (Command::Push, Language::Rust) => format!("p.push({}); ", edge.size),
+            (_, Language::C) => format!("{}(); ", call),
+            (_, Language::Rust) => format!("p.{}(); ", call),
+        };
+        code += &match language {
+            Language::C => format!(
+                "        case {}: /* {} */\n            {}{}\n            break;\n",
+                i, comment, statement, next
+            ),
+            Language::Rust => format!(
+                "            // {}\n            {} => {{ {}{} }}\n",
+                comment, i, statement, next
+            ),
+        };
+    }
+
+    code += &match language {
+        Language::C => String::from("        }\n    }\n}\n"),
+        Language::Rust => String::from(
+            "            _ => unreachable!(),\n        };\n    }\n    let _ = p.output.flush();\n}\n",
+        ),
+    };
+    code
+}
@@ -0,0 +1,177 @@
+#[cfg(test)]
+mod tests {
+    use crate::assembler::assemble;
+    use crate::blocks::BlockMap;
+    use crate::machine::*;
+    use crate::piet_io::*;
+    use crate::test_support::load_image;
+    use crate::transpiler::*;
+    use std::io::{Cursor, Write};
+    use std::process::{Command as Process, Stdio};
+
+    fn interpret(
+        rgb_img: Vec<Vec<RGB>>,
+        codel_size: i32,
+        division: Division,
+        input: &str,
+    ) -> Vec<u8> {
+        let out = SharedBuffer::default();
+        let io = Io::new(
+            Box::new(Cursor::new(input.as_bytes().to_vec())),
+            Box::new(out.clone()),
+        );
+        let mut interp = Interpreter::new(io);
+        interp.division = division;
+        let mut machine = Machine::new(rgb_img, codel_size, interp);
+        machine.run(&Limits::default(), |_, _| ()).unwrap();
+        machine.interp.io.flush().unwrap();
+        out.contents()
+    }
+
+    // compiles the transpiled program and runs it with `input`
+    fn compile_and_run(code: &str, language: Language, input: &str) -> Vec<u8> {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("prog");
+        let (source, mut compiler) = match language {
+            Language::C => (dir.path().join("prog.c"), Process::new("cc")),
+            Language::Rust => {
+                let mut rustc = Process::new("rustc");
+                rustc.args(["--edition", "2021"]);
+                (dir.path().join("prog.rs"), rustc)
+            }
+        };
+        std::fs::write(&source, code).unwrap();
+        let compiled = compiler
+            .arg(&source)
+            .arg("-o")
+            .arg(&binary)
+            .output()
+            .unwrap();
+        assert!(
+            compiled.status.success(),
+            "{}",
+            String::from_utf8_lossy(&compiled.stderr)
+        );
+
+        let mut child = Process::new(&binary)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(input.as_bytes())
+            .unwrap();
+        child.wait_with_output().unwrap().stdout
+    }
+
+    fn runs_like_the_interpreter(
+        rgb_img: Vec<Vec<RGB>>,
+        codel_size: i32,
+        division: Division,
+        input: &str,
+    ) {
+        let graph = Graph::compile(&BlockMap::new(&rgb_img, codel_size));
+        let expected = interpret(rgb_img, codel_size, division, input);
+        for language in [Language::C, Language::Rust] {
+            let code = transpile(&graph, language, division);
+            let actual = compile_and_run(&code, language, input);
+            assert_eq!(
+                String::from_utf8_lossy(&actual),
+                String::from_utf8_lossy(&expected),
+                "{:?}",
+                language
+            );
+        }
+    }
+
+    #[test]
+    fn transpiled_programs_print_the_same() {
+        let (img, size) = load_image("tests/fixtures/piet_hello_world.png");
+        runs_like_the_interpreter(img, size, Division::Floor, "");
+        let (img, size) = load_image("tests/fixtures/fizzbuzz.png");
+        runs_like_the_interpreter(img, size, Division::Floor, "");
+    }
+    #[test]
+    fn transpiled_commands_keep_their_semantics() {
+        let source = "
+            in_num
+            in_num
+            div         ; rounds according to the division
+            out_num
+            push 32
+            out_char
+            in_num
+            in_num
+            mod
+            out_num
+            push 32
+            out_char
+            push 10     ; 10^10 wraps around
+            dup
+            mult
+            dup
+            mult
+            dup
+            mult
+            push 100
+            mult
+            out_num
+            in_char     ; a two byte character
+            out_num
+            in_char
+            out_num
+            push -1     ; not a character
+            out_char
+            push 5      ; underflow leaves the 5
+            add
+            out_num
+            push 4
+            push 0
+            div         ; by zero, ignored
+            out_num
+            out_num
+            push 1
+            push 2
+            push 3
+            push 3
+            push 1
+            roll
+            out_num
+            out_num
+            out_num
+            push 3
+            push 2
+            greater
+            out_num
+            in_num      ; not a number
+            push 3
+            push 9
+            roll        ; too deep, ignored
+            out_num
+            out_num
+            out_num
+            in_num      ; end of input
+        ";
+        let input = "-7\n2\n-7\n2\n\u{e9}xabc\n";
+        for division in [Division::Floor, Division::Truncate] {
+            runs_like_the_interpreter(assemble(source).unwrap(), 1, division, input);
+        }
+    }
+    #[test]
+    fn dispatches_on_states() {
+        let (img, size) = load_image("tests/fixtures/piet_hello_world.png");
+        let graph = Graph::compile(&BlockMap::new(&img, size));
+        let c = transpile(&graph, Language::C, Division::Floor);
+        assert!(c.contains(
+            "        case 0: /* (0, 0) red dp=RIGHT cc=LEFT */\n            push(72); state = 1;\n"
+        ));
+        let rust = transpile(&graph, Language::Rust, Division::Floor);
+        assert!(rust.contains(
+            "            // (0, 0) red dp=RIGHT cc=LEFT\n            0 => { p.push(72); 1 }\n"
+        ));
+    }
+}
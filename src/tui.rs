@@ -0,0 +1,257 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::process::{Command as Process, Stdio};
+
+use crate::debugger::*;
+use crate::navigation::exit_codel;
+use crate::piet_io::SharedBuffer;
+use crate::types::*;
+
+mod tests;
+
+const ENTER_SCREEN: &str = "\x1b[?1049h";
+const LEAVE_SCREEN: &str = "\x1b[?1049l";
+const CLEAR: &str = "\x1b[H\x1b[2J";
+const RESET: &str = "\x1b[0m";
+
+// columns of the panel right of the program showing the machine state
+const SIDE_WIDTH: usize = 24;
+// lines of program output shown below the program
+const OUTPUT_LINES: usize = 5;
+
+/// Full-screen view of a debugging session: the program with the current block
+/// highlighted, the machine state, the stack and the output of the program.
+/// It is controlled with the debugger commands typed in at the bottom.
+pub struct Tui {
+    pub debugger: Debugger,
+    /// Where the program writes to, shown in the output pane.
+    output: SharedBuffer,
+    /// What the last debugger command printed.
+    message: String,
+    pub width: usize,
+    pub height: usize,
+    // codel shown in the top left corner, moves to keep the current block visible
+    origin: Coordinates,
+}
+
+/// Size of the terminal as (columns, lines), 80x24 if it can't be found out.
+pub fn terminal_size() -> (usize, usize) {
+    let from_env = |name| std::env::var(name).ok().and_then(|v| v.parse().ok());
+    if let (Some(w), Some(h)) = (from_env("COLUMNS"), from_env("LINES")) {
+        return (w, h);
+    }
+    let stty = File::open("/dev/tty").ok().and_then(|tty| {
+        Process::new("stty")
+            .arg("size")
+            .stdin(tty)
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+    });
+    if let Some(out) = stty {
+        let size: Vec<usize> = String::from_utf8_lossy(&out.stdout)
+            .split_whitespace()
+            .filter_map(|n| n.parse().ok())
+            .collect();
+        if let [h, w] = size[..] {
+            return (w, h);
+        }
+    }
+    (80, 24)
+}
+
+fn arrow(dp: Direction) -> char {
+    match dp {
+        Direction::RIGHT => '→',
+        Direction::DOWN => '↓',
+        Direction::LEFT => '←',
+        Direction::UP => '↑',
+    }
+}
+
+// pads or cuts `text` to exactly `width` characters
+fn fit(text: &str, width: usize) -> String {
+    let mut line: String = text.chars().take(width).collect();
+    let len = line.chars().count();
+    line.extend(std::iter::repeat_n(' ', width - len));
+    line
+}
+
+impl Tui {
+    pub fn new(debugger: Debugger, output: SharedBuffer) -> Tui {
+        Tui {
+            debugger,
+            output,
+            message: String::from("type `help` for a list of commands"),
+            width: 80,
+            height: 24,
+            origin: Coordinates { x: 0, y: 0 },
+        }
+    }
+
+    /// Redraws the screen after every command until `quit` or the end of the input.
+    pub fn run(&mut self) {
+        print!("{}", ENTER_SCREEN);
+        loop {
+            let (width, height) = terminal_size();
+            self.width = width;
+            self.height = height;
+            print!("{}{}(pint) ", CLEAR, self.render());
+            let _ = io::stdout().flush();
+
+            let line = match self.debugger.machine.interp.io.read_line() {
+                Ok(Some(line)) => line,
+                _ => break,
+            };
+            match self.debugger.execute_line(&line) {
+                Some(message) => self.message = message,
+                None => break,
+            }
+        }
+        print!("{}", LEAVE_SCREEN);
+        let _ = io::stdout().flush();
+        // the output would be lost with the screen otherwise
+        print!("{}", String::from_utf8_lossy(&self.output.contents()));
+    }
+
+    /// The whole screen except the prompt, one codel per character cell.
+    pub fn render(&mut self) -> String {
+        let message: Vec<String> = self.message.lines().map(String::from).collect();
+        let message = &message[..message.len().min(self.height / 3)];
+        let grid_height = self
+            .height
+            .saturating_sub(OUTPUT_LINES + 1 + message.len() + 1)
+            .max(1);
+        let grid_width = self.width.saturating_sub(SIDE_WIDTH + 1).max(1);
+
+        let grid = self.render_grid(grid_width, grid_height);
+        let side = self.side_panel();
+        let mut screen = String::new();
+        for (i, row) in grid.iter().enumerate() {
+            let side = side.get(i).map(String::as_str).unwrap_or("");
+            screen.push_str(&format!("{}{} {}\n", row, RESET, fit(side, SIDE_WIDTH)));
+        }
+
+        screen.push_str(&format!("{}\n", fit("── output ", self.width)));
+        let output = String::from_utf8_lossy(&self.output.contents()).into_owned();
+        let lines: Vec<&str> = output.lines().collect();
+        let shown = &lines[lines.len().saturating_sub(OUTPUT_LINES)..];
+        for i in 0..OUTPUT_LINES {
+            let line = shown.get(i).copied().unwrap_or("");
+            screen.push_str(&format!("{}\n", fit(line, self.width)));
+        }
+        for line in message {
+            let line = line.as_str();
+            screen.push_str(&format!("{}\n", fit(line, self.width)));
+        }
+        screen
+    }
+
+    // keeps the current codel inside the visible part of the program
+    fn scroll(&mut self, width: i32, height: i32, codels: Coordinates) {
+        let current = self.debugger.machine.codel();
+        let follow = |origin: i32, pos: i32, size: i32, total: i32| {
+            if pos >= origin && pos < origin + size {
+                origin
+            } else {
+                (pos - size / 2).min(total - size).max(0)
+            }
+        };
+        self.origin = Coordinates {
+            x: follow(self.origin.x, current.x, width, codels.x),
+            y: follow(self.origin.y, current.y, height, codels.y),
+        };
+    }
+
+    fn render_grid(&mut self, width: usize, height: usize) -> Vec<String> {
+        let machine = &self.debugger.machine;
+        let size = machine.codel_size;
+        let codels = Coordinates {
+            x: machine.rgb_img[0].len() as i32 / size,
+            y: machine.rgb_img.len() as i32 / size,
+        };
+        self.scroll(width as i32, height as i32, codels);
+
+        let machine = &self.debugger.machine;
+        let block: Vec<Coordinates> = if self.debugger.finished {
+            Vec::new()
+        } else {
+            machine.current_block()
+        };
+        let exit = if block.is_empty() {
+            None
+        } else {
+            Some(exit_codel(&machine.interp.dp, &machine.interp.cc, &block))
+        };
+
+        let rows = (codels.y - self.origin.y).min(height as i32);
+        let cols = (codels.x - self.origin.x).min(width as i32);
+        (0..rows)
+            .map(|row| {
+                let mut line = String::new();
+                for col in 0..cols {
+                    let pixel = Coordinates {
+                        x: (self.origin.x + col) * size,
+                        y: (self.origin.y + row) * size,
+                    };
+                    let RGB(r, g, b) = machine.rgb_img[pixel.y as usize][pixel.x as usize];
+                    // dark text on light colors and the other way round
+                    let fg = if (r as u32 * 3 + g as u32 * 6 + b as u32) / 10 > 128 {
+                        "0;0;0"
+                    } else {
+                        "255;255;255"
+                    };
+                    let symbol = if Some(pixel) == exit {
+                        arrow(machine.interp.dp)
+                    } else if pixel == machine.pos && !block.is_empty() {
+                        '@'
+                    } else if block.contains(&pixel) {
+                        '•'
+                    } else {
+                        ' '
+                    };
+                    line.push_str(&format!(
+                        "\x1b[48;2;{};{};{}m\x1b[38;2;{}m{}",
+                        r, g, b, fg, symbol
+                    ));
+                }
+                line.push_str(RESET);
+                line.push_str(&" ".repeat(width - cols as usize));
+                line
+            })
+            .collect()
+    }
+
+    fn side_panel(&self) -> Vec<String> {
+        let machine = &self.debugger.machine;
+        let codel = machine.codel();
+        let next = match machine.peek() {
+            Some(_) if self.debugger.finished => "-",
+            Some((_, command)) => command.name(),
+            None => "terminate",
+        };
+        let mut lines = vec![
+            format!("step  {}", machine.steps),
+            format!("pos   ({}, {})", codel.x, codel.y),
+            format!("dp    {:?} {}", machine.interp.dp, arrow(machine.interp.dp)),
+            format!("cc    {:?}", machine.interp.cc),
+            format!("next  {}", next),
+            String::new(),
+            String::from("stack"),
+        ];
+        let stack = &machine.interp.stack;
+        // the lines left for the stack, the top of it comes first
+        let room = self
+            .height
+            .saturating_sub(OUTPUT_LINES + 2 + lines.len())
+            .max(1);
+        for (i, n) in stack.iter().rev().enumerate() {
+            if i + 1 == room && stack.len() > room {
+                lines.push(format!("  ... {} more", stack.len() - i));
+                break;
+            }
+            lines.push(format!("  {}", n));
+        }
+        lines
+    }
+}
@@ -0,0 +1,76 @@
+#[cfg(test)]
+mod tests {
+    use crate::debugger::Debugger;
+    use crate::test_support::{load, HELLO_WORLD};
+    use crate::tui::*;
+
+    fn hello_world() -> Tui {
+        let (machine, out) = load(HELLO_WORLD);
+        let mut tui = Tui::new(Debugger::new(machine), out);
+        tui.width = 80;
+        tui.height = 40;
+        tui
+    }
+
+    #[test]
+    fn one_cell_per_codel() {
+        let mut tui = hello_world();
+        let screen = tui.render();
+        let first = screen.lines().next().unwrap();
+
+        let codels = tui.debugger.machine.rgb_img[0].len() / 5;
+        assert_eq!(first.matches("\x1b[48;2;").count(), codels);
+        // the first block is red
+        assert!(first.starts_with("\x1b[48;2;255;0;0m\x1b[38;2;255;255;255m@"));
+        assert!(screen.contains("step  0"));
+        assert!(screen.contains("dp    RIGHT →"));
+    }
+    #[test]
+    fn highlights_current_block() {
+        let mut tui = hello_world();
+        let screen = tui.render();
+        // entered at the top left, leaving to the right
+        assert!(screen.lines().next().unwrap().contains("@"));
+        assert_eq!(screen.matches('→').count(), 2);
+
+        tui.debugger.execute_line("step");
+        let screen = tui.render();
+        assert!(screen.contains("step  1"));
+        assert!(!screen.lines().next().unwrap().contains("@\x1b"));
+    }
+    #[test]
+    fn shows_stack_and_output() {
+        let mut tui = hello_world();
+        tui.debugger.execute_line("stack 1 2 3");
+        let screen = tui.render();
+        let stack: Vec<&str> = screen
+            .lines()
+            .filter_map(|l| l.rsplit("\x1b[0m").next())
+            .map(str::trim)
+            .skip_while(|l| *l != "stack")
+            .take(4)
+            .collect();
+        assert_eq!(stack, ["stack", "3", "2", "1"]);
+
+        tui.debugger.execute_line("stack");
+        tui.debugger.execute_line("continue");
+        assert!(tui.render().contains("Hello world!"));
+    }
+    #[test]
+    fn follows_the_current_codel() {
+        let mut tui = hello_world();
+        // leaves room for 4 codels next to the side panel
+        tui.width = 29;
+        tui.debugger.execute_line("step 8");
+        let codel = tui.debugger.machine.codel();
+        assert!(codel.x >= 4);
+
+        let screen = tui.render();
+        let grid: String = screen
+            .lines()
+            .take_while(|l| !l.starts_with("── output"))
+            .map(|l| l.split("\x1b[0m").next().unwrap())
+            .collect();
+        assert!(grid.contains('@') || grid.contains(['→', '↓', '←', '↑']));
+    }
+}
@@ -1,4 +1,6 @@
-#[derive(Copy, Clone, Debug)]
+use std::str::FromStr;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Direction {
     // maybe as hashmap
     RIGHT,
@@ -7,7 +9,7 @@ pub enum Direction {
     UP,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct ColorInfo {
     pub color: RGB,
     pub size: i32,
@@ -32,18 +34,44 @@ impl Direction {
     }
 }
 
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Direction, String> {
+        match s.to_lowercase().as_str() {
+            "right" | "r" => Ok(Direction::RIGHT),
+            "down" | "d" => Ok(Direction::DOWN),
+            "left" | "l" => Ok(Direction::LEFT),
+            "up" | "u" => Ok(Direction::UP),
+            _ => Err(format!("{} is not a direction", s)),
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, Copy, Clone, Debug)]
 pub struct Coordinates {
     pub x: i32,
     pub y: i32,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum CodelChooser {
     LEFT,
     RIGHT,
 }
 
+impl FromStr for CodelChooser {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<CodelChooser, String> {
+        match s.to_lowercase().as_str() {
+            "left" | "l" => Ok(CodelChooser::LEFT),
+            "right" | "r" => Ok(CodelChooser::RIGHT),
+            _ => Err(format!("{} is not a codel chooser", s)),
+        }
+    }
+}
+
 impl CodelChooser {
     pub fn toggle(&self) -> CodelChooser {
         match self {
@@ -53,5 +81,8 @@ impl CodelChooser {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct RGB(pub u8, pub u8, pub u8);
+
+pub const WHITE: RGB = RGB(255, 255, 255);
+pub const BLACK: RGB = RGB(0, 0, 0);
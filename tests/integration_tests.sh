@@ -1,17 +1,19 @@
 #!/bin/bash
 
 function assert_eq {
-  if [ $# -eq 3 ]; then
+  if [ $# -ge 3 ]; then
     local snapshot=$1
     local fixture=$2
     # local cs=$3 # codelsize
     local name=$3
+    # everything after the name is passed on to pint
+    local args=("${@:4}")
   else
-    echo "assert_eq accepts 3 arguments"
+    echo "assert_eq accepts at least 3 arguments"
     exit 1
   fi
 
-  $(cargo r -q --release tests/fixtures/"$fixture" >& tmp)
+  $(cargo r -q --release tests/fixtures/"$fixture" "${args[@]}" >& tmp)
   result=$(diff tests/snapshots/"$snapshot" tmp 2> err)
   error=$(cat err)
 
@@ -34,5 +36,18 @@ assert_eq "success_fizzbuzz" "fizzbuzz.png" "fizzbuzz"
 assert_eq "success_indexed_hello_world" "artsy_hello_world.png" "indexed_hello_world"
 assert_eq "success_bottles" "99bottles.png" "99_bottles_indexed_switch"
 
+# codel grid in a text file
+assert_eq "success_fizzbuzz" "fizzbuzz.txt" "fizzbuzz_text"
+
 # inferred codel-size
 assert_eq "success_pi" "pi_big.png" "correct_codel_pi"
+
+# execution limits
+assert_eq "limit_steps_bottles" "99bottles.png" "max_steps_summary" --max-steps 50
+
+# the compiled graph has to behave like the image
+assert_eq "success_bottles" "99bottles.png" "99_bottles_compiled" --compile
+assert_eq "success_pi" "pi_big.png" "pi_compiled" --compile
+
+# options that don't go together
+assert_eq "failure-overflow-with-big-ints" "piet_hello_world.png" "overflow_with_big_ints" --int big --overflow wrap